@@ -1,6 +1,5 @@
 use godot_enet::{self as gd_enet, DataPile, ENetPeerID};
 use log::info;
-use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
@@ -21,11 +20,7 @@ async fn main() {
 
     server.open().unwrap();
 
-    loop {
-        server.service().await.unwrap();
-
-        std::thread::sleep(Duration::from_millis(10));
-    }
+    server.run().await.unwrap();
 }
 
 async fn auto_authenticate(peer: ENetPeerID, content: Box<[u8]>, _data_pile: DataPile) -> bool {
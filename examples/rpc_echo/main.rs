@@ -1,6 +1,11 @@
 use godot_enet::{
     self as gd_enet, AsyncLayer, ENetPeerID, GDPeerID, LayerResult, fn_layer_err, name_id,
-    packet::{Packet, outgoing, rpc::smart_send_packet},
+    packet::{
+        Packet,
+        channel::{GodotChannel, TransferMode},
+        outgoing,
+        rpc::{RPCSendOptions, RPCTarget, smart_send_packet},
+    },
     sort_names,
 };
 use std::{sync::Arc, time::Duration, vec};
@@ -31,6 +36,7 @@ async fn main() {
     router.register_path("NetworkButtons".to_string(), Arc::new(function_router));
 
     builder = builder
+        .godot_server(32, 0, 0, 0)
         .layer(gd_enet::layers::AutoParseLayer)
         .layer(gd_enet::layers::PeerMapLayer::default())
         .layer(path_cache_layer)
@@ -41,11 +47,9 @@ async fn main() {
 
     server.open().unwrap();
 
-    loop {
-        server.service().await.unwrap();
+    server.run_until(tokio::signal::ctrl_c()).await.unwrap();
 
-        std::thread::sleep(Duration::from_millis(10));
-    }
+    server.shutdown(0, Duration::from_secs(3)).await.unwrap();
 }
 
 async fn echo(event: gd_enet::event::Event) -> LayerResult {
@@ -64,7 +68,7 @@ async fn echo(event: gd_enet::event::Event) -> LayerResult {
                 packet: outgoing::Packet::reliable(raw_packet.data()),
             };
 
-            if let Err(e) = event.tx_outgoing.send(outgoing_packet) {
+            if let Err(e) = event.tx_outgoing.send(outgoing_packet.into()) {
                 return Err(fn_layer_err!(
                     "Echo",
                     "Failed to transmit outgoing packet: {:?}",
@@ -90,17 +94,25 @@ async fn echo(event: gd_enet::event::Event) -> LayerResult {
                 log::info!("Arg {}: {:?}", i, arg);
             }
 
-            let godot_enet::event::EventType::Receive { raw_packet, .. } = &event.event else {
+            let godot_enet::event::EventType::Receive {
+                channel,
+                transfer_mode,
+                raw_packet,
+            } = &event.event
+            else {
                 return Err(fn_layer_err!("Echo", "Expected Receive event type",));
             };
 
-            let outgoing_packet = outgoing::OutgoingPacket {
-                peer_id: ENetPeerID(0),
-                channel_id: 0,
-                packet: outgoing::Packet::reliable(raw_packet.data()),
-            };
+            // Echo on the channel of the @rpc annotation, and with its mode when it is known
+            let outgoing_packet = outgoing::OutgoingPacket::new(
+                ENetPeerID(0),
+                *channel,
+                transfer_mode.unwrap_or(TransferMode::Reliable),
+                raw_packet.data(),
+            )
+            .map_err(|e| fn_layer_err!("Echo", "Failed to build outgoing packet: {}", e))?;
 
-            if let Err(e) = event.tx_outgoing.send(outgoing_packet) {
+            if let Err(e) = event.tx_outgoing.send(outgoing_packet.into()) {
                 return Err(fn_layer_err!(
                     "Echo",
                     "Failed to transmit outgoing packet: {:?}",
@@ -166,10 +178,16 @@ async fn send_abc(event: gd_enet::event::Event) -> LayerResult {
         gd_enet::routers::hash_function_set(&["rpc_testing".to_string(), "abc".to_string()]),
         args,
         name_id!("abc", NAMES),
-        outgoing_cache,
-        &event.tx_outgoing,
-        gd_peer_id,
-        enet_peer_id,
+        RPCTarget {
+            outgoing_cache,
+            tx_outgoing: &event.tx_outgoing,
+            gd_peer: gd_peer_id,
+            enet_peer: enet_peer_id,
+        },
+        RPCSendOptions {
+            channel: GodotChannel::DEFAULT,
+            transfer_mode: TransferMode::Reliable,
+        },
     ) {
         return Err(fn_layer_err!(
             "SendABC",
@@ -2,7 +2,6 @@ use godot_enet::{
     self as gd_enet, AsyncLayer, ENetPeerID, LayerResult, fn_layer_err,
     packet::{Packet, outgoing},
 };
-use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
@@ -23,11 +22,7 @@ async fn main() {
 
     server.open().unwrap();
 
-    loop {
-        server.service().await.unwrap();
-
-        std::thread::sleep(Duration::from_millis(10));
-    }
+    server.run().await.unwrap();
 }
 
 async fn testing(event: gd_enet::event::Event) -> LayerResult {
@@ -44,7 +39,7 @@ async fn testing(event: gd_enet::event::Event) -> LayerResult {
                     packet: outgoing::Packet::reliable((*content).clone()),
                 };
 
-                if let Err(e) = event.tx_outgoing.send(outgoing_packet) {
+                if let Err(e) = event.tx_outgoing.send(outgoing_packet.into()) {
                     return Err(fn_layer_err!(
                         "Testing",
                         "Failed to transmit outgoing packet: {:?}",
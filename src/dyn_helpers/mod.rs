@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 
 /// An object safe version of [`Eq`]. This trait is automatically implemented
@@ -39,3 +40,22 @@ where
         self.type_id().hash(&mut state);
     }
 }
+
+/// An object safe version of [`Ord`]. This trait is automatically implemented
+/// for any `'static` type that implements `Ord`.
+pub trait DynOrd: DynEq {
+    /// Compares `self` and `other`, ordering values of different types by their type.
+    fn dyn_cmp(&self, other: &dyn DynOrd) -> Ordering;
+}
+
+impl<T> DynOrd for T
+where
+    T: DynEq + Ord,
+{
+    fn dyn_cmp(&self, other: &dyn DynOrd) -> Ordering {
+        if let Some(other) = (other as &dyn Any).downcast_ref::<T>() {
+            return self.cmp(other);
+        }
+        self.type_id().cmp(&(other as &dyn Any).type_id())
+    }
+}
@@ -1,6 +1,12 @@
-use crate::{ENetPeerID, packet::outgoing::OutgoingPacket};
+use crate::{
+    ENetPeerID,
+    packet::{
+        channel::{GodotChannel, TransferMode},
+        outgoing::OutgoingCommand,
+    },
+};
 use rusty_enet as enet;
-use std::sync::mpsc;
+use tokio::sync::mpsc;
 
 #[derive(Clone, Debug)]
 pub struct Event {
@@ -10,7 +16,7 @@ pub struct Event {
 
     pub data_pile: super::DataPile,
 
-    pub tx_outgoing: mpsc::Sender<OutgoingPacket>,
+    pub tx_outgoing: mpsc::UnboundedSender<OutgoingCommand>,
 }
 
 #[derive(Clone, Debug)]
@@ -22,7 +28,10 @@ pub enum EventType {
         godot_peer: super::GDPeerID,
     },
     Receive {
-        channel_id: u8,
+        /// The Godot channel, not the ENet channel the packet arrived on.
+        channel: GodotChannel,
+        /// Unknown on the user channels, see [`TransferMode::received`].
+        transfer_mode: Option<TransferMode>,
         raw_packet: enet::Packet,
     },
 }
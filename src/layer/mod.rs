@@ -27,15 +27,33 @@ pub struct LayerError {
     message: String,
 
     layer: String,
+
+    source: Option<Box<dyn StdError + Send + Sync>>,
 }
 
 impl LayerError {
     pub fn new(message: String, layer: String) -> Self {
-        Self { message, layer }
+        Self {
+            message,
+            layer,
+            source: None,
+        }
+    }
+
+    /// Attach the error which caused this one
+    pub fn with_source(mut self, source: impl StdError + Send + Sync + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
     }
 }
 
-impl StdError for LayerError {}
+impl StdError for LayerError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn StdError + 'static))
+    }
+}
 
 impl Display for LayerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
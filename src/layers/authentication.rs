@@ -2,178 +2,457 @@ use crate::{
     DataPile, ENetPeerID, Layer, LayerReturn,
     event::{Event, EventType},
     layer_err,
+    layers::{AuthStep, AuthenticationError, Authenticator, FnAuthenticator},
     packet::{
-        Packet, gen_packet, outgoing,
+        Packet,
+        channel::{GodotChannel, TransferMode},
+        gen_packet, outgoing,
         sys::{SysAuthCommand, SysCommand, SysCommandPacket},
     },
 };
 use dashmap::DashMap;
-use std::sync::{Arc, mpsc::Sender};
+use log::{info, warn};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+// From scene_multiplayer.h
+const DEFAULT_AUTH_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Authentication state of a peer, replicating the pending peers of `SceneMultiplayer`
+pub enum AuthState {
+    /// Neither side has completed authentication.
+    Pending,
+    /// The server accepted the peer, waiting for the client's `CompleteNotification`.
+    ServerAccepted,
+    /// The client sent its `CompleteNotification`, waiting for the server to accept the peer.
+    ClientCompleted,
+    /// Both sides completed authentication, the peer is connected.
+    Authenticated,
+    /// The peer was rejected or timed out, and is being disconnected.
+    Failed,
+}
+
+struct PeerAuth {
+    state: AuthState,
+
+    /// Distinguishes connections reusing the same ENet peer.
+    session: u64,
+
+    /// Held back until both sides completed authentication.
+    connect_event: Option<Event>,
+
+    /// Added to every event from the peer once authenticated.
+    peer_data: DataPile,
+}
+
+#[derive(Default, Clone)]
+/// The authentication state of every connected peer
+pub struct AuthCache {
+    peers: Arc<DashMap<ENetPeerID, PeerAuth>>,
+
+    last_session: Arc<AtomicU64>,
+}
+
+impl AuthCache {
+    /// Get the authentication state of a peer
+    pub fn get_state(&self, peer: &ENetPeerID) -> Option<AuthState> {
+        self.peers.get(peer).map(|entry| entry.state)
+    }
+
+    /// Whether both sides completed authentication for a peer
+    pub fn is_authenticated(&self, peer: &ENetPeerID) -> bool {
+        self.get_state(peer) == Some(AuthState::Authenticated)
+    }
+
+    /// Moves the peer to its next state, returning the held back connect event once authenticated
+    fn advance(&self, peer: &ENetPeerID, completed: AuthState) -> Option<Event> {
+        let mut entry = self.peers.get_mut(peer)?;
+
+        entry.state = match (entry.state, completed) {
+            (AuthState::Pending, state) => state,
+            (AuthState::ServerAccepted, AuthState::ClientCompleted)
+            | (AuthState::ClientCompleted, AuthState::ServerAccepted) => AuthState::Authenticated,
+            (state, _) => state,
+        };
+
+        if entry.state == AuthState::Authenticated {
+            let peer_data = entry.peer_data.clone();
+
+            entry.connect_event.take().map(|mut event| {
+                event.data_pile.extend(peer_data);
+                event
+            })
+        } else {
+            None
+        }
+    }
+}
 
 /// A [`Layer`](crate::Layer) which automatically processes authentication.
 ///
 /// Depends on [`AutoParseLayer`](crate::layers::AutoParseLayer).
 ///
+/// Like `SceneMultiplayer`, connect events are held back until both the server accepted the peer
+/// and the client sent its `CompleteNotification`, and peers which do not complete in time are disconnected.
+/// Disconnect events are only passed on for peers which were authenticated.
+/// Packets from peers without authentication state are dropped and the peers disconnected,
+/// so connects must be processed before the peer's packets, as with [`DispatchMode::Ordered`](crate::server::DispatchMode).
+///
 /// Should run before any layers which require authentication,
 /// which should be all others in most cases.
-pub struct AuthenticationLayer<F>
-where
-    F: Future<Output = bool> + Sync + Send + 'static,
-{
-    pub authentication_callback: fn(ENetPeerID, Box<[u8]>, DataPile) -> F,
+pub struct AuthenticationLayer {
+    authenticator: Arc<dyn Authenticator>,
 
-    cache: Arc<DashMap<ENetPeerID, bool>>,
+    cache: AuthCache,
 
     /// Whether to protect unauthed peers by blocking their packets automatically.
     pub protected: bool,
 
-    /// Whether to automatically send blank (\[0x00\]) authentication packets.
+    /// Whether to automatically send a blank (\[0x00\]) authentication packet
+    /// when accepting a peer without sending any message.
     ///
-    /// If false, your authentication_callback must send an authentication packet
-    /// with some content if returning true.
+    /// Godot clients only complete authentication after receiving a message,
+    /// so if false, your authenticator must send one before or while accepting.
     pub auto_send_auth: bool,
+
+    /// How long peers have to complete authentication before being disconnected,
+    /// mirrors `SceneMultiplayer.auth_timeout`.
+    pub timeout: Option<Duration>,
 }
 
-impl<F> AuthenticationLayer<F>
-where
-    F: Future<Output = bool> + Sync + Send + 'static,
-{
-    pub fn build(authentication_callback: fn(ENetPeerID, Box<[u8]>, DataPile) -> F) -> Self {
+impl AuthenticationLayer {
+    /// Build an authentication layer which accepts or rejects peers from their first message
+    pub fn build<F>(authentication_callback: fn(ENetPeerID, Box<[u8]>, DataPile) -> F) -> Self
+    where
+        F: Future<Output = bool> + Sync + Send + 'static,
+    {
+        Self::with_authenticator(FnAuthenticator::build(authentication_callback))
+    }
+
+    /// Build an authentication layer which runs an [`Authenticator`]
+    pub fn with_authenticator(authenticator: impl Authenticator) -> Self {
         Self {
-            authentication_callback,
+            authenticator: Arc::new(authenticator),
 
-            cache: Arc::new(DashMap::new()),
+            cache: AuthCache::default(),
 
             protected: true,
             auto_send_auth: true,
+            timeout: Some(DEFAULT_AUTH_TIMEOUT),
         }
     }
+
+    /// Obtain a handle to the layer's [`AuthCache`]
+    pub fn cache(&self) -> AuthCache {
+        self.cache.clone()
+    }
 }
 
-impl<F> Layer for AuthenticationLayer<F>
-where
-    F: Future<Output = bool> + Sync + Send + 'static,
-{
+impl Layer for AuthenticationLayer {
     fn call(&self, mut event: Event) -> LayerReturn {
+        let authenticator = self.authenticator.clone();
         let cache = self.cache.clone();
-        let protected = self.protected.clone();
-        let auto_send_auth = self.auto_send_auth.clone();
-        let authentication_callback = self.authentication_callback;
+        let protected = self.protected;
+        let auto_send_auth = self.auto_send_auth;
+        let timeout = self.timeout;
 
         return Box::pin(async move {
-            if let EventType::Disconnect { .. } = event.event {
-                // Clean up cache on disconnect
-                if let Some(_) = cache.remove(&event.peer_id) {
-                    log::info!(
+            event.data_pile.insert(cache.clone());
+
+            match event.event {
+                EventType::Connect { .. } => {
+                    let session = cache.last_session.fetch_add(1, Ordering::Relaxed) + 1;
+
+                    cache.peers.insert(
+                        event.peer_id,
+                        PeerAuth {
+                            state: AuthState::Pending,
+                            session,
+                            connect_event: Some(event.clone()),
+                            peer_data: DataPile::new(),
+                        },
+                    );
+
+                    if let Some(timeout) = timeout {
+                        tokio::spawn(disconnect_on_timeout(
+                            cache.clone(),
+                            event.peer_id,
+                            session,
+                            timeout,
+                            event.tx_outgoing.clone(),
+                        ));
+                    }
+
+                    let step = authenticator
+                        .start(event.peer_id, event.data_pile.clone())
+                        .await;
+
+                    return apply_step(&cache, &event, step, auto_send_auth)
+                        .map_err(|e| layer_err!("{}", e).with_source(e));
+                }
+                EventType::Disconnect { .. } => {
+                    // Clean up cache on disconnect
+                    let Some((_, peer_auth)) = cache.peers.remove(&event.peer_id) else {
+                        return Ok(Some(event));
+                    };
+
+                    authenticator.end(event.peer_id);
+
+                    info!(
                         "Peer {:?} disconnected, removed from authentication cache",
                         event.peer_id
                     );
-                }
 
-                event.data_pile.insert(cache);
+                    // Like SceneMultiplayer, only peers which were connected are disconnected
+                    return if peer_auth.state == AuthState::Authenticated {
+                        event.data_pile.extend(peer_auth.peer_data);
 
-                return Ok(Some(event));
-            };
+                        Ok(Some(event))
+                    } else {
+                        Ok(None)
+                    };
+                }
+                EventType::Receive { .. } => {}
+            }
+
+            let Some(state) = cache.get_state(&event.peer_id) else {
+                // Fail closed, peers are only known once their connect was processed
+                warn!(
+                    "Peer {:?} sent a packet without authentication state",
+                    event.peer_id
+                );
 
-            let EventType::Receive { .. } = event.event else {
-                event.data_pile.insert(cache);
+                send_disconnect(event.peer_id, event.tx_outgoing.clone()).map_err(|e| {
+                    layer_err!("Failed to disconnect unknown peer: {}", e).with_source(e)
+                })?;
 
-                return Ok(Some(event));
+                return Ok(None);
             };
 
+            if state == AuthState::Authenticated
+                && let Some(peer_auth) = cache.peers.get(&event.peer_id)
+            {
+                event.data_pile.extend(peer_auth.peer_data.clone());
+            }
+
             let Some(parsed_packet) = event.data_pile.get::<Packet>() else {
                 return Err(layer_err!(
                     "Ran without parsed packet, requires AutoParseLayer".to_string()
                 ));
             };
 
-            let Packet::NetworkCommandSys(sys_packet) = parsed_packet else {
-                event.data_pile.insert(cache.clone());
-
-                return if protected && cache.get(&event.peer_id).is_some_and(|v| !*v) {
+            let Packet::NetworkCommandSys(SysCommandPacket {
+                sys_cmd: SysCommand::SysCommandAuth(auth_cmd),
+            }) = parsed_packet
+            else {
+                return if state == AuthState::Failed
+                    || (protected && state != AuthState::Authenticated)
+                {
                     Ok(None)
                 } else {
                     Ok(Some(event))
                 };
             };
 
-            let SysCommand::SysCommandAuth(auth_cmd) = &sys_packet.sys_cmd else {
-                event.data_pile.insert(cache.clone());
+            // Replicates SceneMultiplayer::_process_sys for SYS_COMMAND_AUTH on the server
+            match (state, auth_cmd) {
+                (AuthState::Authenticated | AuthState::Failed, _) => {
+                    warn!(
+                        "Peer {:?} sent an authentication packet while {:?}",
+                        event.peer_id, state
+                    );
 
-                return if protected && cache.get(&event.peer_id).is_some_and(|v| !*v) {
-                    Ok(None)
-                } else {
-                    Ok(Some(event))
-                };
-            };
+                    return Ok(None);
+                }
+                (_, SysAuthCommand::CompleteNotification) => {
+                    return Ok(cache.advance(&event.peer_id, AuthState::ClientCompleted));
+                }
+                (AuthState::ServerAccepted, SysAuthCommand::AuthMessage(_)) => {
+                    return Ok(None);
+                }
+                (_, SysAuthCommand::AuthMessage(auth_data)) => {
+                    let step = authenticator
+                        .message(event.peer_id, auth_data.clone(), event.data_pile.clone())
+                        .await;
 
-            let SysAuthCommand::AuthMessage(auth_data) = auth_cmd else {
-                event.data_pile.insert(cache.clone());
+                    return apply_step(&cache, &event, step, auto_send_auth)
+                        .map_err(|e| layer_err!("{}", e).with_source(e));
+                }
+            }
+        });
+    }
+}
 
-                return if protected && cache.get(&event.peer_id).is_some_and(|v| !*v) {
-                    Ok(None)
-                } else {
-                    Ok(Some(event))
-                };
-            };
+/// Sends the messages of an authentication step and advances the peer's state,
+/// returning the held back connect event once both sides completed.
+fn apply_step(
+    cache: &AuthCache,
+    event: &Event,
+    step: AuthStep,
+    auto_send_auth: bool,
+) -> Result<Option<Event>, AuthenticationError> {
+    // The peer may have timed out or disconnected while authenticating
+    if cache
+        .get_state(&event.peer_id)
+        .is_none_or(|state| state == AuthState::Failed)
+    {
+        return Ok(None);
+    }
 
-            let authenticated = authentication_callback(
-                event.peer_id.clone(),
-                auth_data.clone(),
-                event.data_pile.clone(),
-            )
-            .await;
-            cache.insert(event.peer_id.clone(), authenticated);
+    match step {
+        AuthStep::Continue { messages } => {
+            for message in messages {
+                send_authentication_packet(event.peer_id, event.tx_outgoing.clone(), message)?;
+            }
 
-            if authenticated {
-                if auto_send_auth {
-                    send_authentication_packet(event.peer_id.clone(), event.tx_outgoing.clone())
-                        .map_err(|e| layer_err!("Failed to send authentication packet: {:?}", e))?;
-                }
+            Ok(None)
+        }
+        AuthStep::Accept {
+            messages,
+            peer_data,
+        } => {
+            if messages.is_empty() && auto_send_auth {
+                send_authentication_packet(
+                    event.peer_id,
+                    event.tx_outgoing.clone(),
+                    Box::new([0x00]),
+                )?;
+            }
 
-                let raw_packet = gen_packet(&Packet::NetworkCommandSys(SysCommandPacket {
-                    sys_cmd: SysCommand::SysCommandAuth(SysAuthCommand::CompleteNotification),
-                }))
-                .map_err(|e| {
-                    layer_err!("Failed to generate authentication success packet: {:?}", e)
-                })?;
+            for message in messages {
+                send_authentication_packet(event.peer_id, event.tx_outgoing.clone(), message)?;
+            }
 
-                let outgoing_packet = outgoing::OutgoingPacket {
-                    peer_id: event.peer_id.clone(),
-                    channel_id: 0,
-                    packet: outgoing::Packet::reliable(raw_packet),
-                };
+            let raw_packet = gen_packet(&Packet::NetworkCommandSys(SysCommandPacket {
+                sys_cmd: SysCommand::SysCommandAuth(SysAuthCommand::CompleteNotification),
+            }))
+            .map_err(|source| AuthenticationError::Packet {
+                packet: "authentication success",
+                source,
+            })?;
 
-                event.tx_outgoing.send(outgoing_packet).map_err(|e| {
-                    layer_err!("Failed to transmit authentication success packet: {:?}", e)
+            let outgoing_packet = outgoing::OutgoingPacket::new(
+                event.peer_id,
+                GodotChannel::DEFAULT,
+                TransferMode::Reliable,
+                raw_packet,
+            )
+            .map_err(|source| AuthenticationError::Packet {
+                packet: "authentication success",
+                source,
+            })?;
+
+            event
+                .tx_outgoing
+                .send(outgoing_packet.into())
+                .map_err(|source| AuthenticationError::Send {
+                    command: "authentication success packet",
+                    source,
                 })?;
+
+            if let Some(mut peer_auth) = cache.peers.get_mut(&event.peer_id) {
+                peer_auth.peer_data = peer_data;
             }
 
-            event.data_pile.insert(cache);
+            Ok(cache.advance(&event.peer_id, AuthState::ServerAccepted))
+        }
+        AuthStep::Reject => {
+            if let Some(mut peer_auth) = cache.peers.get_mut(&event.peer_id) {
+                peer_auth.state = AuthState::Failed;
+            }
 
-            return Ok(None);
-        });
+            send_disconnect(event.peer_id, event.tx_outgoing.clone())?;
+
+            Ok(None)
+        }
     }
 }
 
+// Replicates the auth_timeout check of SceneMultiplayer::poll
+async fn disconnect_on_timeout(
+    cache: AuthCache,
+    peer_id: ENetPeerID,
+    session: u64,
+    timeout: Duration,
+    tx_outgoing: UnboundedSender<outgoing::OutgoingCommand>,
+) {
+    tokio::time::sleep(timeout).await;
+
+    {
+        let Some(mut peer_auth) = cache.peers.get_mut(&peer_id) else {
+            return;
+        };
+
+        if peer_auth.session != session
+            || matches!(
+                peer_auth.state,
+                AuthState::Authenticated | AuthState::Failed
+            )
+        {
+            return;
+        }
+
+        peer_auth.state = AuthState::Failed;
+    }
+
+    warn!("Peer {:?} did not authenticate in time", peer_id);
+
+    if let Err(e) = send_disconnect(peer_id, tx_outgoing) {
+        log::error!("Failed to disconnect timed out peer {:?}: {}", peer_id, e);
+    }
+}
+
+fn send_disconnect(
+    peer_id: ENetPeerID,
+    tx_outgoing: UnboundedSender<outgoing::OutgoingCommand>,
+) -> Result<(), AuthenticationError> {
+    tx_outgoing
+        .send(outgoing::OutgoingCommand::Disconnect { peer_id, data: 0 })
+        .map_err(|source| AuthenticationError::Send {
+            command: "disconnect",
+            source,
+        })?;
+
+    Ok(())
+}
+
 fn send_authentication_packet(
     peer_id: ENetPeerID,
-    tx_outgoing: Sender<outgoing::OutgoingPacket>,
-) -> Result<(), String> {
+    tx_outgoing: UnboundedSender<outgoing::OutgoingCommand>,
+    auth_data: Box<[u8]>,
+) -> Result<(), AuthenticationError> {
     let raw_packet = gen_packet(&Packet::NetworkCommandSys(SysCommandPacket {
-        sys_cmd: SysCommand::SysCommandAuth(SysAuthCommand::AuthMessage(Box::new([0x00]))),
+        sys_cmd: SysCommand::SysCommandAuth(SysAuthCommand::AuthMessage(auth_data)),
     }))
-    .map_err(|e| format!("Failed to generate authentication request packet: {:?}", e))?;
+    .map_err(|source| AuthenticationError::Packet {
+        packet: "authentication request",
+        source,
+    })?;
 
-    let outgoing_packet = outgoing::OutgoingPacket {
-        peer_id: peer_id.clone(),
-        channel_id: 0,
-        packet: outgoing::Packet::reliable(raw_packet),
-    };
+    let outgoing_packet = outgoing::OutgoingPacket::new(
+        peer_id,
+        GodotChannel::DEFAULT,
+        TransferMode::Reliable,
+        raw_packet,
+    )
+    .map_err(|source| AuthenticationError::Packet {
+        packet: "authentication request",
+        source,
+    })?;
 
     tx_outgoing
-        .send(outgoing_packet)
-        .map_err(|e| format!("Failed to transmit authentication request packet: {:?}", e))?;
+        .send(outgoing_packet.into())
+        .map_err(|source| AuthenticationError::Send {
+            command: "authentication request packet",
+            source,
+        })?;
 
     Ok(())
 }
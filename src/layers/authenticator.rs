@@ -0,0 +1,80 @@
+use crate::{DataPile, ENetPeerID};
+use std::{future::Future, pin::Pin};
+
+pub type AuthReturn = Pin<Box<dyn Future<Output = AuthStep> + Send + Sync>>;
+
+#[derive(Clone, Debug)]
+/// The outcome of a round of authentication
+pub enum AuthStep {
+    /// Send the messages to the peer and wait for its next message.
+    Continue { messages: Vec<Box<[u8]>> },
+    /// Send the messages to the peer and accept it.
+    ///
+    /// The peer data is added to the [`DataPile`](crate::DataPile) of every later event from the peer,
+    /// such as the claims of a verified token.
+    Accept {
+        messages: Vec<Box<[u8]>>,
+        peer_data: DataPile,
+    },
+    /// Reject the peer, disconnecting it.
+    Reject,
+}
+
+/// Authenticates peers over one or more rounds of `AuthMessage`s,
+/// replicating `SceneMultiplayer.auth_callback` and `complete_auth`.
+///
+/// Per peer state, such as a challenge sent to the peer, should be kept by the authenticator
+/// and cleaned up in [`Authenticator::end`].
+pub trait Authenticator: Send + Sync + 'static {
+    /// Called when a peer connects, before any of its messages are received.
+    ///
+    /// Can be used to send the first challenge to the peer.
+    fn start(&self, _peer: ENetPeerID, _data_pile: DataPile) -> AuthReturn {
+        Box::pin(async { AuthStep::Continue { messages: vec![] } })
+    }
+
+    /// Called with every `AuthMessage` the peer sends until it is accepted or rejected.
+    fn message(&self, peer: ENetPeerID, message: Box<[u8]>, data_pile: DataPile) -> AuthReturn;
+
+    /// Called when the peer disconnects, whether or not it was authenticated.
+    fn end(&self, _peer: ENetPeerID) {}
+}
+
+/// An [`Authenticator`] which accepts or rejects peers from their first message with a function
+pub struct FnAuthenticator<F>
+where
+    F: Future<Output = bool> + Sync + Send + 'static,
+{
+    pub authentication_callback: fn(ENetPeerID, Box<[u8]>, DataPile) -> F,
+}
+
+impl<F> FnAuthenticator<F>
+where
+    F: Future<Output = bool> + Sync + Send + 'static,
+{
+    pub fn build(authentication_callback: fn(ENetPeerID, Box<[u8]>, DataPile) -> F) -> Self {
+        Self {
+            authentication_callback,
+        }
+    }
+}
+
+impl<F> Authenticator for FnAuthenticator<F>
+where
+    F: Future<Output = bool> + Sync + Send + 'static,
+{
+    fn message(&self, peer: ENetPeerID, message: Box<[u8]>, data_pile: DataPile) -> AuthReturn {
+        let authentication_callback = self.authentication_callback;
+
+        Box::pin(async move {
+            if authentication_callback(peer, message, data_pile).await {
+                AuthStep::Accept {
+                    messages: vec![],
+                    peer_data: DataPile::new(),
+                }
+            } else {
+                AuthStep::Reject
+            }
+        })
+    }
+}
@@ -21,7 +21,7 @@ impl Layer for AutoParseLayer {
             };
 
             let parsed_packet: Packet = crate::packet::parse_packet(raw_packet.data())
-                .map_err(|e| layer_err!("Error Parsing Packet: {}", e))?;
+                .map_err(|e| layer_err!("Error Parsing Packet: {}", e).with_source(e))?;
 
             if let Ok(message) = str::from_utf8(raw_packet.data()) {
                 debug!("Received packet: {:?}", message);
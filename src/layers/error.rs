@@ -0,0 +1,284 @@
+use crate::{
+    GDPeerID,
+    layers::SyncTarget,
+    packet::{PacketError, outgoing::OutgoingCommand},
+};
+use std::{error::Error as StdError, fmt::Display, time::SystemTimeError};
+use tokio::sync::mpsc::error::SendError;
+
+#[derive(Debug)]
+/// Error updating the [`PathCache`](super::PathCache)
+pub enum PathCacheError {
+    /// The peer has no cache, as it is not connected.
+    PeerNotCached(GDPeerID),
+}
+
+impl StdError for PathCacheError {}
+
+impl Display for PathCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathCacheError::PeerNotCached(gd_peer) => {
+                write!(f, "No path cache for Godot Peer {:?}", gd_peer)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Error from the [`ReplicationManager`](super::ReplicationManager)
+pub enum ReplicationError {
+    /// Godot synchronizers can not have more than 64 watched properties.
+    TooManyWatchProperties(usize),
+    /// The synchronizer targets a net id, but the manager has no [`SpawnRegistry`](super::SpawnRegistry).
+    MissingSpawnRegistry(SyncTarget),
+    /// The synchronizer is not registered.
+    NotRegistered(SyncTarget),
+    /// The synchronizer has no sync property at the index.
+    NoSyncProperty { target: SyncTarget, index: usize },
+    /// The synchronizer has no watch property at the index.
+    NoWatchProperty { target: SyncTarget, index: usize },
+}
+
+impl StdError for ReplicationError {}
+
+impl Display for ReplicationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplicationError::TooManyWatchProperties(count) => write!(
+                f,
+                "Synchronizers can not have more than 64 watched properties, got {}",
+                count
+            ),
+            ReplicationError::MissingSpawnRegistry(target) => write!(
+                f,
+                "Synchronizer {:?} needs a SpawnRegistry to know when it is spawned",
+                target
+            ),
+            ReplicationError::NotRegistered(target) => {
+                write!(f, "Synchronizer {:?} is not registered", target)
+            }
+            ReplicationError::NoSyncProperty { target, index } => {
+                write!(f, "No sync property {} in {:?}", index, target)
+            }
+            ReplicationError::NoWatchProperty { target, index } => {
+                write!(f, "No watch property {} in {:?}", index, target)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Error from the [`SpawnRegistry`](super::SpawnRegistry)
+pub enum SpawnError {
+    /// The spawner is not registered.
+    SpawnerNotRegistered(String),
+    /// The scene is not one of the spawner's spawnable scenes.
+    SceneNotSpawnable { spawner_path: String, scene: String },
+    /// Spawned nodes must have a name.
+    MissingName,
+    /// No spawned node has the net id.
+    NotSpawned(u32),
+    /// The peer is not tracked by the registry, as it is not connected.
+    PeerNotTracked(GDPeerID),
+    /// The path of the spawner could not be cached on the peer.
+    SpawnerNotCached(String),
+    /// The spawn or despawn packet could not be generated or sent.
+    Packet(PacketError),
+}
+
+impl StdError for SpawnError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            SpawnError::Packet(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnError::SpawnerNotRegistered(spawner_path) => {
+                write!(f, "Spawner {} is not registered", spawner_path)
+            }
+            SpawnError::SceneNotSpawnable {
+                spawner_path,
+                scene,
+            } => write!(
+                f,
+                "Scene {} is not spawnable by spawner {}",
+                scene, spawner_path
+            ),
+            SpawnError::MissingName => write!(f, "Spawned nodes must have a name"),
+            SpawnError::NotSpawned(net_id) => {
+                write!(f, "No spawned node with net id {}", net_id)
+            }
+            SpawnError::PeerNotTracked(gd_peer) => {
+                write!(
+                    f,
+                    "Godot Peer {:?} is not tracked by the spawn registry",
+                    gd_peer
+                )
+            }
+            SpawnError::SpawnerNotCached(spawner_path) => {
+                write!(f, "Failed to cache spawner path {}", spawner_path)
+            }
+            SpawnError::Packet(source) => write!(f, "Failed to send packet: {}", source),
+        }
+    }
+}
+
+impl From<PacketError> for SpawnError {
+    fn from(source: PacketError) -> Self {
+        SpawnError::Packet(source)
+    }
+}
+
+#[derive(Debug)]
+/// Error signing or verifying a token of a [`TokenVerifier`](super::TokenVerifier)
+pub enum TokenError {
+    /// The claims can not be put in a token, for the named reason.
+    Unsignable(&'static str),
+    /// The token does not have the layout of the format, for the named reason.
+    Malformed(&'static str),
+    /// The named part of the token is not valid UTF-8.
+    InvalidUtf8(&'static str),
+    /// The named part of the token is not valid base64.
+    InvalidBase64 {
+        part: &'static str,
+        source: base64::DecodeError,
+    },
+    /// The named part of the token is not valid JSON.
+    InvalidJson {
+        part: &'static str,
+        source: serde_json::Error,
+    },
+    /// The token is signed with an algorithm other than HS256.
+    UnsupportedAlgorithm(Option<String>),
+    /// The signature does not match the token.
+    InvalidSignature,
+    /// The named claim is required but missing.
+    MissingClaim(&'static str),
+    /// A claim has the wrong type, for the named reason.
+    InvalidClaim(&'static str),
+    /// The token expired at the unix timestamp.
+    Expired { expires_at: u64 },
+    /// The token is not valid before the unix timestamp.
+    NotYetValid { not_before: u64 },
+    /// The HMAC key is not usable.
+    InvalidKey,
+    /// The system time is before the unix epoch.
+    Clock(SystemTimeError),
+}
+
+impl StdError for TokenError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            TokenError::InvalidBase64 { source, .. } => Some(source),
+            TokenError::InvalidJson { source, .. } => Some(source),
+            TokenError::Clock(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenError::Unsignable(reason) => write!(f, "{}", reason),
+            TokenError::Malformed(reason) => write!(f, "{}", reason),
+            TokenError::InvalidUtf8(part) => write!(f, "Invalid UTF-8 in token {}", part),
+            TokenError::InvalidBase64 { part, source } => {
+                write!(f, "Invalid base64 in token {}: {}", part, source)
+            }
+            TokenError::InvalidJson { part, source } => {
+                write!(f, "Invalid JSON in token {}: {}", part, source)
+            }
+            TokenError::UnsupportedAlgorithm(algorithm) => {
+                write!(f, "Token algorithm must be HS256, got {:?}", algorithm)
+            }
+            TokenError::InvalidSignature => write!(f, "Invalid token signature"),
+            TokenError::MissingClaim(claim) => write!(f, "Token is missing the {} claim", claim),
+            TokenError::InvalidClaim(reason) => write!(f, "{}", reason),
+            TokenError::Expired { expires_at } => {
+                write!(f, "Token expired at {}", expires_at)
+            }
+            TokenError::NotYetValid { not_before } => {
+                write!(f, "Token is not valid before {}", not_before)
+            }
+            TokenError::InvalidKey => write!(f, "Invalid HMAC key"),
+            TokenError::Clock(source) => {
+                write!(f, "System time is before the unix epoch: {}", source)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Error sending the packets of the [`AuthenticationLayer`](super::AuthenticationLayer)
+pub enum AuthenticationError {
+    /// The named packet could not be generated.
+    Packet {
+        packet: &'static str,
+        source: PacketError,
+    },
+    /// The named command could not be handed to the server.
+    Send {
+        command: &'static str,
+        source: SendError<OutgoingCommand>,
+    },
+}
+
+impl StdError for AuthenticationError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            AuthenticationError::Packet { source, .. } => Some(source),
+            AuthenticationError::Send { source, .. } => Some(source),
+        }
+    }
+}
+
+impl Display for AuthenticationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthenticationError::Packet { packet, source } => {
+                write!(f, "Failed to generate {} packet: {}", packet, source)
+            }
+            AuthenticationError::Send { command, source } => {
+                write!(f, "Failed to transmit {}: {}", command, source)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Error announcing peers from the [`PeerRosterLayer`](super::PeerRosterLayer)
+pub enum RosterError {
+    /// The roster packet could not be generated.
+    Packet(PacketError),
+    /// The roster packet could not be handed to the server.
+    Send(SendError<OutgoingCommand>),
+}
+
+impl StdError for RosterError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            RosterError::Packet(source) => Some(source),
+            RosterError::Send(source) => Some(source),
+        }
+    }
+}
+
+impl Display for RosterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RosterError::Packet(source) => {
+                write!(f, "Failed to generate roster packet: {}", source)
+            }
+            RosterError::Send(source) => {
+                write!(f, "Failed to transmit roster packet: {}", source)
+            }
+        }
+    }
+}
@@ -1,13 +1,29 @@
 mod authentication;
+mod authenticator;
 mod auto_parse;
+mod error;
 mod passthrough;
 mod path_cache;
 mod peer_map;
+mod peer_roster;
+mod relay;
+mod replication;
 mod rpc_parse;
+mod spawner;
+mod sync_validation;
+mod token_authenticator;
 
 pub use authentication::*;
+pub use authenticator::*;
 pub use auto_parse::*;
+pub use error::*;
 pub use passthrough::*;
 pub use path_cache::*;
 pub use peer_map::*;
+pub use peer_roster::*;
+pub use relay::*;
+pub use replication::*;
 pub use rpc_parse::*;
+pub use spawner::*;
+pub use sync_validation::*;
+pub use token_authenticator::*;
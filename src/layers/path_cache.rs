@@ -2,38 +2,60 @@ use crate::{
     ENetPeerID, GDPeerID, Layer, LayerReturn,
     event::{Event, EventType},
     layer_err,
-    packet::{Packet, RemoteCacheID, confirm_path, outgoing, simplify_path},
+    layers::PathCacheError,
+    packet::{
+        Packet, RemoteCacheID,
+        channel::{GodotChannel, TransferMode},
+        confirm_path, outgoing, simplify_path,
+    },
 };
-use dashmap::DashMap;
-use log::{debug, error};
+use dashmap::{DashMap, DashSet};
+use log::{debug, error, warn};
 use std::sync::Arc;
 
 #[derive(Default, Clone)]
+/// The paths each peer cached, namespaced by shard like the [`PeerMap`](crate::layers::PeerMap)
 pub struct PathCache {
-    id_cache: Arc<DashMap<GDPeerID, DashMap<RemoteCacheID, String>>>,
-    path_cache: Arc<DashMap<GDPeerID, DashMap<String, RemoteCacheID>>>,
-    checksum_cache: Arc<DashMap<GDPeerID, DashMap<RemoteCacheID, String>>>,
+    shard: usize,
+
+    id_cache: Arc<DashMap<(usize, GDPeerID), DashMap<RemoteCacheID, String>>>,
+    path_cache: Arc<DashMap<(usize, GDPeerID), DashMap<String, RemoteCacheID>>>,
+    checksum_cache: Arc<DashMap<(usize, GDPeerID), DashMap<RemoteCacheID, String>>>,
 }
 
 impl PathCache {
+    /// Obtain a handle to the same cache which looks up the Godot peers of a shard
+    pub fn in_shard(&self, shard: usize) -> Self {
+        Self {
+            shard,
+
+            ..self.clone()
+        }
+    }
+
+    fn key(&self, gd_peer: &GDPeerID) -> (usize, GDPeerID) {
+        (self.shard, *gd_peer)
+    }
+
     pub fn create_cache_entry(&self, gd_peer: &GDPeerID) {
-        self.path_cache.insert(*gd_peer, DashMap::new());
-        self.id_cache.insert(*gd_peer, DashMap::new());
-        self.checksum_cache.insert(*gd_peer, DashMap::new());
+        self.path_cache.insert(self.key(gd_peer), DashMap::new());
+        self.id_cache.insert(self.key(gd_peer), DashMap::new());
+        self.checksum_cache
+            .insert(self.key(gd_peer), DashMap::new());
 
         debug!("Created path cache entry for Godot Peer ID: {:?}", gd_peer);
     }
 
     pub fn remove_cache_entry(&self, gd_peer: &GDPeerID) {
-        self.path_cache.remove(gd_peer);
-        self.id_cache.remove(gd_peer);
-        self.checksum_cache.remove(gd_peer);
+        self.path_cache.remove(&self.key(gd_peer));
+        self.id_cache.remove(&self.key(gd_peer));
+        self.checksum_cache.remove(&self.key(gd_peer));
 
         debug!("Removed path cache entry for Godot Peer ID: {:?}", gd_peer);
     }
 
     pub fn get_path(&self, peer: &GDPeerID, remote_cache_id: &RemoteCacheID) -> Option<String> {
-        let id_cache = self.id_cache.get(peer)?;
+        let id_cache = self.id_cache.get(&self.key(peer))?;
 
         id_cache
             .get(remote_cache_id)
@@ -41,13 +63,13 @@ impl PathCache {
     }
 
     pub fn get_id(&self, peer: &GDPeerID, path: &str) -> Option<RemoteCacheID> {
-        let path_cache = self.path_cache.get(peer)?;
+        let path_cache = self.path_cache.get(&self.key(peer))?;
 
         path_cache.get(path).map(|entry| *entry.value())
     }
 
     pub fn get_checksum(&self, peer: &GDPeerID, remote_cache_id: &RemoteCacheID) -> Option<String> {
-        let checksum_cache = self.checksum_cache.get(peer)?;
+        let checksum_cache = self.checksum_cache.get(&self.key(peer))?;
 
         checksum_cache
             .get(remote_cache_id)
@@ -60,10 +82,10 @@ impl PathCache {
         remote_cache_id: RemoteCacheID,
         path: &str,
         checksum: &str,
-    ) -> Result<(), String> {
-        let path_cache = self.path_cache.get(peer);
-        let id_cache = self.id_cache.get(peer);
-        let checksum_cache = self.checksum_cache.get(peer);
+    ) -> Result<(), PathCacheError> {
+        let path_cache = self.path_cache.get(&self.key(peer));
+        let id_cache = self.id_cache.get(&self.key(peer));
+        let checksum_cache = self.checksum_cache.get(&self.key(peer));
 
         if let (Some(path_cache), Some(id_cache), Some(checksum_cache)) =
             (path_cache, id_cache, checksum_cache)
@@ -77,19 +99,16 @@ impl PathCache {
                 peer, remote_cache_id, path
             );
         } else {
-            return Err(format!(
-                "Failed to insert path cache entry for Godot Peer ID: {:?}, Remote Cache ID: {}, Path: {} - No cache entry found for peer",
-                peer, remote_cache_id, path
-            ));
+            return Err(PathCacheError::PeerNotCached(*peer));
         }
 
         return Ok(());
     }
 
     pub fn remove_id(&self, peer: &GDPeerID, remote_cache_id: &RemoteCacheID) {
-        let id_cache = self.id_cache.get(peer);
-        let path_cache = self.path_cache.get(peer);
-        let checksum_cache = self.checksum_cache.get(peer);
+        let id_cache = self.id_cache.get(&self.key(peer));
+        let path_cache = self.path_cache.get(&self.key(peer));
+        let checksum_cache = self.checksum_cache.get(&self.key(peer));
 
         if let (Some(id_cache), Some(path_cache), Some(checksum_cache)) =
             (id_cache, path_cache, checksum_cache)
@@ -112,9 +131,9 @@ impl PathCache {
     }
 
     pub fn remove_path(&self, peer: &GDPeerID, path: &str) {
-        let path_cache = self.path_cache.get(peer);
-        let id_cache = self.id_cache.get(peer);
-        let checksum_cache = self.checksum_cache.get(peer);
+        let path_cache = self.path_cache.get(&self.key(peer));
+        let id_cache = self.id_cache.get(&self.key(peer));
+        let checksum_cache = self.checksum_cache.get(&self.key(peer));
 
         if let (Some(path_cache), Some(id_cache), Some(checksum_cache)) =
             (path_cache, id_cache, checksum_cache)
@@ -138,12 +157,77 @@ impl PathCache {
 }
 
 #[derive(Default, Clone)]
+/// The paths sent to each peer, namespaced by shard like the [`PeerMap`](crate::layers::PeerMap)
 pub struct OutgoingCache {
-    // TODO: Use Confirm Path To Set States (0, uncached, 1 cache sent, 2 confirmed)
     pub cache: Arc<PathCache>,
+    // Cache ids each peer answered with ConfirmPath, like PathSentCache::confirmed_peers
+    confirmed: Arc<DashMap<(usize, GDPeerID), DashSet<RemoteCacheID>>>,
 }
 
 impl OutgoingCache {
+    /// Obtain a handle to the same cache which looks up the Godot peers of a shard
+    pub fn in_shard(&self, shard: usize) -> Self {
+        Self {
+            cache: Arc::new(self.cache.in_shard(shard)),
+            confirmed: self.confirmed.clone(),
+        }
+    }
+
+    fn key(&self, gd_peer: &GDPeerID) -> (usize, GDPeerID) {
+        self.cache.key(gd_peer)
+    }
+
+    pub fn create_cache_entry(&self, gd_peer: &GDPeerID) {
+        self.cache.create_cache_entry(gd_peer);
+        self.confirmed.insert(self.key(gd_peer), DashSet::new());
+    }
+
+    pub fn remove_cache_entry(&self, gd_peer: &GDPeerID) {
+        self.cache.remove_cache_entry(gd_peer);
+        self.confirmed.remove(&self.key(gd_peer));
+    }
+
+    /// Mark a path sent to the peer as confirmed, once it answered with ConfirmPath
+    ///
+    /// Ids which were never sent to the peer are ignored.
+    pub fn confirm(&self, gd_peer: &GDPeerID, remote_cache_id: RemoteCacheID) {
+        if self.cache.get_path(gd_peer, &remote_cache_id).is_none() {
+            debug!(
+                "Godot Peer {:?} confirmed unknown Remote Cache ID: {}",
+                gd_peer, remote_cache_id
+            );
+            return;
+        }
+
+        if let Some(confirmed) = self.confirmed.get(&self.key(gd_peer)) {
+            confirmed.insert(remote_cache_id);
+        }
+    }
+
+    /// Whether the peer confirmed the path with the RemoteCacheID
+    pub fn is_confirmed(&self, gd_peer: &GDPeerID, remote_cache_id: &RemoteCacheID) -> bool {
+        self.confirmed
+            .get(&self.key(gd_peer))
+            .is_some_and(|confirmed| confirmed.contains(remote_cache_id))
+    }
+
+    /// Gets the RemoteCacheID for the given path and peer, once the peer confirmed it.
+    /// If it does not exist, it will create a new entry in the cache.
+    ///
+    /// Replicates `SceneReplicationInterface::_verify_synchronizer`,
+    /// for packets which the peer drops when it can not resolve the path yet.
+    pub fn get_confirmed_or_write_id(
+        &self,
+        gd_peer: &GDPeerID,
+        enet_peer: &ENetPeerID,
+        path: &str,
+        checksum: &str,
+        tx_outgoing: &tokio::sync::mpsc::UnboundedSender<outgoing::OutgoingCommand>,
+    ) -> Option<RemoteCacheID> {
+        self.get_or_write_id(gd_peer, enet_peer, path, checksum, tx_outgoing)
+            .filter(|id| self.is_confirmed(gd_peer, id))
+    }
+
     /// Gets the RemoteCacheID for the given path and peer.
     /// If it does not exist, it will create a new entry in the cache
     /// and return the new RemoteCacheID.
@@ -155,56 +239,100 @@ impl OutgoingCache {
         enet_peer: &ENetPeerID,
         path: &str,
         checksum: &str,
-        tx_outgoing: &std::sync::mpsc::Sender<outgoing::OutgoingPacket>,
+        tx_outgoing: &tokio::sync::mpsc::UnboundedSender<outgoing::OutgoingCommand>,
     ) -> Option<RemoteCacheID> {
         match self.cache.get_id(gd_peer, path) {
             Some(id) => Some(id),
             None => {
-                let new_id: RemoteCacheID = rand::random::<u32>();
-
-                if let Err(e) = self.cache.insert(gd_peer, new_id, path, checksum) {
-                    error!(
-                        "Failed to insert new path cache entry for Godot Peer ID: {:?}, Path: {}: {}",
-                        gd_peer, path, e
-                    );
-                    return None;
-                }
+                self.write_id(gd_peer, enet_peer, path, checksum, tx_outgoing);
 
-                let packet = match simplify_path::gen_packet(checksum, new_id, path) {
-                    Ok(packet) => packet,
-                    Err(e) => {
-                        error!(
-                            "Failed to generate SimplifyPath packet for Godot Peer ID: {:?}, Path: {}: {}",
-                            gd_peer, path, e
-                        );
-                        return None;
-                    }
-                };
+                return None;
+            }
+        }
+    }
 
-                let outgoing_packet = outgoing::OutgoingPacket {
-                    peer_id: *enet_peer,
-                    channel_id: 0,
-                    packet: outgoing::Packet::reliable(packet),
-                };
+    /// Gets the RemoteCacheID for the given path and peer.
+    /// If it does not exist, it will create a new entry in the cache
+    /// and return the new RemoteCacheID immediately.
+    ///
+    /// Only use the new RemoteCacheID in reliable packets sent on channel 0,
+    /// so that they arrive after the SimplifyPath packet.
+    pub fn get_or_write_id_ordered(
+        &self,
+        gd_peer: &GDPeerID,
+        enet_peer: &ENetPeerID,
+        path: &str,
+        checksum: &str,
+        tx_outgoing: &tokio::sync::mpsc::UnboundedSender<outgoing::OutgoingCommand>,
+    ) -> Option<RemoteCacheID> {
+        match self.cache.get_id(gd_peer, path) {
+            Some(id) => Some(id),
+            None => self.write_id(gd_peer, enet_peer, path, checksum, tx_outgoing),
+        }
+    }
 
-                if let Err(e) = tx_outgoing.send(outgoing_packet) {
-                    error!(
-                        "Failed to send SimplifyPath packet for Godot Peer ID: {:?}, Ener Peer ID {:?}, Path: {}: {}",
-                        gd_peer, enet_peer, path, e
-                    );
+    /// Creates a new cache entry and sends the SimplifyPath packet to the peer.
+    fn write_id(
+        &self,
+        gd_peer: &GDPeerID,
+        enet_peer: &ENetPeerID,
+        path: &str,
+        checksum: &str,
+        tx_outgoing: &tokio::sync::mpsc::UnboundedSender<outgoing::OutgoingCommand>,
+    ) -> Option<RemoteCacheID> {
+        let new_id: RemoteCacheID = rand::random::<u32>();
 
-                    return None;
-                }
+        if let Err(e) = self.cache.insert(gd_peer, new_id, path, checksum) {
+            error!(
+                "Failed to insert new path cache entry for Godot Peer ID: {:?}, Path: {}: {}",
+                gd_peer, path, e
+            );
+            return None;
+        }
 
+        let packet = match simplify_path::gen_packet(checksum, new_id, path) {
+            Ok(packet) => packet,
+            Err(e) => {
+                error!(
+                    "Failed to generate SimplifyPath packet for Godot Peer ID: {:?}, Path: {}: {}",
+                    gd_peer, path, e
+                );
                 return None;
             }
+        };
+
+        let outgoing_packet = match outgoing::OutgoingPacket::new(
+            *enet_peer,
+            GodotChannel::DEFAULT,
+            TransferMode::Reliable,
+            packet,
+        ) {
+            Ok(outgoing_packet) => outgoing_packet,
+            Err(e) => {
+                error!(
+                    "Failed to build SimplifyPath packet for Godot Peer ID: {:?}, Path: {}: {}",
+                    gd_peer, path, e
+                );
+                return None;
+            }
+        };
+
+        if let Err(e) = tx_outgoing.send(outgoing_packet.into()) {
+            error!(
+                "Failed to send SimplifyPath packet for Godot Peer ID: {:?}, Ener Peer ID {:?}, Path: {}: {}",
+                gd_peer, enet_peer, path, e
+            );
+
+            return None;
         }
+
+        return Some(new_id);
     }
 }
 
 #[derive(Clone)]
 /// A [`Layer`](crate::Layer) which maintains a path cache for each id,
-/// adding the cache to the [`DataPile`](crate::DataPile).
+/// adding the caches, in the shard of the event's peer, to the [`DataPile`](crate::DataPile).
 ///
 /// Depends on [`AutoParseLayer`](crate::layers::AutoParseLayer)
 /// and [`PeerMapLayer`](crate::layers::PeerMapLayer).
@@ -230,8 +358,8 @@ impl Default for PathCacheLayer {
 
 impl Layer for PathCacheLayer {
     fn call(&self, mut event: Event) -> LayerReturn {
-        let cache = self.cache.clone();
-        let outgoing_cache = self.outgoing_cache.clone();
+        let cache = self.cache.in_shard(event.peer_id.shard());
+        let outgoing_cache = self.outgoing_cache.in_shard(event.peer_id.shard());
         let consume_confirm_path = self.consume_confirm_path;
         let consume_simplify_path = self.consume_simplify_path;
 
@@ -239,11 +367,11 @@ impl Layer for PathCacheLayer {
             match event.event {
                 EventType::Connect { ref godot_peer } => {
                     cache.create_cache_entry(godot_peer);
-                    outgoing_cache.cache.create_cache_entry(godot_peer);
+                    outgoing_cache.create_cache_entry(godot_peer);
                 }
                 EventType::Disconnect { ref godot_peer } => {
                     cache.remove_cache_entry(godot_peer);
-                    outgoing_cache.cache.remove_cache_entry(godot_peer);
+                    outgoing_cache.remove_cache_entry(godot_peer);
                 }
                 EventType::Receive { .. } => {
                     let parsed_packet = match event.data_pile.get::<Packet>() {
@@ -263,7 +391,8 @@ impl Layer for PathCacheLayer {
                     };
 
                     if let Packet::NetworkCommandConfirmPath {
-                        remote_cache_id, ..
+                        valid_rpc_checksum,
+                        remote_cache_id,
                     } = parsed_packet
                     {
                         debug!(
@@ -271,6 +400,16 @@ impl Layer for PathCacheLayer {
                             peer_id, remote_cache_id
                         );
 
+                        // Godot confirms the path even when the checksum differs, see SceneCacheInterface::process_confirm_path
+                        if !valid_rpc_checksum {
+                            warn!(
+                                "Godot Peer {:?} has a different RPC checksum for Remote Cache ID: {}",
+                                peer_id, remote_cache_id
+                            );
+                        }
+
+                        outgoing_cache.confirm(peer_id, *remote_cache_id);
+
                         if consume_confirm_path {
                             return Ok(None);
                         }
@@ -310,13 +449,17 @@ impl Layer for PathCacheLayer {
                             }
                         };
 
-                        let outgoing_packet = outgoing::OutgoingPacket {
-                            peer_id: event.peer_id,
-                            channel_id: 0,
-                            packet: outgoing::Packet::reliable(response_packet),
-                        };
-
-                        if let Err(e) = event.tx_outgoing.send(outgoing_packet) {
+                        let outgoing_packet = outgoing::OutgoingPacket::new(
+                            event.peer_id,
+                            GodotChannel::DEFAULT,
+                            TransferMode::Reliable,
+                            response_packet,
+                        )
+                        .map_err(|e| {
+                            layer_err!("Failed to build ConfirmPath packet: {}", e).with_source(e)
+                        })?;
+
+                        if let Err(e) = event.tx_outgoing.send(outgoing_packet.into()) {
                             return Err(layer_err!("Failed to send ConfirmPath packet: {:?}", e));
                         }
 
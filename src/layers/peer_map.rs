@@ -4,38 +4,106 @@ use crate::{
     ENetPeerID, GDPeerID, Layer, LayerReturn,
     event::{Event, EventType},
     layer_err,
+    packet::outgoing::BroadcastTarget,
 };
 use dashmap::DashMap;
 use log::debug;
 
 #[derive(Default, Clone)]
+/// Maps ENet Peer IDs to Godot Peer IDs and back
+///
+/// Godot Peer IDs are namespaced by the shard of their ENet peer,
+/// as clients of different [`ShardedServer`](crate::ShardedServer) shards may pick the same one.
+/// Lookups by Godot Peer ID only see the shard of the handle, see [`PeerMap::in_shard`].
 pub struct PeerMap {
+    shard: usize,
+
     enet_peers: Arc<DashMap<ENetPeerID, GDPeerID>>,
-    gd_peers: Arc<DashMap<GDPeerID, ENetPeerID>>,
+    gd_peers: Arc<DashMap<(usize, GDPeerID), ENetPeerID>>,
 }
 
 impl PeerMap {
+    /// Obtain a handle to the same map which looks up the Godot peers of a shard
+    pub fn in_shard(&self, shard: usize) -> Self {
+        Self {
+            shard,
+
+            ..self.clone()
+        }
+    }
+
+    /// The shard the Godot peers are looked up in
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
     pub fn get_enet_peer(&self, gd_peer: &GDPeerID) -> Option<ENetPeerID> {
-        return self.gd_peers.get(gd_peer).map(|entry| *entry.value());
+        return self
+            .gd_peers
+            .get(&(self.shard, *gd_peer))
+            .map(|entry| *entry.value());
     }
 
     pub fn get_gd_peer(&self, enet_peer: &ENetPeerID) -> Option<GDPeerID> {
         return self.enet_peers.get(enet_peer).map(|entry| *entry.value());
     }
 
+    pub fn get_gd_peers(&self) -> Vec<GDPeerID> {
+        return self
+            .gd_peers
+            .iter()
+            .filter(|entry| entry.key().0 == self.shard)
+            .map(|entry| entry.key().1)
+            .collect();
+    }
+
+    /// Resolve the ENet peers targeted by a broadcast
+    ///
+    /// Targets which are not in the map, such as the server, are skipped.
+    pub fn resolve(&self, target: &BroadcastTarget) -> Vec<ENetPeerID> {
+        match target {
+            BroadcastTarget::Peer(gd_peer) if **gd_peer > 0 => {
+                self.get_enet_peer(gd_peer).into_iter().collect()
+            }
+            BroadcastTarget::Peer(gd_peer) => {
+                // 0 for all, -x for all but x
+                let excluded = (self.shard, GDPeerID(-**gd_peer));
+
+                self.gd_peers
+                    .iter()
+                    .filter(|entry| entry.key().0 == self.shard && *entry.key() != excluded)
+                    .map(|entry| *entry.value())
+                    .collect()
+            }
+            BroadcastTarget::Peers(gd_peers) => {
+                let mut enet_peers: Vec<ENetPeerID> = gd_peers
+                    .iter()
+                    .filter_map(|gd_peer| self.get_enet_peer(gd_peer))
+                    .collect();
+
+                enet_peers.sort();
+                enet_peers.dedup();
+
+                enet_peers
+            }
+        }
+    }
+
+    /// Map the peers, in the shard of the ENet peer
     pub fn insert(&self, enet_peer: ENetPeerID, gd_peer: GDPeerID) {
         self.enet_peers.insert(enet_peer, gd_peer);
-        self.gd_peers.insert(gd_peer, enet_peer);
+        self.gd_peers
+            .insert((enet_peer.shard(), gd_peer), enet_peer);
     }
 
     pub fn remove_enet_peer(&self, enet_peer: &ENetPeerID) {
         if let Some((_, gd_peer)) = self.enet_peers.remove(enet_peer) {
-            self.gd_peers.remove(&gd_peer);
+            self.gd_peers.remove(&(enet_peer.shard(), gd_peer));
         }
     }
 
     pub fn remove_gd_peer(&self, gd_peer: &GDPeerID) {
-        if let Some((_, enet_peer)) = self.gd_peers.remove(gd_peer) {
+        if let Some((_, enet_peer)) = self.gd_peers.remove(&(self.shard, *gd_peer)) {
             self.enet_peers.remove(&enet_peer);
         }
     }
@@ -43,14 +111,21 @@ impl PeerMap {
 
 #[derive(Default, Clone)]
 /// A [`Layer`](crate::Layer) which maintains a mapping between ENet PeerIDs and Godot PeerIDs,
-/// adding the mapping to the [`Datapile`](crate::DataPile).
+/// adding the mapping, in the shard of the event's peer, to the [`Datapile`](crate::DataPile).
 pub struct PeerMapLayer {
     peer_map: PeerMap,
 }
 
+impl PeerMapLayer {
+    /// Obtain a handle to the layer's [`PeerMap`]
+    pub fn peer_map(&self) -> PeerMap {
+        self.peer_map.clone()
+    }
+}
+
 impl Layer for PeerMapLayer {
     fn call(&self, mut event: Event) -> LayerReturn {
-        let peer_map = self.peer_map.clone();
+        let peer_map = self.peer_map.in_shard(event.peer_id.shard());
 
         return Box::pin(async move {
             match event.event {
@@ -91,3 +166,35 @@ impl Layer for PeerMapLayer {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaces_godot_peers_by_shard() {
+        let peer_map = PeerMap::default();
+        let shard_0 = ENetPeerID::in_shard(0, 0, rusty_enet::PeerID(3));
+        let shard_1 = ENetPeerID::in_shard(1, 0, rusty_enet::PeerID(3));
+
+        // Both clients picked the same Godot Peer ID
+        peer_map.insert(shard_0, GDPeerID(7));
+        peer_map.insert(shard_1, GDPeerID(7));
+
+        assert_eq!(peer_map.get_enet_peer(&GDPeerID(7)), Some(shard_0));
+        assert_eq!(
+            peer_map.in_shard(1).get_enet_peer(&GDPeerID(7)),
+            Some(shard_1)
+        );
+        assert_eq!(
+            peer_map
+                .in_shard(1)
+                .resolve(&BroadcastTarget::Peer(GDPeerID(0))),
+            vec![shard_1]
+        );
+
+        peer_map.in_shard(1).remove_gd_peer(&GDPeerID(7));
+        assert_eq!(peer_map.get_gd_peer(&shard_0), Some(GDPeerID(7)));
+        assert_eq!(peer_map.get_gd_peer(&shard_1), None);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    ENetPeerID, GDPeerID, Layer, LayerReturn,
+    event::{Event, EventType},
+    layer_err,
+    layers::RosterError,
+    packet::{
+        Packet,
+        channel::{GodotChannel, TransferMode},
+        gen_packet, outgoing,
+        sys::{SysCommand, SysCommandPacket},
+    },
+};
+use log::debug;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Default, Clone)]
+/// The peers which have been announced to the other peers
+///
+/// Every shard has a roster of its own, like the Godot Peer IDs of the [`PeerMap`](crate::layers::PeerMap).
+pub struct PeerRoster {
+    shard: usize,
+
+    // Announcements are sent while holding the lock,
+    // so peers joining at once see each other and joins are never announced after leaves
+    peers: Arc<Mutex<HashMap<usize, HashMap<GDPeerID, ENetPeerID>>>>,
+}
+
+impl PeerRoster {
+    /// Obtain a handle to the roster of a shard
+    pub fn in_shard(&self, shard: usize) -> Self {
+        Self {
+            shard,
+
+            peers: self.peers.clone(),
+        }
+    }
+
+    /// Get the Godot Peer IDs of every peer in the roster
+    pub fn get_peers(&self) -> Vec<GDPeerID> {
+        self.lock()
+            .get(&self.shard)
+            .map(|peers| peers.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a peer is in the roster
+    pub fn contains(&self, gd_peer: &GDPeerID) -> bool {
+        self.lock()
+            .get(&self.shard)
+            .is_some_and(|peers| peers.contains_key(gd_peer))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<usize, HashMap<GDPeerID, ENetPeerID>>> {
+        // The roster is never left half updated, so a poisoned lock is still usable
+        self.peers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Clone)]
+/// A [`Layer`](crate::Layer) which announces peers to each other,
+/// replicating `SceneMultiplayer` sending `SYS_COMMAND_ADD_PEER` and `SYS_COMMAND_DEL_PEER`.
+///
+/// Newly connected peers are sent the existing roster,
+/// and every other peer is notified of joins and leaves.
+/// Adds the [`PeerRoster`] to the [`DataPile`](crate::DataPile).
+///
+/// Should run after authentication, so only authenticated peers are announced.
+pub struct PeerRosterLayer {
+    roster: PeerRoster,
+
+    /// Whether to announce peers to each other, mirrors `SceneMultiplayer.server_relay`.
+    pub server_relay: bool,
+}
+
+impl Default for PeerRosterLayer {
+    fn default() -> Self {
+        Self {
+            roster: PeerRoster::default(),
+
+            server_relay: true,
+        }
+    }
+}
+
+impl PeerRosterLayer {
+    /// Obtain a handle to the layer's [`PeerRoster`]
+    pub fn roster(&self) -> PeerRoster {
+        self.roster.clone()
+    }
+}
+
+impl Layer for PeerRosterLayer {
+    fn call(&self, mut event: Event) -> LayerReturn {
+        let roster = self.roster.in_shard(event.peer_id.shard());
+        let server_relay = self.server_relay;
+
+        return Box::pin(async move {
+            match event.event {
+                EventType::Connect { ref godot_peer } => {
+                    let mut shards = roster.lock();
+                    let peers = shards.entry(roster.shard).or_default();
+
+                    peers.insert(*godot_peer, event.peer_id);
+
+                    debug!("Added Godot Peer {:?} to the roster", godot_peer);
+
+                    // Replicated from SceneMultiplayer::_admit_peer
+                    if server_relay {
+                        for (other_gd_peer, other_enet_peer) in peers.iter() {
+                            if other_gd_peer == godot_peer {
+                                continue;
+                            }
+
+                            // Send new peer to already connected
+                            send_sys_command(
+                                &event.tx_outgoing,
+                                *other_enet_peer,
+                                SysCommand::SysCommandAddPeer(*godot_peer),
+                            )
+                            .map_err(|e| layer_err!("{}", e).with_source(e))?;
+
+                            // Send already connected to new peer
+                            send_sys_command(
+                                &event.tx_outgoing,
+                                event.peer_id,
+                                SysCommand::SysCommandAddPeer(*other_gd_peer),
+                            )
+                            .map_err(|e| layer_err!("{}", e).with_source(e))?;
+                        }
+                    }
+                }
+                EventType::Disconnect { ref godot_peer } => {
+                    let mut shards = roster.lock();
+                    let peers = shards.entry(roster.shard).or_default();
+
+                    // Replicated from SceneMultiplayer::_del_peer
+                    if peers.remove(godot_peer).is_some() {
+                        debug!("Removed Godot Peer {:?} from the roster", godot_peer);
+
+                        if server_relay {
+                            for enet_peer in peers.values() {
+                                send_sys_command(
+                                    &event.tx_outgoing,
+                                    *enet_peer,
+                                    SysCommand::SysCommandDelPeer(*godot_peer),
+                                )
+                                .map_err(|e| layer_err!("{}", e).with_source(e))?;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            event.data_pile.insert(roster);
+
+            return Ok(Some(event));
+        });
+    }
+}
+
+fn send_sys_command(
+    tx_outgoing: &UnboundedSender<outgoing::OutgoingCommand>,
+    peer_id: ENetPeerID,
+    sys_cmd: SysCommand,
+) -> Result<(), RosterError> {
+    let raw_packet = gen_packet(&Packet::NetworkCommandSys(SysCommandPacket { sys_cmd }))
+        .map_err(RosterError::Packet)?;
+
+    let outgoing_packet = outgoing::OutgoingPacket::new(
+        peer_id,
+        GodotChannel::DEFAULT,
+        TransferMode::Reliable,
+        raw_packet,
+    )
+    .map_err(RosterError::Packet)?;
+
+    tx_outgoing
+        .send(outgoing_packet.into())
+        .map_err(RosterError::Send)?;
+
+    Ok(())
+}
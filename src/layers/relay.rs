@@ -1 +1,218 @@
-// TODO: Build Relay Layer, With Optional Authentication Function
+use crate::{
+    DataPile, GDPeerID, Layer, LayerReturn,
+    event::{Event, EventType},
+    layer_err,
+    layers::PeerMap,
+    packet::{
+        Packet,
+        channel::TransferMode,
+        gen_packet, outgoing, parse_packet,
+        sys::{SysCommand, SysCommandPacket},
+    },
+};
+use log::{debug, warn};
+use std::future::Ready;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A relay request sent by a client
+pub struct RelayMessage {
+    /// The peer which sent the relay request.
+    pub from: GDPeerID,
+    /// The requested target, 0 for all, -x for all but x, or a single peer.
+    pub target: GDPeerID,
+    /// The packet to relay.
+    pub content: Box<[u8]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// The outcome of a relay policy
+pub enum RelayDecision {
+    /// Relay the message as requested.
+    Allow,
+    /// Relay a different packet, or to a different target.
+    Rewrite {
+        target: GDPeerID,
+        content: Box<[u8]>,
+    },
+    /// Do not relay the message.
+    Drop,
+}
+
+/// A [`Layer`](crate::Layer) which relays packets between clients,
+/// replicating `SceneMultiplayer` with `server_relay` enabled.
+///
+/// Relay requests targeting the server as well (0, or -x for a client x)
+/// continue down the layers as the relayed packet,
+/// replacing the parsed packet in the [`DataPile`](crate::DataPile),
+/// while all other relay requests are consumed.
+///
+/// Depends on [`AutoParseLayer`](crate::layers::AutoParseLayer)
+/// and [`PeerMapLayer`](crate::layers::PeerMapLayer).
+pub struct RelayLayer<F>
+where
+    F: Future<Output = RelayDecision> + Sync + Send + 'static,
+{
+    pub policy: Option<fn(RelayMessage, DataPile) -> F>,
+}
+
+impl RelayLayer<Ready<RelayDecision>> {
+    /// Build a relay layer which relays every message
+    pub fn new() -> Self {
+        Self { policy: None }
+    }
+}
+
+impl Default for RelayLayer<Ready<RelayDecision>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> RelayLayer<F>
+where
+    F: Future<Output = RelayDecision> + Sync + Send + 'static,
+{
+    /// Build a relay layer which asks the policy whether to relay each message
+    pub fn build(policy: fn(RelayMessage, DataPile) -> F) -> Self {
+        Self {
+            policy: Some(policy),
+        }
+    }
+}
+
+impl<F> Layer for RelayLayer<F>
+where
+    F: Future<Output = RelayDecision> + Sync + Send + 'static,
+{
+    fn call(&self, mut event: Event) -> LayerReturn {
+        let policy = self.policy;
+
+        return Box::pin(async move {
+            let EventType::Receive {
+                channel,
+                transfer_mode,
+                ..
+            } = event.event
+            else {
+                return Ok(Some(event));
+            };
+
+            let Some(parsed_packet) = event.data_pile.get::<Packet>() else {
+                return Err(layer_err!(
+                    "Ran without parsed packet, requires AutoParseLayer".to_string()
+                ));
+            };
+
+            let Packet::NetworkCommandSys(SysCommandPacket {
+                sys_cmd: SysCommand::SysCommandRelay { content, gdpeer },
+            }) = parsed_packet
+            else {
+                return Ok(Some(event));
+            };
+
+            let Some(from) = event.data_pile.get::<GDPeerID>() else {
+                return Err(layer_err!(
+                    "Ran without Godot Peer ID in DataPile, requires PeerMapLayer".to_string()
+                ));
+            };
+
+            let Some(peer_map) = event.data_pile.get::<PeerMap>() else {
+                return Err(layer_err!(
+                    "Ran without Peer Map in DataPile, requires PeerMapLayer".to_string()
+                ));
+            };
+
+            let mut message = RelayMessage {
+                from: *from,
+                target: *gdpeer,
+                content: content.clone(),
+            };
+
+            let peer_map = peer_map.clone();
+
+            if let Some(policy) = policy {
+                match policy(message.clone(), event.data_pile.clone()).await {
+                    RelayDecision::Allow => {}
+                    RelayDecision::Rewrite { target, content } => {
+                        message.target = target;
+                        message.content = content;
+                    }
+                    RelayDecision::Drop => {
+                        debug!(
+                            "Relay policy dropped message from Godot Peer {:?} to {:?}",
+                            message.from, message.target
+                        );
+
+                        return Ok(None);
+                    }
+                }
+            }
+
+            // This section replicates SceneMultiplayer::_process_sys for SYS_COMMAND_RELAY on the server
+
+            let target = *message.target;
+
+            // Direct messages to the server should not go through relay
+            if target == 1 || (target > 0 && peer_map.get_enet_peer(&message.target).is_none()) {
+                warn!(
+                    "Godot Peer {:?} requested relay to invalid target {:?}",
+                    message.from, message.target
+                );
+
+                return Ok(None);
+            }
+
+            let recipients: Vec<GDPeerID> = if target > 0 {
+                vec![message.target]
+            } else {
+                peer_map
+                    .get_gd_peers()
+                    .into_iter()
+                    .filter(|gd_peer| *gd_peer != message.from && **gd_peer != -target)
+                    .collect()
+            };
+
+            let relay_packet = gen_packet(&Packet::NetworkCommandSys(SysCommandPacket {
+                sys_cmd: SysCommand::SysCommandRelay {
+                    content: message.content.clone(),
+                    gdpeer: message.from, // Set the source
+                },
+            }))
+            .map_err(|e| layer_err!("Failed to generate relay packet: {}", e).with_source(e))?;
+
+            // Relayed on the channel and with the mode it was received, like SceneMultiplayer::_relay,
+            // the mode of user channels is unknown so their packets are relayed reliably
+            let relay_mode = transfer_mode.unwrap_or(TransferMode::Reliable);
+            let broadcast = outgoing::OutgoingBroadcast::new(
+                &peer_map,
+                &outgoing::BroadcastTarget::Peers(recipients),
+                channel,
+                relay_mode,
+                relay_packet,
+            )
+            .map_err(|e| layer_err!("Failed to build relay packet: {}", e).with_source(e))?;
+
+            event
+                .tx_outgoing
+                .send(broadcast.into())
+                .map_err(|e| layer_err!("Failed to transmit relay packet: {}", e))?;
+
+            // Process as the source when the server is targeted as well
+            if target > 0 || target == -1 {
+                return Ok(None);
+            }
+
+            let relayed_packet = parse_packet(&message.content)
+                .map_err(|e| layer_err!("Error Parsing Relayed Packet: {}", e).with_source(e))?;
+
+            event.event = EventType::Receive {
+                channel,
+                transfer_mode,
+                raw_packet: relay_mode.packet(message.content.as_ref()),
+            };
+            event.data_pile.insert(relayed_packet);
+
+            return Ok(Some(event));
+        });
+    }
+}
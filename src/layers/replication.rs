@@ -0,0 +1,665 @@
+use crate::{
+    ENetPeerID, GDPeerID, Layer, LayerReturn,
+    event::{Event, EventType},
+    layer_err,
+    layers::{OutgoingCache, ReplicationError, SpawnRegistry},
+    packet::{
+        PacketError,
+        channel::{GodotChannel, TransferMode},
+        outgoing,
+        sync::{self, NET_ID_PATH_FLAG, SyncCommand, SyncSection, SyncState},
+    },
+    routers::hash_function_set,
+    variant::Variant,
+};
+use dashmap::DashMap;
+use log::error;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU16, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+// From scene_replication_interface.cpp
+const DEFAULT_SYNC_MTU: usize = 1350;
+
+/// Filter deciding whether a synchronizer is visible to a peer,
+/// replicating `MultiplayerSynchronizer::add_visibility_filter` in Godot.
+pub type VisibilityFilter = Arc<dyn Fn(GDPeerID) -> bool + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Identifies a synchronizer on the clients
+pub enum SyncTarget {
+    /// A synchronizer spawned with its node, using one of the spawn's sync ids.
+    NetId(u32),
+    /// A synchronizer already in the client's scene, identified by its path.
+    Path(String),
+}
+
+struct Synchronizer {
+    sync_properties: Vec<Arc<Box<dyn Variant>>>,
+    /// Watched values with the change they were last set in.
+    watch_properties: Vec<(Arc<Box<dyn Variant>>, u64)>,
+
+    public_visibility: bool,
+    /// Peers the synchronizer is visible to, by shard and Godot Peer ID.
+    peer_visibility: HashSet<(usize, GDPeerID)>,
+    visibility_filters: Vec<VisibilityFilter>,
+
+    /// Last change sent to each peer.
+    last_watch_changes: HashMap<(usize, GDPeerID), u64>,
+}
+
+impl Synchronizer {
+    // Replicated from MultiplayerSynchronizer::is_visible_to
+    fn is_visible_to(&self, key: (usize, GDPeerID)) -> bool {
+        if !self.visibility_filters.iter().all(|filter| filter(key.1)) {
+            return false;
+        }
+
+        self.public_visibility || self.peer_visibility.contains(&key)
+    }
+}
+
+struct ReplicationPeer {
+    enet_peer: ENetPeerID,
+
+    outgoing_cache: OutgoingCache,
+    spawns: Option<SpawnRegistry>,
+
+    tx_outgoing: UnboundedSender<outgoing::OutgoingCommand>,
+
+    last_sent_sync: AtomicU16,
+}
+
+#[derive(Clone)]
+/// Emulates server authoritative `MultiplayerSynchronizer`s,
+/// holding their replicated properties and pushing them to every peer they are visible to.
+///
+/// Peers are added and removed by the [`ReplicationLayer`](crate::layers::ReplicationLayer),
+/// and namespaced by shard like the [`PeerMap`](crate::layers::PeerMap).
+pub struct ReplicationManager {
+    shard: usize,
+
+    synchronizers: Arc<DashMap<SyncTarget, Synchronizer>>,
+
+    peers: Arc<DashMap<(usize, GDPeerID), ReplicationPeer>>,
+
+    spawns: Option<SpawnRegistry>,
+
+    /// Sequence of watched property changes, only advanced while holding the changed synchronizer.
+    changes: Arc<AtomicU64>,
+
+    /// Maximum size of the states in a single sync packet, packets are split to stay below it.
+    pub sync_mtu: usize,
+    /// Maximum size of the states in a single delta packet, packets are split to stay below it.
+    pub delta_mtu: usize,
+}
+
+impl Default for ReplicationManager {
+    fn default() -> Self {
+        Self {
+            shard: 0,
+
+            synchronizers: Arc::new(DashMap::new()),
+
+            peers: Arc::new(DashMap::new()),
+
+            spawns: None,
+
+            changes: Arc::new(AtomicU64::new(0)),
+
+            sync_mtu: DEFAULT_SYNC_MTU,
+            delta_mtu: DEFAULT_SYNC_MTU,
+        }
+    }
+}
+
+impl ReplicationManager {
+    /// Obtain a handle to the same manager which looks up the Godot peers of a shard
+    pub fn in_shard(&self, shard: usize) -> Self {
+        Self {
+            shard,
+
+            synchronizers: self.synchronizers.clone(),
+            peers: self.peers.clone(),
+            spawns: self.spawns.as_ref().map(|spawns| spawns.in_shard(shard)),
+            changes: self.changes.clone(),
+            sync_mtu: self.sync_mtu,
+            delta_mtu: self.delta_mtu,
+        }
+    }
+
+    fn key(&self, gd_peer: &GDPeerID) -> (usize, GDPeerID) {
+        (self.shard, *gd_peer)
+    }
+
+    /// Build a manager which replicates the synchronizers of nodes spawned by the registry.
+    ///
+    /// Synchronizers targeted by [`SyncTarget::NetId`] are only synced to a peer
+    /// once the node they belong to was spawned on it.
+    pub fn with_spawns(registry: SpawnRegistry) -> Self {
+        Self {
+            spawns: Some(registry),
+
+            ..Default::default()
+        }
+    }
+
+    /// Start replicating a synchronizer.
+    ///
+    /// `sync_properties` are sent every process, `watch_properties` are only sent when changed.
+    /// Both must be in the order of the synchronizer's replication config.
+    pub fn register(
+        &self,
+        target: SyncTarget,
+        sync_properties: Vec<Arc<Box<dyn Variant>>>,
+        watch_properties: Vec<Arc<Box<dyn Variant>>>,
+    ) -> Result<(), ReplicationError> {
+        if watch_properties.len() > 64 {
+            return Err(ReplicationError::TooManyWatchProperties(
+                watch_properties.len(),
+            ));
+        }
+
+        if matches!(target, SyncTarget::NetId(_)) && self.spawns.is_none() {
+            return Err(ReplicationError::MissingSpawnRegistry(target));
+        }
+
+        // Any change is newer than what peers have seen of a new synchronizer
+        let change = self.changes.fetch_add(1, Ordering::AcqRel) + 1;
+
+        self.synchronizers.insert(
+            target,
+            Synchronizer {
+                sync_properties,
+                watch_properties: watch_properties
+                    .into_iter()
+                    .map(|value| (value, change))
+                    .collect(),
+
+                public_visibility: true,
+                peer_visibility: HashSet::new(),
+                visibility_filters: Vec::new(),
+
+                last_watch_changes: HashMap::new(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Stop replicating a synchronizer
+    pub fn unregister(&self, target: &SyncTarget) {
+        self.synchronizers.remove(target);
+    }
+
+    /// Update a property which is sent every process
+    pub fn set_sync_property(
+        &self,
+        target: &SyncTarget,
+        index: usize,
+        value: Arc<Box<dyn Variant>>,
+    ) -> Result<(), ReplicationError> {
+        let mut synchronizer = self.get_synchronizer_mut(target)?;
+
+        let property = synchronizer.sync_properties.get_mut(index).ok_or_else(|| {
+            ReplicationError::NoSyncProperty {
+                target: target.clone(),
+                index,
+            }
+        })?;
+
+        *property = value;
+
+        Ok(())
+    }
+
+    /// Update a property which is only sent when changed
+    pub fn set_watch_property(
+        &self,
+        target: &SyncTarget,
+        index: usize,
+        value: Arc<Box<dyn Variant>>,
+    ) -> Result<(), ReplicationError> {
+        let mut synchronizer = self.get_synchronizer_mut(target)?;
+
+        let property = synchronizer
+            .watch_properties
+            .get_mut(index)
+            .ok_or_else(|| ReplicationError::NoWatchProperty {
+                target: target.clone(),
+                index,
+            })?;
+
+        if property.0 != value {
+            // Taken while holding the synchronizer, so it is newer than any change process has sent
+            let change = self.changes.fetch_add(1, Ordering::AcqRel) + 1;
+
+            *property = (value, change);
+        }
+
+        Ok(())
+    }
+
+    /// Get the properties which are sent every process
+    pub fn get_sync_properties(&self, target: &SyncTarget) -> Option<Vec<Arc<Box<dyn Variant>>>> {
+        self.synchronizers
+            .get(target)
+            .map(|synchronizer| synchronizer.sync_properties.clone())
+    }
+
+    /// Get the properties which are only sent when changed
+    pub fn get_watch_properties(&self, target: &SyncTarget) -> Option<Vec<Arc<Box<dyn Variant>>>> {
+        self.synchronizers.get(target).map(|synchronizer| {
+            synchronizer
+                .watch_properties
+                .iter()
+                .map(|(value, _)| value.clone())
+                .collect()
+        })
+    }
+
+    /// Set whether the synchronizer is visible to all peers by default
+    pub fn set_visibility_public(
+        &self,
+        target: &SyncTarget,
+        visible: bool,
+    ) -> Result<(), ReplicationError> {
+        self.get_synchronizer_mut(target)?.public_visibility = visible;
+
+        Ok(())
+    }
+
+    /// Set whether the synchronizer is visible to a peer,
+    /// with peer 0 setting the public visibility like in Godot.
+    pub fn set_visibility_for(
+        &self,
+        target: &SyncTarget,
+        gd_peer: GDPeerID,
+        visible: bool,
+    ) -> Result<(), ReplicationError> {
+        let mut synchronizer = self.get_synchronizer_mut(target)?;
+
+        if *gd_peer == 0 {
+            synchronizer.public_visibility = visible;
+        } else if visible {
+            synchronizer.peer_visibility.insert(self.key(&gd_peer));
+        } else {
+            synchronizer.peer_visibility.remove(&self.key(&gd_peer));
+        }
+
+        Ok(())
+    }
+
+    /// Add a filter which must return true for the synchronizer to be visible to a peer
+    pub fn add_visibility_filter(
+        &self,
+        target: &SyncTarget,
+        filter: VisibilityFilter,
+    ) -> Result<(), ReplicationError> {
+        self.get_synchronizer_mut(target)?
+            .visibility_filters
+            .push(filter);
+
+        Ok(())
+    }
+
+    /// Remove every visibility filter of the synchronizer
+    pub fn clear_visibility_filters(&self, target: &SyncTarget) -> Result<(), ReplicationError> {
+        self.get_synchronizer_mut(target)?
+            .visibility_filters
+            .clear();
+
+        Ok(())
+    }
+
+    /// Whether the synchronizer is visible to a peer
+    pub fn is_visible_to(&self, target: &SyncTarget, gd_peer: GDPeerID) -> bool {
+        self.synchronizers
+            .get(target)
+            .is_some_and(|synchronizer| synchronizer.is_visible_to(self.key(&gd_peer)))
+    }
+
+    fn get_synchronizer_mut(
+        &self,
+        target: &SyncTarget,
+    ) -> Result<dashmap::mapref::one::RefMut<'_, SyncTarget, Synchronizer>, ReplicationError> {
+        self.synchronizers
+            .get_mut(target)
+            .ok_or_else(|| ReplicationError::NotRegistered(target.clone()))
+    }
+
+    /// Push the synchronizer states to every connected peer once.
+    ///
+    /// Should be called at a regular interval, or use [`ReplicationManager::run`].
+    pub fn process(&self) {
+        for peer in self.peers.iter() {
+            let key = *peer.key();
+            let gd_peer = key.1;
+
+            let mut sync_states = Vec::new();
+            let mut delta_states = Vec::new();
+
+            for mut synchronizer in self.synchronizers.iter_mut() {
+                if !synchronizer.is_visible_to(key) {
+                    continue;
+                }
+
+                let Some(net_id) = self.get_net_id(synchronizer.key(), gd_peer, peer.value())
+                else {
+                    // The node is not spawned or the path is not confirmed by the peer yet
+                    continue;
+                };
+
+                // Changes are only made while holding the synchronizer, so none are missed
+                let change = self.changes.load(Ordering::Acquire);
+
+                if !synchronizer.sync_properties.is_empty() {
+                    sync_states.push(SyncState {
+                        net_id,
+                        indexes: None,
+                        values: synchronizer.sync_properties.clone(),
+                    });
+                }
+
+                let last_change = synchronizer
+                    .last_watch_changes
+                    .get(&key)
+                    .copied()
+                    .unwrap_or(0);
+
+                let mut indexes: u64 = 0;
+                let mut values = Vec::new();
+
+                for (i, (value, changed)) in synchronizer.watch_properties.iter().enumerate() {
+                    if *changed > last_change {
+                        indexes |= 1 << i;
+                        values.push(value.clone());
+                    }
+                }
+
+                if indexes != 0 {
+                    delta_states.push(SyncState {
+                        net_id,
+                        indexes: Some(indexes),
+                        values,
+                    });
+                }
+
+                synchronizer.last_watch_changes.insert(key, change);
+            }
+
+            let sync_net_time = peer.last_sent_sync.fetch_add(1, Ordering::AcqRel) + 1;
+
+            if let Err(e) = send_states(
+                SyncSection::Sync { sync_net_time },
+                sync_states,
+                self.sync_mtu,
+                peer.value(),
+            ) {
+                error!("Failed to send sync to Godot Peer {:?}: {}", gd_peer, e);
+            }
+
+            if let Err(e) = send_states(
+                SyncSection::Delta,
+                delta_states,
+                self.delta_mtu,
+                peer.value(),
+            ) {
+                error!("Failed to send delta to Godot Peer {:?}: {}", gd_peer, e);
+            }
+        }
+    }
+
+    /// Call [`ReplicationManager::process`] at a fixed interval, forever.
+    pub async fn run(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+
+            self.process();
+        }
+    }
+
+    fn get_net_id(
+        &self,
+        target: &SyncTarget,
+        gd_peer: GDPeerID,
+        peer: &ReplicationPeer,
+    ) -> Option<u32> {
+        match target {
+            SyncTarget::NetId(net_id) => peer
+                .spawns
+                .as_ref()
+                .filter(|spawns| spawns.is_synchronizer_spawned_on(&gd_peer, *net_id))
+                .map(|_| *net_id),
+            // Godot drops syncs for paths it has not confirmed yet
+            SyncTarget::Path(path) => peer
+                .outgoing_cache
+                .get_confirmed_or_write_id(
+                    &gd_peer,
+                    &peer.enet_peer,
+                    path,
+                    &hash_function_set(&[]),
+                    &peer.tx_outgoing,
+                )
+                .map(|path_id| path_id | NET_ID_PATH_FLAG),
+        }
+    }
+
+    fn add_peer(&self, gd_peer: GDPeerID, peer: ReplicationPeer) {
+        self.peers.insert(self.key(&gd_peer), peer);
+    }
+
+    fn remove_peer(&self, gd_peer: &GDPeerID) {
+        let key = self.key(gd_peer);
+
+        self.peers.remove(&key);
+
+        for mut synchronizer in self.synchronizers.iter_mut() {
+            synchronizer.last_watch_changes.remove(&key);
+        }
+    }
+}
+
+// Based on SceneReplicationInterface::_send_sync and _send_delta, splitting the states by mtu
+fn send_states(
+    section: SyncSection,
+    states: Vec<SyncState>,
+    mtu: usize,
+    peer: &ReplicationPeer,
+) -> Result<(), PacketError> {
+    let mut pending: Vec<SyncState> = Vec::new();
+    let mut pending_size = 0;
+
+    for state in states {
+        let state_size = sync::gen_packet(&SyncCommand {
+            section,
+            states: vec![state.clone()],
+        })?
+        .len();
+
+        if !pending.is_empty() && pending_size + state_size > mtu {
+            send_sync_packet(section, std::mem::take(&mut pending), peer)?;
+            pending_size = 0;
+        }
+
+        pending_size += state_size;
+        pending.push(state);
+    }
+
+    if !pending.is_empty() {
+        send_sync_packet(section, pending, peer)?;
+    }
+
+    Ok(())
+}
+
+fn send_sync_packet(
+    section: SyncSection,
+    states: Vec<SyncState>,
+    peer: &ReplicationPeer,
+) -> Result<(), PacketError> {
+    let raw_packet = sync::gen_packet(&SyncCommand { section, states })?;
+
+    // Godot sends syncs unreliably, and deltas reliably
+    let transfer_mode = match section {
+        SyncSection::Sync { .. } => TransferMode::Unreliable,
+        SyncSection::Delta => TransferMode::Reliable,
+    };
+
+    let outgoing_packet = outgoing::OutgoingPacket::new(
+        peer.enet_peer,
+        GodotChannel::DEFAULT,
+        transfer_mode,
+        raw_packet,
+    )?;
+
+    peer.tx_outgoing
+        .send(outgoing_packet.into())
+        .map_err(|_| PacketError::ChannelClosed)?;
+
+    Ok(())
+}
+
+#[derive(Default, Clone)]
+/// A [`Layer`](crate::Layer) which tracks connected peers for a [`ReplicationManager`],
+/// and adds the manager to the [`DataPile`](crate::DataPile).
+///
+/// Depends on [`PeerMapLayer`](crate::layers::PeerMapLayer)
+/// and [`PathCacheLayer`](crate::layers::PathCacheLayer).
+pub struct ReplicationLayer {
+    manager: ReplicationManager,
+}
+
+impl ReplicationLayer {
+    pub fn new(manager: ReplicationManager) -> Self {
+        Self { manager }
+    }
+
+    /// Obtain a handle to the layer's [`ReplicationManager`]
+    pub fn manager(&self) -> ReplicationManager {
+        self.manager.clone()
+    }
+}
+
+impl Layer for ReplicationLayer {
+    fn call(&self, mut event: Event) -> LayerReturn {
+        let manager = self.manager.in_shard(event.peer_id.shard());
+
+        return Box::pin(async move {
+            match event.event {
+                EventType::Connect { ref godot_peer } => {
+                    let Some(outgoing_cache) = event.data_pile.get::<OutgoingCache>() else {
+                        return Err(layer_err!(
+                            "Ran without Outgoing Cache in DataPile, requires PathCacheLayer"
+                                .to_string()
+                        ));
+                    };
+
+                    manager.add_peer(
+                        *godot_peer,
+                        ReplicationPeer {
+                            enet_peer: event.peer_id,
+                            outgoing_cache: outgoing_cache.clone(),
+                            spawns: manager.spawns.clone(),
+                            tx_outgoing: event.tx_outgoing.clone(),
+                            last_sent_sync: AtomicU16::new(0),
+                        },
+                    );
+                }
+                EventType::Disconnect { ref godot_peer } => {
+                    manager.remove_peer(godot_peer);
+                }
+                _ => {}
+            }
+
+            event.data_pile.insert(manager);
+
+            return Ok(Some(event));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{packet::Packet, variant::Int};
+    use tokio::sync::mpsc;
+
+    // Parse every packet the manager queued for the peer
+    fn sent_packets(
+        rx_outgoing: &mut mpsc::UnboundedReceiver<outgoing::OutgoingCommand>,
+    ) -> Vec<Packet> {
+        let mut packets = Vec::new();
+
+        while let Ok(command) = rx_outgoing.try_recv() {
+            let outgoing::OutgoingCommand::Send(outgoing) = command else {
+                panic!("Expected a packet, got {:?}", command);
+            };
+
+            packets.push(crate::packet::parse_packet(outgoing.packet.data()).unwrap());
+        }
+
+        packets
+    }
+
+    #[test]
+    fn waits_for_confirmed_path_before_syncing() {
+        let gd_peer = GDPeerID(2);
+        let (tx_outgoing, mut rx_outgoing) = mpsc::unbounded_channel();
+        let outgoing_cache = OutgoingCache::default();
+        outgoing_cache.create_cache_entry(&gd_peer);
+
+        let manager = ReplicationManager::default();
+        manager.add_peer(
+            gd_peer,
+            ReplicationPeer {
+                enet_peer: ENetPeerID(0),
+                outgoing_cache: outgoing_cache.clone(),
+                spawns: None,
+                tx_outgoing,
+                last_sent_sync: AtomicU16::new(0),
+            },
+        );
+        manager
+            .register(
+                SyncTarget::Path("/root/Level/Synchronizer".to_string()),
+                vec![Arc::new(Box::new(Int(1)))],
+                Vec::new(),
+            )
+            .unwrap();
+
+        manager.process();
+        let packets = sent_packets(&mut rx_outgoing);
+        let [
+            Packet::NetworkCommandSimplifyPath {
+                remote_cache_id, ..
+            },
+        ] = packets.as_slice()
+        else {
+            panic!("Expected only the SimplifyPath packet, got {:?}", packets);
+        };
+
+        // Cached but not confirmed, so the peer could not resolve the sync
+        manager.process();
+        assert!(sent_packets(&mut rx_outgoing).is_empty());
+
+        outgoing_cache.confirm(&gd_peer, *remote_cache_id);
+        manager.process();
+        let packets = sent_packets(&mut rx_outgoing);
+        let [Packet::NetworkCommandSync(command)] = packets.as_slice() else {
+            panic!("Expected a sync packet, got {:?}", packets);
+        };
+        assert_eq!(
+            command.states[0].net_id,
+            *remote_cache_id | NET_ID_PATH_FLAG
+        );
+    }
+}
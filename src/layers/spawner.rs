@@ -0,0 +1,438 @@
+use crate::{
+    ENetPeerID, GDPeerID, Layer, LayerReturn,
+    event::{Event, EventType},
+    layer_err,
+    layers::{OutgoingCache, SpawnError},
+    packet::{
+        PacketError,
+        channel::{GodotChannel, TransferMode},
+        despawn, outgoing,
+        spawn::{self, CUSTOM_SPAWN_SCENE_ID, SpawnCommand},
+    },
+    routers::hash_function_set,
+    variant::Variant,
+};
+use dashmap::{DashMap, DashSet};
+use log::{debug, error};
+use std::sync::{
+    Arc,
+    atomic::{AtomicU32, Ordering},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+#[derive(Clone, Debug)]
+/// A node spawned through the [`SpawnRegistry`]
+pub struct SpawnedNode {
+    /// Path of the `MultiplayerSpawner` which spawns the node.
+    pub spawner_path: String,
+    /// Index into the spawner's spawnable scenes,
+    /// or [`CUSTOM_SPAWN_SCENE_ID`] for custom spawns.
+    pub scene_id: u8,
+    /// Name of the spawned node.
+    pub name: String,
+    /// Argument passed to the custom spawn function, only present for custom spawns.
+    pub spawn_arg: Option<Arc<Box<dyn Variant>>>,
+    /// Network IDs of the synchronizers attached to the spawned node.
+    pub sync_ids: Vec<u32>,
+    /// Spawn properties of the node's synchronizers, in order.
+    pub state: Vec<Arc<Box<dyn Variant>>>,
+}
+
+#[derive(Clone)]
+struct SpawnPeer {
+    enet_peer: ENetPeerID,
+
+    outgoing_cache: OutgoingCache,
+
+    tx_outgoing: UnboundedSender<outgoing::OutgoingCommand>,
+}
+
+#[derive(Default, Clone)]
+/// Emulates Godot's `MultiplayerSpawner` on the server,
+/// tracking which spawned nodes exist on which peer.
+///
+/// Peers are added and removed by the [`SpawnerLayer`](crate::layers::SpawnerLayer),
+/// and namespaced by shard like the [`PeerMap`](crate::layers::PeerMap).
+pub struct SpawnRegistry {
+    shard: usize,
+
+    spawners: Arc<DashMap<String, Vec<String>>>,
+
+    nodes: Arc<DashMap<u32, SpawnedNode>>,
+
+    peers: Arc<DashMap<(usize, GDPeerID), SpawnPeer>>,
+    peer_nodes: Arc<DashMap<(usize, GDPeerID), DashSet<u32>>>,
+    /// Sync ids of the nodes whose spawn was sent to each peer.
+    peer_synchronizers: Arc<DashMap<(usize, GDPeerID), DashSet<u32>>>,
+
+    last_net_id: Arc<AtomicU32>,
+}
+
+impl SpawnRegistry {
+    /// Obtain a handle to the same registry which looks up the Godot peers of a shard
+    pub fn in_shard(&self, shard: usize) -> Self {
+        Self {
+            shard,
+
+            ..self.clone()
+        }
+    }
+
+    fn key(&self, gd_peer: &GDPeerID) -> (usize, GDPeerID) {
+        (self.shard, *gd_peer)
+    }
+
+    /// Declare a spawner and its spawnable scenes.
+    ///
+    /// The scenes must be in the same order as in the spawner's `_spawnable_scenes` in Godot.
+    pub fn register_spawner(&self, spawner_path: &str, scenes: &[String]) {
+        self.spawners
+            .insert(spawner_path.to_string(), scenes.to_vec());
+    }
+
+    /// Get the scene id of a spawnable scene within a spawner
+    pub fn get_scene_id(&self, spawner_path: &str, scene: &str) -> Option<u8> {
+        let scenes = self.spawners.get(spawner_path)?;
+
+        scenes
+            .iter()
+            .position(|s| s == scene)
+            .filter(|pos| *pos < CUSTOM_SPAWN_SCENE_ID as usize)
+            .map(|pos| pos as u8)
+    }
+
+    /// Generate a new network id, shared by spawned nodes and synchronizers like in Godot
+    pub fn next_net_id(&self) -> u32 {
+        self.last_net_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Spawn a spawnable scene on every connected peer.
+    ///
+    /// `synchronizers` is the number of synchronizers attached to the scene,
+    /// and `state` is the spawn properties of those synchronizers.
+    ///
+    /// Returns the net id of the spawned node.
+    pub fn spawn(
+        &self,
+        spawner_path: &str,
+        scene: &str,
+        name: &str,
+        synchronizers: usize,
+        state: Vec<Arc<Box<dyn Variant>>>,
+    ) -> Result<u32, SpawnError> {
+        let scene_id = self.get_scene_id(spawner_path, scene).ok_or_else(|| {
+            SpawnError::SceneNotSpawnable {
+                spawner_path: spawner_path.to_string(),
+                scene: scene.to_string(),
+            }
+        })?;
+
+        self.spawn_node(SpawnedNode {
+            spawner_path: spawner_path.to_string(),
+            scene_id,
+            name: name.to_string(),
+            spawn_arg: None,
+            sync_ids: (0..synchronizers).map(|_| self.next_net_id()).collect(),
+            state,
+        })
+    }
+
+    /// Spawn a node with the spawner's custom spawn function on every connected peer.
+    ///
+    /// `synchronizers` is the number of synchronizers attached to the node,
+    /// and `state` is the spawn properties of those synchronizers.
+    ///
+    /// Returns the net id of the spawned node.
+    pub fn spawn_custom(
+        &self,
+        spawner_path: &str,
+        name: &str,
+        spawn_arg: Arc<Box<dyn Variant>>,
+        synchronizers: usize,
+        state: Vec<Arc<Box<dyn Variant>>>,
+    ) -> Result<u32, SpawnError> {
+        if !self.spawners.contains_key(spawner_path) {
+            return Err(SpawnError::SpawnerNotRegistered(spawner_path.to_string()));
+        }
+
+        self.spawn_node(SpawnedNode {
+            spawner_path: spawner_path.to_string(),
+            scene_id: CUSTOM_SPAWN_SCENE_ID,
+            name: name.to_string(),
+            spawn_arg: Some(spawn_arg),
+            sync_ids: (0..synchronizers).map(|_| self.next_net_id()).collect(),
+            state,
+        })
+    }
+
+    /// Track a node and spawn it on every connected peer
+    ///
+    /// Returns the net id of the spawned node.
+    pub fn spawn_node(&self, node: SpawnedNode) -> Result<u32, SpawnError> {
+        if node.name.is_empty() {
+            return Err(SpawnError::MissingName);
+        }
+
+        let net_id = self.next_net_id();
+
+        self.nodes.insert(net_id, node);
+
+        debug!("Spawned node with net id {}", net_id);
+
+        for peer in self.peers.iter() {
+            if let Err(e) = self.send_spawn(peer.key(), peer.value(), net_id) {
+                error!(
+                    "Failed to spawn node {} on Godot Peer {:?}: {}",
+                    net_id,
+                    peer.key().1,
+                    e
+                );
+            }
+        }
+
+        Ok(net_id)
+    }
+
+    /// Stop tracking a node and despawn it from every peer it exists on.
+    pub fn despawn(&self, net_id: u32) -> Result<SpawnedNode, SpawnError> {
+        let (_, node) = self
+            .nodes
+            .remove(&net_id)
+            .ok_or(SpawnError::NotSpawned(net_id))?;
+
+        for peer in self.peers.iter() {
+            if let Err(e) = self.send_despawn(peer.key(), peer.value(), net_id, &node.sync_ids) {
+                error!(
+                    "Failed to despawn node {} on Godot Peer {:?}: {}",
+                    net_id,
+                    peer.key().1,
+                    e
+                );
+            }
+        }
+
+        debug!("Despawned node with net id {}", net_id);
+
+        Ok(node)
+    }
+
+    /// Get a spawned node by its net id
+    pub fn get_node(&self, net_id: u32) -> Option<SpawnedNode> {
+        self.nodes.get(&net_id).map(|entry| entry.value().clone())
+    }
+
+    /// Get the net ids of every spawned node
+    pub fn get_nodes(&self) -> Vec<u32> {
+        self.nodes.iter().map(|entry| *entry.key()).collect()
+    }
+
+    /// Get the net ids of the spawned nodes which exist on a peer
+    pub fn get_peer_nodes(&self, gd_peer: &GDPeerID) -> Vec<u32> {
+        self.peer_nodes
+            .get(&self.key(gd_peer))
+            .map(|nodes| nodes.iter().map(|net_id| *net_id).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a spawned node exists on a peer
+    pub fn is_spawned_on(&self, gd_peer: &GDPeerID, net_id: u32) -> bool {
+        self.peer_nodes
+            .get(&self.key(gd_peer))
+            .is_some_and(|nodes| nodes.contains(&net_id))
+    }
+
+    /// Whether the spawn of the node a synchronizer belongs to was sent to a peer
+    pub fn is_synchronizer_spawned_on(&self, gd_peer: &GDPeerID, sync_id: u32) -> bool {
+        self.peer_synchronizers
+            .get(&self.key(gd_peer))
+            .is_some_and(|synchronizers| synchronizers.contains(&sync_id))
+    }
+
+    /// Start tracking a peer and replay every existing spawn to it
+    fn add_peer(&self, gd_peer: GDPeerID, peer: SpawnPeer) {
+        let key = self.key(&gd_peer);
+
+        self.peer_nodes.insert(key, DashSet::new());
+        self.peer_synchronizers.insert(key, DashSet::new());
+        self.peers.insert(key, peer.clone());
+
+        for net_id in self.get_nodes() {
+            if let Err(e) = self.send_spawn(&key, &peer, net_id) {
+                error!(
+                    "Failed to replay spawn of node {} to Godot Peer {:?}: {}",
+                    net_id, gd_peer, e
+                );
+            }
+        }
+    }
+
+    fn remove_peer(&self, gd_peer: &GDPeerID) {
+        let key = self.key(gd_peer);
+
+        self.peers.remove(&key);
+        self.peer_nodes.remove(&key);
+        self.peer_synchronizers.remove(&key);
+    }
+
+    fn send_spawn(
+        &self,
+        key: &(usize, GDPeerID),
+        peer: &SpawnPeer,
+        net_id: u32,
+    ) -> Result<(), SpawnError> {
+        let Some(peer_nodes) = self.peer_nodes.get(key) else {
+            return Err(SpawnError::PeerNotTracked(key.1));
+        };
+
+        // Guards against spawning twice when a spawn races a replay
+        if !peer_nodes.insert(net_id) {
+            return Ok(());
+        }
+
+        let Some(node) = self.get_node(net_id) else {
+            // Despawned in the meantime
+            peer_nodes.remove(&net_id);
+
+            return Ok(());
+        };
+
+        let spawner_id = peer
+            .outgoing_cache
+            .get_or_write_id_ordered(
+                &key.1,
+                &peer.enet_peer,
+                &node.spawner_path,
+                &hash_function_set(&[]),
+                &peer.tx_outgoing,
+            )
+            .ok_or_else(|| SpawnError::SpawnerNotCached(node.spawner_path.clone()))?;
+
+        let sync_ids = node.sync_ids.clone();
+
+        let raw_packet = spawn::gen_packet(&SpawnCommand {
+            scene_id: node.scene_id,
+            spawner_id,
+            net_id,
+            sync_ids: node.sync_ids,
+            name: node.name,
+
+            spawn_arg: node.spawn_arg,
+            state: node.state,
+        })?;
+
+        peer.tx_outgoing
+            .send(outgoing::OutgoingCommand::Send(
+                outgoing::OutgoingPacket::new(
+                    peer.enet_peer,
+                    GodotChannel::DEFAULT,
+                    TransferMode::Reliable,
+                    raw_packet,
+                )?,
+            ))
+            .map_err(|_| PacketError::ChannelClosed)?;
+
+        // Syncs queued from now on arrive after the spawn
+        if let Some(synchronizers) = self.peer_synchronizers.get(key) {
+            for sync_id in sync_ids {
+                synchronizers.insert(sync_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send_despawn(
+        &self,
+        key: &(usize, GDPeerID),
+        peer: &SpawnPeer,
+        net_id: u32,
+        sync_ids: &[u32],
+    ) -> Result<(), SpawnError> {
+        if let Some(synchronizers) = self.peer_synchronizers.get(key) {
+            for sync_id in sync_ids {
+                synchronizers.remove(sync_id);
+            }
+        }
+
+        let Some(peer_nodes) = self.peer_nodes.get(key) else {
+            return Ok(());
+        };
+
+        if peer_nodes.remove(&net_id).is_none() {
+            return Ok(());
+        }
+
+        let raw_packet = despawn::gen_packet(net_id)?;
+
+        peer.tx_outgoing
+            .send(outgoing::OutgoingCommand::Send(
+                outgoing::OutgoingPacket::new(
+                    peer.enet_peer,
+                    GodotChannel::DEFAULT,
+                    TransferMode::Reliable,
+                    raw_packet,
+                )?,
+            ))
+            .map_err(|_| PacketError::ChannelClosed)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Default, Clone)]
+/// A [`Layer`](crate::Layer) which tracks connected peers for a [`SpawnRegistry`],
+/// replaying existing spawns to newly connected peers,
+/// and adds the registry to the [`DataPile`](crate::DataPile).
+///
+/// Depends on [`PeerMapLayer`](crate::layers::PeerMapLayer)
+/// and [`PathCacheLayer`](crate::layers::PathCacheLayer).
+pub struct SpawnerLayer {
+    registry: SpawnRegistry,
+}
+
+impl SpawnerLayer {
+    pub fn new(registry: SpawnRegistry) -> Self {
+        Self { registry }
+    }
+
+    /// Obtain a handle to the layer's [`SpawnRegistry`]
+    pub fn registry(&self) -> SpawnRegistry {
+        self.registry.clone()
+    }
+}
+
+impl Layer for SpawnerLayer {
+    fn call(&self, mut event: Event) -> LayerReturn {
+        let registry = self.registry.in_shard(event.peer_id.shard());
+
+        return Box::pin(async move {
+            match event.event {
+                EventType::Connect { ref godot_peer } => {
+                    let Some(outgoing_cache) = event.data_pile.get::<OutgoingCache>() else {
+                        return Err(layer_err!(
+                            "Ran without Outgoing Cache in DataPile, requires PathCacheLayer"
+                                .to_string()
+                        ));
+                    };
+
+                    registry.add_peer(
+                        *godot_peer,
+                        SpawnPeer {
+                            enet_peer: event.peer_id,
+                            outgoing_cache: outgoing_cache.clone(),
+                            tx_outgoing: event.tx_outgoing.clone(),
+                        },
+                    );
+                }
+                EventType::Disconnect { ref godot_peer } => {
+                    registry.remove_peer(godot_peer);
+                }
+                _ => {}
+            }
+
+            event.data_pile.insert(registry);
+
+            return Ok(Some(event));
+        });
+    }
+}
@@ -0,0 +1,432 @@
+use crate::{
+    ENetPeerID, GDPeerID, Layer, LayerReturn,
+    event::{Event, EventType},
+    layer_err,
+    layers::{PathCache, SyncTarget},
+    packet::{
+        Packet, gen_packet,
+        sync::{NET_ID_PATH_FLAG, SyncCommand, SyncSection, SyncState},
+    },
+    variant::{Int, Transform3D, Variant, Vector3},
+};
+use dashmap::DashMap;
+use log::warn;
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A replicated property of a synchronizer
+pub enum SyncProperty {
+    /// Index into the properties sent every sync.
+    Sync(usize),
+    /// Index into the watched properties sent in deltas.
+    Watch(usize),
+}
+
+/// The outcome of validating a single property update
+pub enum SyncValidation {
+    /// The update is valid and kept as is.
+    Accept,
+    /// The update is replaced with the given value.
+    Clamp(Arc<Box<dyn Variant>>),
+    /// The update is invalid and the state containing it is dropped.
+    Reject,
+}
+
+/// Validates property updates sent by clients in sync packets
+pub trait SyncValidator: Send + Sync + 'static {
+    /// Validate a property update.
+    ///
+    /// `previous` is the last accepted value from the same peer,
+    /// and the time elapsed since it was accepted.
+    fn validate(
+        &self,
+        value: &dyn Variant,
+        previous: Option<(&dyn Variant, Duration)>,
+    ) -> SyncValidation;
+}
+
+/// Called with every violation detected by a [`SyncValidationLayer`]
+pub type ViolationReporter = Arc<dyn Fn(&SyncViolation) + Send + Sync>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A property update which failed validation
+pub struct SyncViolation {
+    /// The peer which sent the update.
+    pub gd_peer: GDPeerID,
+    /// The synchronizer the update was sent for.
+    pub target: SyncTarget,
+    /// The property which failed validation.
+    pub property: SyncProperty,
+    /// Whether the update was clamped rather than dropped.
+    pub clamped: bool,
+}
+
+/// Limits each axis of a `Vector3` property to a range.
+pub struct Vector3RangeValidator {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+
+    /// Whether to clamp values out of range instead of rejecting them.
+    pub clamp: bool,
+}
+
+impl SyncValidator for Vector3RangeValidator {
+    fn validate(
+        &self,
+        value: &dyn Variant,
+        _previous: Option<(&dyn Variant, Duration)>,
+    ) -> SyncValidation {
+        let Some(vector) = value.as_any().downcast_ref::<Vector3>() else {
+            return SyncValidation::Reject;
+        };
+
+        let axes = [*vector.x, *vector.y, *vector.z];
+
+        if axes.iter().any(|axis| axis.is_nan()) {
+            return SyncValidation::Reject;
+        }
+
+        let clamped = [0, 1, 2].map(|i| axes[i].clamp(self.min[i], self.max[i]));
+
+        if axes == clamped {
+            SyncValidation::Accept
+        } else if self.clamp {
+            SyncValidation::Clamp(Arc::new(Box::new(Vector3 {
+                x: clamped[0].into(),
+                y: clamped[1].into(),
+                z: clamped[2].into(),
+            })))
+        } else {
+            SyncValidation::Reject
+        }
+    }
+}
+
+/// Limits how fast the origin of a `Transform3D` property can move, in units per second.
+pub struct Transform3DRateValidator {
+    pub max_speed: f64,
+
+    /// Whether to limit the movement of values moving too fast instead of rejecting them.
+    pub clamp: bool,
+}
+
+impl SyncValidator for Transform3DRateValidator {
+    fn validate(
+        &self,
+        value: &dyn Variant,
+        previous: Option<(&dyn Variant, Duration)>,
+    ) -> SyncValidation {
+        let Some(transform) = value.as_any().downcast_ref::<Transform3D>() else {
+            return SyncValidation::Reject;
+        };
+
+        let origin = transform.origin.map(|axis| *axis);
+
+        if origin.iter().any(|axis| !axis.is_finite()) {
+            return SyncValidation::Reject;
+        }
+
+        let Some((previous, elapsed)) = previous else {
+            return SyncValidation::Accept;
+        };
+
+        let Some(previous) = previous.as_any().downcast_ref::<Transform3D>() else {
+            return SyncValidation::Accept;
+        };
+
+        let previous_origin = previous.origin.map(|axis| *axis);
+
+        let offset = [0, 1, 2].map(|i| origin[i] - previous_origin[i]);
+        let distance = offset.iter().map(|axis| axis * axis).sum::<f64>().sqrt();
+        let max_distance = self.max_speed * elapsed.as_secs_f64();
+
+        if distance <= max_distance {
+            SyncValidation::Accept
+        } else if self.clamp {
+            let scale = max_distance / distance;
+
+            SyncValidation::Clamp(Arc::new(Box::new(Transform3D {
+                basis: transform.basis,
+                origin: [0, 1, 2].map(|i| (previous_origin[i] + offset[i] * scale).into()),
+            })))
+        } else {
+            SyncValidation::Reject
+        }
+    }
+}
+
+/// Only allows an `Int` property to take one of the listed values, such as the values of an enum.
+pub struct IntWhitelistValidator {
+    pub allowed: HashSet<i64>,
+}
+
+impl SyncValidator for IntWhitelistValidator {
+    fn validate(
+        &self,
+        value: &dyn Variant,
+        _previous: Option<(&dyn Variant, Duration)>,
+    ) -> SyncValidation {
+        match value.as_any().downcast_ref::<Int>() {
+            Some(int) if self.allowed.contains(&int.0) => SyncValidation::Accept,
+            _ => SyncValidation::Reject,
+        }
+    }
+}
+
+type ValidatorMap = DashMap<(SyncTarget, SyncProperty), Vec<Arc<dyn SyncValidator>>>;
+// Keyed by ENet peer, as Godot Peer IDs are only unique within a shard
+type PreviousMap =
+    DashMap<(ENetPeerID, SyncTarget, SyncProperty), (Arc<Box<dyn Variant>>, Instant)>;
+
+#[derive(Default, Clone)]
+/// A [`Layer`](crate::Layer) which validates the properties of sync packets sent by clients,
+/// clamping or dropping the updates which fail their validators.
+///
+/// The validated [`SyncCommand`] replaces the parsed packet in the [`DataPile`](crate::DataPile),
+/// and the raw packet is regenerated from it if anything changed.
+/// Violations are reported to the reporter and added to the [`DataPile`](crate::DataPile)
+/// as a `Vec<SyncViolation>`.
+///
+/// Depends on [`AutoParseLayer`](crate::layers::AutoParseLayer),
+/// [`PeerMapLayer`](crate::layers::PeerMapLayer),
+/// and [`PathCacheLayer`](crate::layers::PathCacheLayer).
+pub struct SyncValidationLayer {
+    validators: Arc<ValidatorMap>,
+
+    previous: Arc<PreviousMap>,
+
+    reporter: Option<ViolationReporter>,
+}
+
+impl SyncValidationLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a validator to a property, validators of the same property run in the order they were added
+    pub fn validator(
+        self,
+        target: SyncTarget,
+        property: SyncProperty,
+        validator: impl SyncValidator,
+    ) -> Self {
+        self.validators
+            .entry((target, property))
+            .or_default()
+            .push(Arc::new(validator));
+
+        self
+    }
+
+    /// Set the function called with every violation
+    pub fn reporter(mut self, reporter: ViolationReporter) -> Self {
+        self.reporter = Some(reporter);
+
+        self
+    }
+
+    fn validate_state(
+        &self,
+        enet_peer: ENetPeerID,
+        gd_peer: GDPeerID,
+        target: &SyncTarget,
+        state: &mut SyncState,
+        violations: &mut Vec<SyncViolation>,
+    ) -> bool {
+        let now = Instant::now();
+
+        let properties: Vec<SyncProperty> = match state.indexes {
+            None => (0..state.values.len()).map(SyncProperty::Sync).collect(),
+            Some(indexes) => (0..64)
+                .filter(|i| indexes & (1 << i) != 0)
+                .map(SyncProperty::Watch)
+                .collect(),
+        };
+
+        let mut accepted = Vec::new();
+
+        for (value, property) in state.values.iter_mut().zip(properties) {
+            let key = (target.clone(), property);
+
+            let Some(validators) = self.validators.get(&key) else {
+                continue;
+            };
+
+            let previous_key = (enet_peer, key.0, key.1);
+            let previous = self.previous.get(&previous_key).map(|entry| entry.clone());
+
+            let mut clamped = false;
+
+            for validator in validators.iter() {
+                let result = validator.validate(
+                    value.as_ref().as_ref(),
+                    previous
+                        .as_ref()
+                        .map(|(value, time)| (value.as_ref().as_ref(), now - *time)),
+                );
+
+                match result {
+                    SyncValidation::Accept => {}
+                    SyncValidation::Clamp(new_value) => {
+                        *value = new_value;
+                        clamped = true;
+                    }
+                    SyncValidation::Reject => {
+                        violations.push(SyncViolation {
+                            gd_peer,
+                            target: previous_key.1,
+                            property: previous_key.2,
+                            clamped: false,
+                        });
+
+                        return false;
+                    }
+                }
+            }
+
+            if clamped {
+                violations.push(SyncViolation {
+                    gd_peer,
+                    target: previous_key.1.clone(),
+                    property: previous_key.2.clone(),
+                    clamped: true,
+                });
+            }
+
+            accepted.push((previous_key, value.clone()));
+        }
+
+        // Only remember values once the whole state is accepted
+        for (key, value) in accepted {
+            self.previous.insert(key, (value, now));
+        }
+
+        true
+    }
+}
+
+impl Layer for SyncValidationLayer {
+    fn call(&self, mut event: Event) -> LayerReturn {
+        let layer = self.clone();
+
+        return Box::pin(async move {
+            if let EventType::Disconnect { .. } = event.event {
+                layer.previous.retain(|key, _| key.0 != event.peer_id);
+
+                return Ok(Some(event));
+            }
+
+            let EventType::Receive {
+                channel,
+                transfer_mode,
+                ..
+            } = event.event
+            else {
+                return Ok(Some(event));
+            };
+
+            let Some(parsed_packet) = event.data_pile.get::<Packet>() else {
+                return Err(layer_err!(
+                    "Ran without parsed packet, requires AutoParseLayer".to_string()
+                ));
+            };
+
+            let Packet::NetworkCommandSync(command) = parsed_packet else {
+                return Ok(Some(event));
+            };
+
+            let Some(gd_peer) = event.data_pile.get::<GDPeerID>() else {
+                return Err(layer_err!(
+                    "Ran without Godot Peer ID in DataPile, requires PeerMapLayer".to_string()
+                ));
+            };
+
+            let Some(path_cache) = event.data_pile.get::<PathCache>() else {
+                return Err(layer_err!(
+                    "Ran without Path Cache in DataPile, requires PathCacheLayer".to_string()
+                ));
+            };
+
+            let gd_peer = *gd_peer;
+            let section: SyncSection = command.section;
+
+            let mut states = Vec::new();
+            let mut violations = Vec::new();
+
+            for mut state in command.states.clone() {
+                // Empty states carry nothing to validate, and can not be regenerated
+                if state.values.is_empty() {
+                    continue;
+                }
+
+                let target = if state.is_path_id() {
+                    match path_cache.get_path(&gd_peer, &(state.net_id & !NET_ID_PATH_FLAG)) {
+                        Some(path) => SyncTarget::Path(path),
+                        // Godot ignores states for uncached paths as well
+                        None => continue,
+                    }
+                } else {
+                    SyncTarget::NetId(state.net_id)
+                };
+
+                if layer.validate_state(
+                    event.peer_id,
+                    gd_peer,
+                    &target,
+                    &mut state,
+                    &mut violations,
+                ) {
+                    states.push(state);
+                }
+            }
+
+            if violations.is_empty() {
+                return Ok(Some(event));
+            }
+
+            for violation in &violations {
+                warn!(
+                    "Godot Peer {:?} sent an invalid update of {:?} in {:?}, {}",
+                    violation.gd_peer,
+                    violation.property,
+                    violation.target,
+                    if violation.clamped {
+                        "clamped"
+                    } else {
+                        "dropped"
+                    }
+                );
+
+                if let Some(reporter) = &layer.reporter {
+                    reporter(violation);
+                }
+            }
+
+            event.data_pile.insert(violations);
+
+            if states.is_empty() {
+                return Ok(None);
+            }
+
+            let validated = Packet::NetworkCommandSync(SyncCommand { section, states });
+
+            let raw_packet = gen_packet(&validated).map_err(|e| {
+                layer_err!("Failed to regenerate validated sync packet: {}", e).with_source(e)
+            })?;
+
+            event.event = EventType::Receive {
+                channel,
+                transfer_mode,
+                raw_packet: transfer_mode.unwrap_or_default().packet(raw_packet),
+            };
+
+            event.data_pile.insert(validated);
+
+            return Ok(Some(event));
+        });
+    }
+}
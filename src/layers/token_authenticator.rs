@@ -0,0 +1,492 @@
+use crate::{
+    DataPile, ENetPeerID,
+    layers::{AuthReturn, AuthStep, Authenticator, TokenError},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, KeyInit, Mac};
+use log::warn;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Length of an HMAC-SHA256 tag
+const HMAC_TAG_SIZE: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// Claims of a verified token,
+/// added to the [`DataPile`](crate::DataPile) of every event from the authenticated peer.
+pub struct AuthClaims {
+    pub user_id: String,
+    pub roles: Vec<String>,
+    /// Unix timestamp in seconds after which the token is no longer valid.
+    pub expires_at: Option<u64>,
+}
+
+/// Verifies the token sent by a peer as its authentication message
+pub trait TokenVerifier: Send + Sync + 'static {
+    fn verify(&self, token: &[u8]) -> Result<AuthClaims, TokenError>;
+}
+
+/// An [`Authenticator`] which accepts peers sending a token accepted by its [`TokenVerifier`]
+pub struct TokenAuthenticator<V: TokenVerifier> {
+    verifier: Arc<V>,
+}
+
+impl<V: TokenVerifier> TokenAuthenticator<V> {
+    pub fn new(verifier: V) -> Self {
+        Self {
+            verifier: Arc::new(verifier),
+        }
+    }
+}
+
+impl<V: TokenVerifier> Authenticator for TokenAuthenticator<V> {
+    fn message(&self, peer: ENetPeerID, message: Box<[u8]>, _data_pile: DataPile) -> AuthReturn {
+        let verifier = self.verifier.clone();
+
+        Box::pin(async move {
+            match verifier.verify(&message) {
+                Ok(claims) => {
+                    let mut peer_data = DataPile::new();
+                    peer_data.insert(claims);
+
+                    AuthStep::Accept {
+                        messages: vec![],
+                        peer_data,
+                    }
+                }
+                Err(e) => {
+                    warn!("Peer {:?} sent an invalid token: {}", peer, e);
+
+                    AuthStep::Reject
+                }
+            }
+        })
+    }
+}
+
+/// Verifies HMAC-SHA256 signed tokens.
+///
+/// The token is the UTF-8 payload `user_id;expires_at;role1,role2`,
+/// followed by the 32 byte HMAC-SHA256 of the payload.
+/// Can be built in Godot with `HMACContext` and `PackedByteArray.append_array`.
+pub struct HmacTokenVerifier {
+    key: Vec<u8>,
+
+    /// Tolerated clock difference when checking the expiry.
+    pub leeway: Duration,
+}
+
+impl HmacTokenVerifier {
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            key: key.to_vec(),
+
+            leeway: Duration::ZERO,
+        }
+    }
+
+    /// Generate a token for the claims, which must have an expiry
+    pub fn sign(&self, claims: &AuthClaims) -> Result<Vec<u8>, TokenError> {
+        let Some(expires_at) = claims.expires_at else {
+            return Err(TokenError::Unsignable("HMAC tokens must have an expiry"));
+        };
+
+        if claims.user_id.contains(';') || claims.roles.iter().any(|role| role.contains([';', ',']))
+        {
+            return Err(TokenError::Unsignable(
+                "HMAC token claims must not contain separators",
+            ));
+        }
+
+        let mut token = format!(
+            "{};{};{}",
+            claims.user_id,
+            expires_at,
+            claims.roles.join(",")
+        )
+        .into_bytes();
+
+        let mut mac = new_mac(&self.key)?;
+        mac.update(&token);
+        token.extend(mac.finalize().into_bytes());
+
+        Ok(token)
+    }
+}
+
+impl TokenVerifier for HmacTokenVerifier {
+    fn verify(&self, token: &[u8]) -> Result<AuthClaims, TokenError> {
+        if token.len() < HMAC_TAG_SIZE {
+            return Err(TokenError::Malformed(
+                "Token too short to contain signature",
+            ));
+        }
+
+        let (payload, tag) = token.split_at(token.len() - HMAC_TAG_SIZE);
+
+        let mut mac = new_mac(&self.key)?;
+        mac.update(payload);
+        mac.verify_slice(tag)
+            .map_err(|_| TokenError::InvalidSignature)?;
+
+        let payload = str::from_utf8(payload).map_err(|_| TokenError::InvalidUtf8("payload"))?;
+
+        let mut fields = payload.splitn(3, ';');
+
+        let (Some(user_id), Some(expires_at), Some(roles)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(TokenError::Malformed("Token payload is missing fields"));
+        };
+
+        let expires_at = expires_at
+            .parse::<u64>()
+            .map_err(|_| TokenError::InvalidClaim("Invalid expiry in token payload"))?;
+
+        check_expiry(expires_at, self.leeway)?;
+
+        Ok(AuthClaims {
+            user_id: user_id.to_string(),
+            roles: roles
+                .split(',')
+                .filter(|role| !role.is_empty())
+                .map(|role| role.to_string())
+                .collect(),
+            expires_at: Some(expires_at),
+        })
+    }
+}
+
+/// Verifies JWT-style compact tokens signed with HS256.
+///
+/// The user id is read from the `sub` claim, the roles from the `roles` claim,
+/// and the expiry from the `exp` claim. The `nbf` claim is checked if present.
+pub struct JwtVerifier {
+    key: Vec<u8>,
+
+    /// Tolerated clock difference when checking the expiry.
+    pub leeway: Duration,
+
+    /// Whether to reject tokens without an `exp` claim.
+    pub require_expiry: bool,
+}
+
+impl JwtVerifier {
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            key: key.to_vec(),
+
+            leeway: Duration::ZERO,
+            require_expiry: true,
+        }
+    }
+
+    /// Generate a token for the claims
+    pub fn sign(&self, claims: &AuthClaims) -> Result<Vec<u8>, TokenError> {
+        let header = json!({ "alg": "HS256", "typ": "JWT" });
+
+        let mut payload = json!({ "sub": claims.user_id, "roles": claims.roles });
+        if let Some(expires_at) = claims.expires_at {
+            payload["exp"] = json!(expires_at);
+        }
+
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(payload.to_string())
+        );
+
+        let mut mac = new_mac(&self.key)?;
+        mac.update(signing_input.as_bytes());
+
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+        Ok(format!("{}.{}", signing_input, signature).into_bytes())
+    }
+}
+
+impl TokenVerifier for JwtVerifier {
+    fn verify(&self, token: &[u8]) -> Result<AuthClaims, TokenError> {
+        let token = str::from_utf8(token).map_err(|_| TokenError::InvalidUtf8("token"))?;
+
+        let parts: Vec<&str> = token.split('.').collect();
+        let [header, payload, signature] = parts[..] else {
+            return Err(TokenError::Malformed("Token must have exactly three parts"));
+        };
+
+        let header = decode_json(header, "header")?;
+
+        // Only HS256 is accepted, to prevent algorithm confusion
+        let algorithm = header.get("alg").and_then(Value::as_str);
+        if algorithm != Some("HS256") {
+            return Err(TokenError::UnsupportedAlgorithm(
+                algorithm.map(|algorithm| algorithm.to_string()),
+            ));
+        }
+
+        let signature =
+            URL_SAFE_NO_PAD
+                .decode(signature)
+                .map_err(|source| TokenError::InvalidBase64 {
+                    part: "signature",
+                    source,
+                })?;
+
+        let signing_input = &token[..(token.len() - parts[2].len() - 1)];
+
+        let mut mac = new_mac(&self.key)?;
+        mac.update(signing_input.as_bytes());
+        mac.verify_slice(&signature)
+            .map_err(|_| TokenError::InvalidSignature)?;
+
+        let payload = decode_json(payload, "payload")?;
+
+        let Some(user_id) = payload.get("sub").and_then(Value::as_str) else {
+            return Err(TokenError::MissingClaim("sub"));
+        };
+
+        let roles = match payload.get("roles") {
+            None => vec![],
+            Some(Value::Array(roles)) => roles
+                .iter()
+                .map(|role| role.as_str().map(|role| role.to_string()))
+                .collect::<Option<Vec<String>>>()
+                .ok_or(TokenError::InvalidClaim("Token roles must be strings"))?,
+            Some(_) => return Err(TokenError::InvalidClaim("Token roles must be an array")),
+        };
+
+        let expires_at = match payload.get("exp") {
+            None if self.require_expiry => {
+                return Err(TokenError::MissingClaim("exp"));
+            }
+            None => None,
+            Some(exp) => Some(exp.as_u64().ok_or(TokenError::InvalidClaim(
+                "Token exp claim must be a timestamp",
+            ))?),
+        };
+
+        if let Some(expires_at) = expires_at {
+            check_expiry(expires_at, self.leeway)?;
+        }
+
+        if let Some(not_before) = payload.get("nbf") {
+            let not_before = not_before.as_u64().ok_or(TokenError::InvalidClaim(
+                "Token nbf claim must be a timestamp",
+            ))?;
+
+            if unix_now()? + self.leeway.as_secs() < not_before {
+                return Err(TokenError::NotYetValid { not_before });
+            }
+        }
+
+        Ok(AuthClaims {
+            user_id: user_id.to_string(),
+            roles,
+            expires_at,
+        })
+    }
+}
+
+fn new_mac(key: &[u8]) -> Result<HmacSha256, TokenError> {
+    HmacSha256::new_from_slice(key).map_err(|_| TokenError::InvalidKey)
+}
+
+fn decode_json(encoded: &str, part: &'static str) -> Result<Value, TokenError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|source| TokenError::InvalidBase64 { part, source })?;
+
+    serde_json::from_slice(&bytes).map_err(|source| TokenError::InvalidJson { part, source })
+}
+
+fn unix_now() -> Result<u64, TokenError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .map_err(TokenError::Clock)
+}
+
+fn check_expiry(expires_at: u64, leeway: Duration) -> Result<(), TokenError> {
+    // Tokens are only valid before their expiry, as in RFC 7519
+    if unix_now()? >= expires_at.saturating_add(leeway.as_secs()) {
+        return Err(TokenError::Expired { expires_at });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"secret key";
+
+    fn claims(expires_at: Option<u64>) -> AuthClaims {
+        AuthClaims {
+            user_id: "player".to_string(),
+            roles: vec!["admin".to_string(), "tester".to_string()],
+            expires_at,
+        }
+    }
+
+    fn in_an_hour() -> Option<u64> {
+        Some(unix_now().unwrap() + 3600)
+    }
+
+    // Signs any header and payload, to build tokens sign would refuse to
+    fn jwt(header: Value, payload: Value, key: &[u8]) -> Vec<u8> {
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(payload.to_string())
+        );
+
+        let mut mac = new_mac(key).unwrap();
+        mac.update(signing_input.as_bytes());
+
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+        format!("{}.{}", signing_input, signature).into_bytes()
+    }
+
+    #[test]
+    fn verifies_hmac_token() {
+        let verifier = HmacTokenVerifier::new(KEY);
+        let claims = claims(in_an_hour());
+
+        let token = verifier.sign(&claims).unwrap();
+
+        assert_eq!(verifier.verify(&token).unwrap(), claims);
+    }
+
+    #[test]
+    fn rejects_hmac_token_with_bad_signature() {
+        let token = HmacTokenVerifier::new(b"other key")
+            .sign(&claims(in_an_hour()))
+            .unwrap();
+
+        assert!(HmacTokenVerifier::new(KEY).verify(&token).is_err());
+
+        let mut token = HmacTokenVerifier::new(KEY)
+            .sign(&claims(in_an_hour()))
+            .unwrap();
+        token[0] ^= 1;
+
+        assert!(HmacTokenVerifier::new(KEY).verify(&token).is_err());
+        assert!(HmacTokenVerifier::new(KEY).verify(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn rejects_expired_hmac_token() {
+        let verifier = HmacTokenVerifier::new(KEY);
+        let now = unix_now().unwrap();
+
+        let token = verifier.sign(&claims(Some(now))).unwrap();
+        assert!(verifier.verify(&token).is_err());
+
+        let token = verifier.sign(&claims(Some(now - 10))).unwrap();
+        assert!(verifier.verify(&token).is_err());
+
+        let mut lenient = HmacTokenVerifier::new(KEY);
+        lenient.leeway = Duration::from_secs(60);
+        assert!(lenient.verify(&token).is_ok());
+    }
+
+    #[test]
+    fn verifies_jwt() {
+        let verifier = JwtVerifier::new(KEY);
+        let claims = claims(in_an_hour());
+
+        let token = verifier.sign(&claims).unwrap();
+
+        assert_eq!(verifier.verify(&token).unwrap(), claims);
+    }
+
+    #[test]
+    fn rejects_jwt_with_bad_signature() {
+        let token = JwtVerifier::new(b"other key")
+            .sign(&claims(in_an_hour()))
+            .unwrap();
+
+        assert!(JwtVerifier::new(KEY).verify(&token).is_err());
+    }
+
+    #[test]
+    fn rejects_expired_and_early_jwt() {
+        let verifier = JwtVerifier::new(KEY);
+        let now = unix_now().unwrap();
+        let header = json!({ "alg": "HS256", "typ": "JWT" });
+
+        // Expires exactly now
+        let token = jwt(header.clone(), json!({ "sub": "player", "exp": now }), KEY);
+        assert!(verifier.verify(&token).is_err());
+
+        let token = jwt(
+            header.clone(),
+            json!({ "sub": "player", "exp": now + 3600, "nbf": now + 600 }),
+            KEY,
+        );
+        assert!(verifier.verify(&token).is_err());
+
+        let token = jwt(
+            header.clone(),
+            json!({ "sub": "player", "exp": now + 3600, "nbf": now }),
+            KEY,
+        );
+        assert!(verifier.verify(&token).is_ok());
+
+        let token = jwt(header, json!({ "sub": "player" }), KEY);
+        assert!(verifier.verify(&token).is_err());
+    }
+
+    #[test]
+    fn rejects_jwt_with_other_algorithms() {
+        let verifier = JwtVerifier::new(KEY);
+        let payload = json!({ "sub": "player", "exp": in_an_hour() });
+
+        let token = jwt(
+            json!({ "alg": "HS512", "typ": "JWT" }),
+            payload.clone(),
+            KEY,
+        );
+        assert!(verifier.verify(&token).is_err());
+
+        // Unsigned token, with the signature left empty
+        let token = format!(
+            "{}.{}.",
+            URL_SAFE_NO_PAD.encode(json!({ "alg": "none", "typ": "JWT" }).to_string()),
+            URL_SAFE_NO_PAD.encode(payload.to_string())
+        );
+        assert!(verifier.verify(token.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_jwt() {
+        let verifier = JwtVerifier::new(KEY);
+        let token = String::from_utf8(verifier.sign(&claims(in_an_hour())).unwrap()).unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+
+        for malformed in [
+            String::new(),
+            parts[..2].join("."),
+            format!("{}.{}", token, parts[2]),
+            format!("{}!.{}.{}", parts[0], parts[1], parts[2]),
+            format!("{}.{}.{}", URL_SAFE_NO_PAD.encode("{"), parts[1], parts[2]),
+            format!("{}.{}.{}!", parts[0], parts[1], parts[2]),
+        ] {
+            assert!(
+                verifier.verify(malformed.as_bytes()).is_err(),
+                "{}",
+                malformed
+            );
+        }
+
+        assert!(verifier.verify(&[0xff, b'.', b'.']).is_err());
+    }
+}
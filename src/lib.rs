@@ -46,13 +46,65 @@ impl Deref for GDPeerID {
     }
 }
 
+// Peer ids of each host of the server start after those of the previous host
+const HOST_PEER_ID_STRIDE: usize = rusty_enet::consts::PROTOCOL_MAXIMUM_PEER_ID as usize + 1;
+
+// Peer ids of each shard start after those of the previous shard
+const SHARD_PEER_ID_STRIDE: usize = HOST_PEER_ID_STRIDE * MAX_HOSTS;
+
+/// Maximum number of addresses a single [`Server`] can bind
+pub const MAX_HOSTS: usize = 256;
+
+/// Maximum number of shards whose peer ids stay distinct, see [`ShardedServer`]
+pub const MAX_SHARDS: usize = 4096;
+
+// The last peer id of the last shard is u32::MAX, so 32-bit targets just fit every peer id
+const _: () = assert!(
+    match SHARD_PEER_ID_STRIDE.checked_mul(MAX_SHARDS - 1) {
+        Some(last_shard) => last_shard.checked_add(SHARD_PEER_ID_STRIDE - 1).is_some(),
+        None => false,
+    },
+    "The peer ids of every shard must fit in a usize"
+);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// The peer id within ENet
+///
+/// Servers bound to several addresses number the peers of each host separately,
+/// and sharded servers number the peers of each shard separately,
+/// peers of the first host of the first shard keep their ENet peer id.
 pub struct ENetPeerID(pub usize);
 
+impl ENetPeerID {
+    /// Identify a peer of one of the hosts of the server
+    pub fn new(host_index: usize, peer_id: rusty_enet::PeerID) -> Self {
+        ENetPeerID::in_shard(0, host_index, peer_id)
+    }
+
+    /// Identify a peer of one of the hosts of a shard
+    pub fn in_shard(shard: usize, host_index: usize, peer_id: rusty_enet::PeerID) -> Self {
+        ENetPeerID(shard * SHARD_PEER_ID_STRIDE + host_index * HOST_PEER_ID_STRIDE + peer_id.0)
+    }
+
+    /// Index of the shard of the peer
+    pub fn shard(self) -> usize {
+        self.0 / SHARD_PEER_ID_STRIDE
+    }
+
+    /// Index of the host of the peer within its shard, in the order the addresses were bound
+    pub fn host_index(self) -> usize {
+        self.0 % SHARD_PEER_ID_STRIDE / HOST_PEER_ID_STRIDE
+    }
+
+    /// The peer id within its ENet host
+    pub fn enet_peer_id(self) -> rusty_enet::PeerID {
+        rusty_enet::PeerID(self.0 % HOST_PEER_ID_STRIDE)
+    }
+}
+
 impl From<rusty_enet::PeerID> for ENetPeerID {
     fn from(value: rusty_enet::PeerID) -> Self {
-        ENetPeerID(value.0)
+        ENetPeerID::new(0, value)
     }
 }
 
@@ -62,12 +114,6 @@ impl From<usize> for ENetPeerID {
     }
 }
 
-impl Into<rusty_enet::PeerID> for ENetPeerID {
-    fn into(self) -> rusty_enet::PeerID {
-        rusty_enet::PeerID(self.0)
-    }
-}
-
 impl Into<usize> for ENetPeerID {
     fn into(self) -> usize {
         self.0
@@ -81,3 +127,71 @@ impl Deref for ENetPeerID {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_host_keeps_enet_peer_id() {
+        let peer = ENetPeerID::new(0, rusty_enet::PeerID(5));
+
+        assert_eq!(peer, ENetPeerID(5));
+        assert_eq!(peer.host_index(), 0);
+        assert_eq!(peer.enet_peer_id(), rusty_enet::PeerID(5));
+    }
+
+    #[test]
+    fn hosts_number_peers_separately() {
+        let last = rusty_enet::PeerID(HOST_PEER_ID_STRIDE - 1);
+
+        for host_index in [1, 2, MAX_HOSTS - 1] {
+            let first = ENetPeerID::new(host_index, rusty_enet::PeerID(0));
+            let peer = ENetPeerID::new(host_index, last);
+
+            assert_eq!(first.host_index(), host_index);
+            assert_eq!(first.enet_peer_id(), rusty_enet::PeerID(0));
+            assert_eq!(peer.host_index(), host_index);
+            assert_eq!(peer.enet_peer_id(), last);
+
+            // The last peer of the previous host comes right before
+            assert_eq!(ENetPeerID::new(host_index - 1, last).0 + 1, first.0);
+        }
+    }
+
+    #[test]
+    fn shards_number_peers_separately() {
+        let peer = ENetPeerID::in_shard(3, 2, rusty_enet::PeerID(9));
+
+        assert_eq!(peer.shard(), 3);
+        assert_eq!(peer.host_index(), 2);
+        assert_eq!(peer.enet_peer_id(), rusty_enet::PeerID(9));
+
+        // The first shard keeps the ids of a single server
+        assert_eq!(
+            ENetPeerID::in_shard(0, 2, rusty_enet::PeerID(9)),
+            ENetPeerID::new(2, rusty_enet::PeerID(9))
+        );
+
+        // The last peer of the previous shard comes right before
+        let last = ENetPeerID::in_shard(
+            MAX_SHARDS - 2,
+            MAX_HOSTS - 1,
+            rusty_enet::PeerID(HOST_PEER_ID_STRIDE - 1),
+        );
+        let first = ENetPeerID::in_shard(MAX_SHARDS - 1, 0, rusty_enet::PeerID(0));
+        assert_eq!(last.shard(), MAX_SHARDS - 2);
+        assert_eq!(last.0 + 1, first.0);
+        assert_eq!(first.shard(), MAX_SHARDS - 1);
+        assert_eq!(first.host_index(), 0);
+
+        let max = ENetPeerID::in_shard(
+            MAX_SHARDS - 1,
+            MAX_HOSTS - 1,
+            rusty_enet::PeerID(HOST_PEER_ID_STRIDE - 1),
+        );
+        assert_eq!(max.0 as u64, u32::MAX as u64);
+        assert_eq!(max.shard(), MAX_SHARDS - 1);
+        assert_eq!(max.host_index(), MAX_HOSTS - 1);
+    }
+}
@@ -0,0 +1,139 @@
+use rusty_enet::{self as enet, Packet, PacketKind};
+
+// From enet_multiplayer_peer.h
+pub const SYSCH_RELIABLE: u8 = 0;
+pub const SYSCH_UNRELIABLE: u8 = 1;
+pub const SYSCH_MAX: u8 = 2;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// How a packet is delivered, mirrors `MultiplayerPeer.TransferMode`
+pub enum TransferMode {
+    /// Packets may be lost or arrive in any order.
+    Unreliable,
+    /// Packets may be lost, and older packets arriving late are dropped.
+    UnreliableOrdered,
+    /// Packets arrive, in the order they were sent.
+    #[default]
+    Reliable,
+}
+
+impl TransferMode {
+    /// Build a packet with the ENet flags Godot uses for the mode,
+    /// mirrors `ENetMultiplayerPeer::put_packet`
+    pub fn packet(self, data: impl enet::ToRawPacket) -> Packet {
+        Packet::new(data, self.into())
+    }
+
+    /// The mode of a packet received on the ENet channel,
+    /// mirrors `ENetMultiplayerPeer::get_packet_mode`
+    ///
+    /// rusty_enet 0.4 can not report the mode of received packets,
+    /// as `Packet::kind` sets every flag instead of clearing the sent flag.
+    /// On Godot's system channels the mode is taken from the channel instead,
+    /// which can not tell unreliable and unreliable ordered packets apart.
+    /// The user channels carry no such hint, so their mode is unknown.
+    pub fn received(enet_channel: u8) -> Option<Self> {
+        match enet_channel {
+            SYSCH_RELIABLE => Some(TransferMode::Reliable),
+            SYSCH_UNRELIABLE => Some(TransferMode::Unreliable),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransferMode> for PacketKind {
+    fn from(value: TransferMode) -> Self {
+        match value {
+            TransferMode::Unreliable => PacketKind::AlwaysUnreliable { sequenced: false },
+            TransferMode::UnreliableOrdered => PacketKind::AlwaysUnreliable { sequenced: true },
+            TransferMode::Reliable => PacketKind::Reliable,
+        }
+    }
+}
+
+impl From<PacketKind> for TransferMode {
+    fn from(value: PacketKind) -> Self {
+        match value {
+            PacketKind::Reliable => TransferMode::Reliable,
+            PacketKind::Unreliable { sequenced: true }
+            | PacketKind::AlwaysUnreliable { sequenced: true } => TransferMode::UnreliableOrdered,
+            PacketKind::Unreliable { sequenced: false }
+            | PacketKind::AlwaysUnreliable { sequenced: false } => TransferMode::Unreliable,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// A transfer channel of Godot, like the channel of `@rpc`
+///
+/// Channel 0 is shared with Godot's own messages,
+/// while the other channels are shifted after Godot's system channels.
+pub struct GodotChannel(pub u8);
+
+impl GodotChannel {
+    pub const DEFAULT: GodotChannel = GodotChannel(0);
+
+    /// The ENet channel packets of the mode are sent on,
+    /// mirrors `ENetMultiplayerPeer::put_packet`
+    ///
+    /// Channels shifted past the last channel ENet supports have none.
+    pub fn enet_channel(self, transfer_mode: TransferMode) -> Option<u8> {
+        if self.0 > 0 {
+            return self.0.checked_add(SYSCH_MAX - 1).filter(|channel| {
+                u32::from(*channel) < enet::consts::PROTOCOL_MAXIMUM_CHANNEL_COUNT
+            });
+        }
+
+        match transfer_mode {
+            TransferMode::Reliable => Some(SYSCH_RELIABLE),
+            TransferMode::Unreliable | TransferMode::UnreliableOrdered => Some(SYSCH_UNRELIABLE),
+        }
+    }
+
+    /// The channel of a packet received on the ENet channel,
+    /// mirrors `ENetMultiplayerPeer::get_packet_channel`
+    pub fn from_enet_channel(enet_channel: u8) -> Self {
+        if enet_channel >= SYSCH_MAX {
+            return GodotChannel(enet_channel - SYSCH_MAX + 1);
+        }
+
+        GodotChannel::DEFAULT
+    }
+}
+
+impl From<u8> for GodotChannel {
+    fn from(value: u8) -> Self {
+        GodotChannel(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_channels_like_godot() {
+        assert_eq!(
+            GodotChannel::DEFAULT.enet_channel(TransferMode::Reliable),
+            Some(SYSCH_RELIABLE)
+        );
+        assert_eq!(
+            GodotChannel::DEFAULT.enet_channel(TransferMode::UnreliableOrdered),
+            Some(SYSCH_UNRELIABLE)
+        );
+        assert_eq!(
+            GodotChannel(1).enet_channel(TransferMode::Unreliable),
+            Some(SYSCH_MAX)
+        );
+
+        // ENet channel ids stop at 254
+        assert_eq!(
+            GodotChannel(253).enet_channel(TransferMode::Reliable),
+            Some(254)
+        );
+        assert_eq!(GodotChannel(254).enet_channel(TransferMode::Reliable), None);
+        assert_eq!(GodotChannel(255).enet_channel(TransferMode::Reliable), None);
+
+        assert_eq!(GodotChannel::from_enet_channel(254), GodotChannel(253));
+    }
+}
@@ -1,9 +1,12 @@
-use crate::packet::{Packet, RemoteCacheID};
+use crate::packet::{Packet, PacketError, RemoteCacheID};
 
 // Based on Godot's SceneMultiplayer::_process_packet and SceneCacheInterface::process_confirm_path
-pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
     if packet.len() != 6 {
-        return Err("Packet not sized to contain Godot ENet packet".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet packet",
+            len: packet.len(),
+        });
     }
 
     let valid_rpc_checksum = packet[1];
@@ -20,7 +23,7 @@ pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
 pub fn gen_packet(
     valid_rpc_checksum: bool,
     remote_cache_id: RemoteCacheID,
-) -> Result<Vec<u8>, String> {
+) -> Result<Vec<u8>, PacketError> {
     let mut out_packet: Vec<u8> = Vec::new();
 
     out_packet.push(2); // CMD_MASK for Confirm Path
@@ -0,0 +1,48 @@
+use super::{Packet, PacketError};
+
+// Based on SceneReplicationInterface::on_despawn_receive
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
+    if packet.len() < 5 {
+        return Err(PacketError::Truncated {
+            part: "Godot ENet despawn packet",
+            len: packet.len(),
+        });
+    }
+
+    let net_id = u32::from_le_bytes([packet[1], packet[2], packet[3], packet[4]]);
+
+    Ok(Packet::NetworkCommandDespawn { net_id })
+}
+
+// Reverse of parse_packet, based on SceneReplicationInterface::_make_despawn_packet
+pub fn gen_packet(net_id: u32) -> Result<Vec<u8>, PacketError> {
+    let mut out_packet: Vec<u8> = Vec::new();
+
+    out_packet.push(5); // CMD_MASK for Despawn Command
+
+    out_packet.extend(&net_id.to_le_bytes());
+
+    Ok(out_packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_despawn() {
+        for net_id in [0, 1, 0xDEADBEEF, u32::MAX] {
+            let packet = gen_packet(net_id).unwrap();
+
+            assert_eq!(
+                parse_packet(&packet).unwrap(),
+                Packet::NetworkCommandDespawn { net_id }
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_despawn() {
+        assert!(parse_packet(&[5, 1, 2, 3]).is_err());
+    }
+}
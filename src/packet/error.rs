@@ -0,0 +1,101 @@
+use super::channel::GodotChannel;
+use crate::variant::VariantError;
+use std::{error::Error as StdError, fmt::Display, string::FromUtf8Error};
+
+#[derive(Debug)]
+/// Error parsing or generating a [`Packet`](super::Packet)
+pub enum PacketError {
+    /// The packet is too short to contain the named part.
+    Truncated { part: &'static str, len: usize },
+    /// A state of a sync command is larger than the rest of the packet.
+    StateTruncated {
+        net_id: u32,
+        size: usize,
+        remaining: usize,
+    },
+    /// The packet command is not one of the SceneMultiplayer commands.
+    UnknownCommand(u8),
+    /// The sys command is not one of the SceneMultiplayer sys commands.
+    UnknownSysCommand(u8),
+    /// A field of the packet has an invalid value.
+    Invalid(&'static str),
+    /// A string field of the packet is not valid UTF-8.
+    InvalidUtf8 {
+        field: &'static str,
+        source: FromUtf8Error,
+    },
+    /// A variant in the packet failed to encode or decode.
+    Variant {
+        /// What was being encoded or decoded.
+        context: String,
+        source: VariantError,
+    },
+    /// The command can not be generated, for the named reason.
+    Unencodable(&'static str),
+    /// A state of a sync command can not be generated, for the named reason.
+    UnencodableState { net_id: u32, reason: &'static str },
+    /// The outgoing channel of the server is closed.
+    ChannelClosed,
+    /// The Godot channel is past the last ENet channel.
+    InvalidChannel(GodotChannel),
+}
+
+impl PacketError {
+    /// Wrap a variant error with what was being encoded or decoded
+    pub fn variant(context: impl Into<String>, source: VariantError) -> Self {
+        PacketError::Variant {
+            context: context.into(),
+            source,
+        }
+    }
+}
+
+impl StdError for PacketError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PacketError::InvalidUtf8 { source, .. } => Some(source),
+            PacketError::Variant { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PacketError::Truncated { part, len } => {
+                write!(f, "Packet of {} bytes too short to contain {}", len, part)
+            }
+            PacketError::StateTruncated {
+                net_id,
+                size,
+                remaining,
+            } => write!(
+                f,
+                "Sync state {} of size {} does not fit in the {} bytes left",
+                net_id, size, remaining
+            ),
+            PacketError::UnknownCommand(command) => {
+                write!(f, "Invalid value for Packet command: {}", command)
+            }
+            PacketError::UnknownSysCommand(command) => {
+                write!(f, "Invalid value for Sys command: {}", command)
+            }
+            PacketError::Invalid(reason) => write!(f, "{}", reason),
+            PacketError::InvalidUtf8 { field, .. } => {
+                write!(f, "Failed to parse {} as UTF-8", field)
+            }
+            PacketError::Variant { context, source } => {
+                write!(f, "Failed to {}: \n{}", context, source)
+            }
+            PacketError::Unencodable(reason) => write!(f, "{}", reason),
+            PacketError::UnencodableState { net_id, reason } => {
+                write!(f, "Sync state {} {}", net_id, reason)
+            }
+            PacketError::ChannelClosed => write!(f, "Outgoing channel is closed"),
+            PacketError::InvalidChannel(channel) => {
+                write!(f, "Godot channel {} has no ENet channel", channel.0)
+            }
+        }
+    }
+}
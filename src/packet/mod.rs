@@ -1,10 +1,17 @@
+pub mod channel;
 pub mod confirm_path;
+pub mod despawn;
+mod error;
 pub mod outgoing;
 pub mod raw;
 pub mod rpc;
 pub mod simplify_path;
+pub mod spawn;
+pub mod sync;
 pub mod sys;
 
+pub use error::*;
+
 use crate::packet::rpc::RPCCommand;
 
 // From scene_multiplayer.h
@@ -28,39 +35,40 @@ pub enum Packet {
     NetworkCommandRaw {
         content: Box<[u8]>,
     },
-    NetworkCommandSpawn,
-    NetworkCommandDespawn,
-    NetworkCommandSync,
+    NetworkCommandSpawn(spawn::SpawnCommand),
+    NetworkCommandDespawn {
+        net_id: u32,
+    },
+    NetworkCommandSync(sync::SyncCommand),
     NetworkCommandSys(sys::SysCommandPacket),
 }
 
 // Based on Godot's SceneMultiplayer::_process_packet
 /// Parses the provided Godot ENet packet data
-pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
     if packet.len() < 1 {
-        return Err("Packet too short to contain Godot ENet packet".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet packet",
+            len: packet.len(),
+        });
     }
 
-    // TODO: Remaining packets
     match packet[0] & CMD_MASK {
         0 => rpc::parse_packet(packet),
         1 => simplify_path::parse_packet(packet),
         2 => confirm_path::parse_packet(packet),
         3 => raw::parse_packet(packet),
-        4 => Ok(Packet::NetworkCommandSpawn),
-        5 => Ok(Packet::NetworkCommandDespawn),
-        6 => Ok(Packet::NetworkCommandSync),
+        4 => spawn::parse_packet(packet),
+        5 => despawn::parse_packet(packet),
+        6 => sync::parse_packet(packet),
         7 => sys::parse_packet(packet),
-        _ => Err(format!(
-            "Invalid value for Packet command: {}",
-            packet[0] & CMD_MASK
-        )),
+        _ => Err(PacketError::UnknownCommand(packet[0] & CMD_MASK)),
     }
 }
 
 // Reverse of parse_packet
 /// Generate a Godot ENet Packet from the provided data
-pub fn gen_packet(packet: &Packet) -> Result<Vec<u8>, String> {
+pub fn gen_packet(packet: &Packet) -> Result<Vec<u8>, PacketError> {
     match packet {
         Packet::NetworkCommandRemoteCall(header) => rpc::gen_packet(
             header,
@@ -79,11 +87,9 @@ pub fn gen_packet(packet: &Packet) -> Result<Vec<u8>, String> {
             remote_cache_id,
         } => confirm_path::gen_packet(*valid_rpc_checksum, *remote_cache_id),
         Packet::NetworkCommandRaw { content } => raw::gen_packet(content),
-        Packet::NetworkCommandSpawn => todo!(), // TODO: Remaining packets
-        Packet::NetworkCommandDespawn => todo!(),
-        Packet::NetworkCommandSync => todo!(),
+        Packet::NetworkCommandSpawn(command) => spawn::gen_packet(command),
+        Packet::NetworkCommandDespawn { net_id } => despawn::gen_packet(*net_id),
+        Packet::NetworkCommandSync(command) => sync::gen_packet(command),
         Packet::NetworkCommandSys(packet) => sys::gen_packet(packet),
     }
 }
-
-// TODO: Custom Packet Parse Error?
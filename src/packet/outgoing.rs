@@ -1,6 +1,10 @@
-pub use rusty_enet::Packet;
+pub use rusty_enet::{Packet, PacketKind};
 
-use crate::ENetPeerID;
+use super::{
+    PacketError,
+    channel::{GodotChannel, TransferMode},
+};
+use crate::{ENetPeerID, GDPeerID, layers::PeerMap};
 
 #[derive(Clone, Debug)]
 /// Packet leaving the server.
@@ -9,3 +13,176 @@ pub struct OutgoingPacket {
     pub channel_id: u8,
     pub packet: Packet,
 }
+
+impl OutgoingPacket {
+    /// Build a packet sent like Godot sends it on the channel with the mode
+    pub fn new(
+        peer_id: ENetPeerID,
+        channel: GodotChannel,
+        transfer_mode: TransferMode,
+        data: impl rusty_enet::ToRawPacket,
+    ) -> Result<Self, PacketError> {
+        Ok(Self {
+            peer_id,
+            channel_id: channel
+                .enet_channel(transfer_mode)
+                .ok_or(PacketError::InvalidChannel(channel))?,
+            packet: transfer_mode.packet(data),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Packet leaving the server for several peers, sharing a single packet allocation.
+pub struct OutgoingBroadcast {
+    pub peer_ids: Vec<ENetPeerID>,
+    pub channel_id: u8,
+    pub packet: Packet,
+    /// The kind the packet was built with, which ENet does not report back.
+    pub kind: PacketKind,
+}
+
+impl OutgoingBroadcast {
+    /// Build a broadcast to the Godot peers targeted, resolved through the [`PeerMap`],
+    /// sent like Godot sends it on the channel with the mode
+    pub fn new(
+        peer_map: &PeerMap,
+        target: &BroadcastTarget,
+        channel: GodotChannel,
+        transfer_mode: TransferMode,
+        data: impl rusty_enet::ToRawPacket,
+    ) -> Result<Self, PacketError> {
+        Ok(Self {
+            peer_ids: peer_map.resolve(target),
+            channel_id: channel
+                .enet_channel(transfer_mode)
+                .ok_or(PacketError::InvalidChannel(channel))?,
+            packet: transfer_mode.packet(data),
+            kind: transfer_mode.into(),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+/// The part of a broadcast forwarded to the peers of another shard.
+///
+/// ENet packets count their references without atomics,
+/// so shards are sent the bytes and build a packet of their own.
+pub struct ForwardedBroadcast {
+    pub peer_ids: Vec<ENetPeerID>,
+    pub channel_id: u8,
+    pub data: Vec<u8>,
+    pub kind: PacketKind,
+}
+
+impl ForwardedBroadcast {
+    /// Copy the broadcast for the peers of another shard
+    pub fn new(broadcast: &OutgoingBroadcast, peer_ids: Vec<ENetPeerID>) -> Self {
+        Self {
+            peer_ids,
+            channel_id: broadcast.channel_id,
+            data: broadcast.packet.data().to_vec(),
+            kind: broadcast.kind,
+        }
+    }
+}
+
+impl From<ForwardedBroadcast> for OutgoingBroadcast {
+    fn from(value: ForwardedBroadcast) -> Self {
+        Self {
+            peer_ids: value.peer_ids,
+            channel_id: value.channel_id,
+            packet: Packet::new(value.data, value.kind),
+            kind: value.kind,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// The Godot peers targeted by a broadcast
+pub enum BroadcastTarget {
+    /// 0 for all, -x for all but x, or a single peer, like the target of `rpc_id`.
+    Peer(GDPeerID),
+    /// Every peer in the set.
+    Peers(Vec<GDPeerID>),
+}
+
+impl From<GDPeerID> for BroadcastTarget {
+    fn from(value: GDPeerID) -> Self {
+        BroadcastTarget::Peer(value)
+    }
+}
+
+impl From<Vec<GDPeerID>> for BroadcastTarget {
+    fn from(value: Vec<GDPeerID>) -> Self {
+        BroadcastTarget::Peers(value)
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Command leaving the server, applied to the ENet host when the server is serviced.
+pub enum OutgoingCommand {
+    /// Send a packet to a peer.
+    Send(OutgoingPacket),
+    /// Send a packet to several peers.
+    Broadcast(OutgoingBroadcast),
+    /// Send a broadcast forwarded from another shard, see [`ShardedServer`](crate::ShardedServer).
+    Forwarded(ForwardedBroadcast),
+    /// Disconnect a peer gracefully, sending it the data with the disconnect.
+    Disconnect { peer_id: ENetPeerID, data: u32 },
+    /// Disconnect a peer once all of its queued packets are sent.
+    DisconnectLater { peer_id: ENetPeerID, data: u32 },
+    /// Disconnect a peer immediately, without waiting for it to acknowledge.
+    DisconnectNow { peer_id: ENetPeerID, data: u32 },
+    /// Drop a peer without notifying it, leaving it to time out.
+    Reset { peer_id: ENetPeerID },
+    /// Configure the unreliable packet throttle of a peer, mirrors `ENetPacketPeer.throttle_configure`.
+    ///
+    /// The interval is in milliseconds.
+    Throttle {
+        peer_id: ENetPeerID,
+        interval: u32,
+        acceleration: u32,
+        deceleration: u32,
+    },
+    /// Configure when a peer times out, mirrors `ENetPacketPeer.set_timeout`.
+    ///
+    /// Values are in milliseconds, 0 keeps the ENet default.
+    Timeout {
+        peer_id: ENetPeerID,
+        limit: u32,
+        minimum: u32,
+        maximum: u32,
+    },
+    /// Set the interval in milliseconds at which a peer is pinged.
+    PingInterval { peer_id: ENetPeerID, interval: u32 },
+}
+
+impl OutgoingCommand {
+    /// The peer the command applies to, [`None`] for broadcasts
+    pub fn peer_id(&self) -> Option<ENetPeerID> {
+        match self {
+            OutgoingCommand::Send(outgoing) => Some(outgoing.peer_id),
+            OutgoingCommand::Broadcast(_) | OutgoingCommand::Forwarded(_) => None,
+            OutgoingCommand::Disconnect { peer_id, .. }
+            | OutgoingCommand::DisconnectLater { peer_id, .. }
+            | OutgoingCommand::DisconnectNow { peer_id, .. }
+            | OutgoingCommand::Reset { peer_id }
+            | OutgoingCommand::Throttle { peer_id, .. }
+            | OutgoingCommand::Timeout { peer_id, .. }
+            | OutgoingCommand::PingInterval { peer_id, .. } => Some(*peer_id),
+        }
+    }
+}
+
+impl From<OutgoingPacket> for OutgoingCommand {
+    fn from(value: OutgoingPacket) -> Self {
+        OutgoingCommand::Send(value)
+    }
+}
+
+impl From<OutgoingBroadcast> for OutgoingCommand {
+    fn from(value: OutgoingBroadcast) -> Self {
+        OutgoingCommand::Broadcast(value)
+    }
+}
@@ -1,14 +1,14 @@
-use super::Packet;
+use super::{Packet, PacketError};
 
 // Heavily Uses SceneMultiplayer::_process_sys() in Godot to revese engineer the header
-pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
     Ok(Packet::NetworkCommandRaw {
         content: (&packet[1..]).into(),
     })
 }
 
 // Reverse of parse_packet
-pub fn gen_packet(packet: &[u8]) -> Result<Vec<u8>, String> {
+pub fn gen_packet(packet: &[u8]) -> Result<Vec<u8>, PacketError> {
     let mut out_packet: Vec<u8> = Vec::new();
 
     out_packet.push(3); // CMD_MASK for Raw Command
@@ -1,8 +1,15 @@
-use super::Packet;
+use super::{Packet, PacketError};
 use crate::layers::OutgoingCache;
-use crate::{ENetPeerID, GDPeerID, packet::outgoing, variant::Variant};
+use crate::{
+    ENetPeerID, GDPeerID,
+    packet::{
+        channel::{GodotChannel, TransferMode},
+        outgoing,
+    },
+    variant::Variant,
+};
 use std::sync::Arc;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 
 // From scene_rpc_interface.h
 const NODE_ID_COMPRESSION_SHIFT: u8 = 4;
@@ -37,23 +44,29 @@ pub struct RPCCommandHeader {
 }
 
 // Heavily Uses SceneRPCInterface::process_rpc() and SceneMultiplayer in Godot to revese engineer the header
-pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
     if packet.len() < 1 {
-        return Err("Packet too short to contain Godot ENet header".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet header",
+            len: packet.len(),
+        });
     }
 
     let node_id_compression = (packet[0] & NODE_ID_COMPRESSION_FLAG) >> NODE_ID_COMPRESSION_SHIFT;
     let name_id_compression = (packet[0] & NAME_ID_COMPRESSION_FLAG) >> NAME_ID_COMPRESSION_SHIFT;
 
     if packet.len() < 1 + (1 << node_id_compression) + (1 << name_id_compression) {
-        return Err("Packet too short to contain Godot ENet RPC header".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet RPC header",
+            len: packet.len(),
+        });
     }
 
     let node_id: u32 = match node_id_compression {
         0 => packet[1] as u32,
         1 => u16::from_le_bytes([packet[1], packet[2]]) as u32,
         2 => u32::from_le_bytes([packet[1], packet[2], packet[3], packet[4]]),
-        _ => return Err("Invalid node_id_compression value".to_string()),
+        _ => return Err(PacketError::Invalid("Invalid node_id_compression value")),
     };
 
     // Node *node = _process_get_node(p_from, p_packet, node_target, p_packet_len);
@@ -63,7 +76,7 @@ pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
     let name_id: u32 = match name_id_compression {
         0 => packet[offset] as u32,
         1 => u16::from_le_bytes([packet[offset], packet[1 + offset]]) as u32,
-        _ => return Err("Invalid name_id_compression value".to_string()),
+        _ => return Err(PacketError::Invalid("Invalid name_id_compression value")),
     };
 
     // From SceneRPCInterface::_process_rpc
@@ -80,7 +93,7 @@ pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
 }
 
 // Reverse of parse_packet
-pub fn gen_packet(header: &RPCCommandHeader, command: &RPCCommand) -> Result<Vec<u8>, String> {
+pub fn gen_packet(header: &RPCCommandHeader, command: &RPCCommand) -> Result<Vec<u8>, PacketError> {
     let mut out_packet: Vec<u8> = Vec::new();
 
     let mut header_byte: u8 = 0;
@@ -127,14 +140,20 @@ pub fn gen_packet(header: &RPCCommandHeader, command: &RPCCommand) -> Result<Vec
             {
                 out_packet.extend_from_slice(&pba.0);
             } else {
-                return Err("RPC Command with byte_only_or_no_args set must have a single PackedByteArray argument".to_string());
+                return Err(PacketError::Unencodable(
+                    "RPC Command with byte_only_or_no_args set must have a single PackedByteArray argument",
+                ));
             }
         } else if command.args.len() > 1 {
-            return Err("RPC Command with byte_only_or_no_args set must have a single PackedByteArray argument".to_string());
+            return Err(PacketError::Unencodable(
+                "RPC Command with byte_only_or_no_args set must have a single PackedByteArray argument",
+            ));
         }
     } else {
         if command.args.len() > 255 {
-            return Err("RPC Command cannot have more than 255 arguments".to_string());
+            return Err(PacketError::Unencodable(
+                "RPC Command cannot have more than 255 arguments",
+            ));
         }
         out_packet.push(command.args.len() as u8);
 
@@ -143,11 +162,9 @@ pub fn gen_packet(header: &RPCCommandHeader, command: &RPCCommand) -> Result<Vec
         for arg in &command.args {
             // TODO: Include Compression?
             let mut encoded = arg.encode().map_err(|e| {
-                format!(
-                    "Failed to encode argument {} of {} in RPC Command: \n{}",
-                    i + 1,
-                    count,
-                    e
+                PacketError::variant(
+                    format!("encode argument {} of {} in RPC Command", i + 1, count),
+                    e,
                 )
             })?;
 
@@ -164,7 +181,7 @@ pub fn gen_packet(header: &RPCCommandHeader, command: &RPCCommand) -> Result<Vec
 pub fn gen_packet_with_path(
     header: &RPCCommandHeader,
     command: &RPCCommand,
-) -> Result<Vec<u8>, String> {
+) -> Result<Vec<u8>, PacketError> {
     let mut out_packet = gen_packet(
         &RPCCommandHeader {
             node_id: 0x80000000,
@@ -188,7 +205,34 @@ pub fn gen_packet_with_path(
     Ok(out_packet)
 }
 
-/// Processes and sends an RPC packet to a peer.
+#[derive(Clone, Copy)]
+/// The peer [`smart_send_packet`] sends to, with the state of its event
+pub struct RPCTarget<'a> {
+    pub outgoing_cache: &'a OutgoingCache,
+    pub tx_outgoing: &'a UnboundedSender<outgoing::OutgoingCommand>,
+    pub gd_peer: &'a GDPeerID,
+    pub enet_peer: &'a ENetPeerID,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// How [`smart_send_packet`] sends an RPC, mirrors the `@rpc` annotation of the function
+pub struct RPCSendOptions {
+    pub channel: GodotChannel,
+    pub transfer_mode: TransferMode,
+}
+
+impl Default for RPCSendOptions {
+    /// The defaults of `@rpc`
+    fn default() -> Self {
+        Self {
+            channel: GodotChannel::DEFAULT,
+            transfer_mode: TransferMode::Unreliable,
+        }
+    }
+}
+
+/// Processes and sends an RPC packet to a peer,
+/// on the channel and with the mode of the `@rpc` annotation of the function.
 ///
 /// Depends on [`PathCacheLayer`](crate::layers::PathCacheLayer).
 pub fn smart_send_packet(
@@ -196,11 +240,16 @@ pub fn smart_send_packet(
     checksum: String,
     args: Vec<Arc<Box<dyn Variant>>>,
     name_id: u32,
-    outgoing_cache: &OutgoingCache,
-    tx_outgoing: &Sender<outgoing::OutgoingPacket>,
-    gd_peer: &GDPeerID,
-    enet_peer: &ENetPeerID,
-) -> Result<(), String> {
+    target: RPCTarget<'_>,
+    options: RPCSendOptions,
+) -> Result<(), PacketError> {
+    let RPCTarget {
+        outgoing_cache,
+        tx_outgoing,
+        gd_peer,
+        enet_peer,
+    } = target;
+
     let node_id: u32 =
         match outgoing_cache.get_or_write_id(gd_peer, enet_peer, &path, &checksum, tx_outgoing) {
             Some(id) => id,
@@ -229,14 +278,15 @@ pub fn smart_send_packet(
         gen_packet(&header, &command)?
     };
 
-    let outgoing_packet = outgoing::OutgoingPacket {
-        peer_id: *enet_peer,
-        channel_id: 0,
-        packet: outgoing::Packet::reliable(packet_data),
-    };
+    let outgoing_packet = outgoing::OutgoingPacket::new(
+        *enet_peer,
+        options.channel,
+        options.transfer_mode,
+        packet_data,
+    )?;
 
-    if let Err(e) = tx_outgoing.send(outgoing_packet) {
-        return Err(format!("Smart Send failed to send RPC Packet: {}", e));
+    if tx_outgoing.send(outgoing_packet.into()).is_err() {
+        return Err(PacketError::ChannelClosed);
     }
 
     return Ok(());
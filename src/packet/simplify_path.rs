@@ -1,23 +1,31 @@
 use crate::{
-    packet::{Packet, RemoteCacheID},
+    packet::{Packet, PacketError, RemoteCacheID},
     utils::clean_path,
 };
 
 // Based on Godot's SceneMultiplayer::_process_packet
-pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
     if packet.len() < 38 {
-        return Err("Packet too short to contain Godot ENet packet".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet packet",
+            len: packet.len(),
+        });
     }
 
-    let methods_md5_hash = String::from_utf8(packet[1..33].to_vec())
-        .map_err(|_| "Failed to parse method_hash as UTF-8".to_string())?;
+    let methods_md5_hash =
+        String::from_utf8(packet[1..33].to_vec()).map_err(|e| PacketError::InvalidUtf8 {
+            field: "method_hash",
+            source: e,
+        })?;
 
     let remote_cache_id = u32::from_le_bytes([packet[34], packet[35], packet[36], packet[37]]);
 
-    let path = clean_path(
-        String::from_utf8(packet[38..].to_vec())
-            .map_err(|_| "Failed to parse path as UTF-8".to_string())?,
-    );
+    let path = clean_path(String::from_utf8(packet[38..].to_vec()).map_err(|e| {
+        PacketError::InvalidUtf8 {
+            field: "path",
+            source: e,
+        }
+    })?);
 
     Ok(Packet::NetworkCommandSimplifyPath {
         methods_md5_hash,
@@ -31,9 +39,11 @@ pub fn gen_packet(
     methods_md5_hash: &str,
     remote_cache_id: RemoteCacheID,
     path: &str,
-) -> Result<Vec<u8>, String> {
+) -> Result<Vec<u8>, PacketError> {
     if methods_md5_hash.len() != 32 {
-        return Err("methods_md5_hash must be exactly 32 characters long".to_string());
+        return Err(PacketError::Invalid(
+            "methods_md5_hash must be exactly 32 characters long",
+        ));
     }
     let mut out_packet: Vec<u8> = Vec::new();
 
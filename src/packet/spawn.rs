@@ -0,0 +1,292 @@
+use super::{Packet, PacketError, RemoteCacheID};
+use crate::{
+    utils::clean_path,
+    variant::{self, Variant},
+};
+use std::sync::Arc;
+
+// From multiplayer_spawner.h
+/// Scene ID used by spawns which are created from a custom spawn function.
+pub const CUSTOM_SPAWN_SCENE_ID: u8 = 0xFF;
+
+// Command, Scene ID, Spawner ID, Net ID, Sync Count, Name Length
+const SPAWN_HEADER_SIZE: usize = 1 + 1 + 4 + 4 + 4 + 4;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Parsed Spawn Command
+///
+/// Replicates the contents of a `MultiplayerSpawner` spawn in Godot.
+pub struct SpawnCommand {
+    /// Index into the spawner's spawnable scenes,
+    /// or [`CUSTOM_SPAWN_SCENE_ID`] for custom spawns.
+    pub scene_id: u8,
+    /// Cache ID of the spawner's path, as sent with a SimplifyPath packet.
+    pub spawner_id: RemoteCacheID,
+    /// Network ID of the spawned node, used to despawn it later.
+    pub net_id: u32,
+    /// Network IDs of the synchronizers attached to the spawned node.
+    pub sync_ids: Vec<u32>,
+    /// Name of the spawned node.
+    pub name: String,
+
+    /// Argument passed to the custom spawn function, only present for custom spawns.
+    pub spawn_arg: Option<Arc<Box<dyn Variant>>>,
+    /// Spawn properties of the node's synchronizers, in order.
+    pub state: Vec<Arc<Box<dyn Variant>>>,
+}
+
+// Heavily Uses SceneReplicationInterface::on_spawn_receive() in Godot to reverse engineer the packet
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
+    if packet.len() < SPAWN_HEADER_SIZE {
+        return Err(PacketError::Truncated {
+            part: "Godot ENet spawn header",
+            len: packet.len(),
+        });
+    }
+
+    let scene_id = packet[1];
+
+    let spawner_id = u32::from_le_bytes([packet[2], packet[3], packet[4], packet[5]]);
+    let net_id = u32::from_le_bytes([packet[6], packet[7], packet[8], packet[9]]);
+    let sync_len = u32::from_le_bytes([packet[10], packet[11], packet[12], packet[13]]) as usize;
+    let name_len = u32::from_le_bytes([packet[14], packet[15], packet[16], packet[17]]) as usize;
+
+    let mut offset = SPAWN_HEADER_SIZE;
+
+    if sync_len
+        .checked_mul(4)
+        .and_then(|len| len.checked_add(name_len))
+        .is_none_or(|len| len > packet.len() - offset)
+    {
+        return Err(PacketError::Truncated {
+            part: "Godot ENet spawn sync ids and name",
+            len: packet.len(),
+        });
+    }
+
+    let mut sync_ids = Vec::with_capacity(sync_len);
+    for _ in 0..sync_len {
+        sync_ids.push(u32::from_le_bytes([
+            packet[offset],
+            packet[offset + 1],
+            packet[offset + 2],
+            packet[offset + 3],
+        ]));
+        offset += 4;
+    }
+
+    if name_len < 1 {
+        return Err(PacketError::Invalid(
+            "Spawn packet contains an empty node name",
+        ));
+    }
+
+    let name = clean_path(
+        String::from_utf8(packet[offset..(offset + name_len)].to_vec()).map_err(|e| {
+            PacketError::InvalidUtf8 {
+                field: "spawn name",
+                source: e,
+            }
+        })?,
+    );
+    offset += name_len;
+
+    let mut spawn_arg = None;
+
+    if scene_id == CUSTOM_SPAWN_SCENE_ID {
+        if packet.len() - offset < 4 {
+            return Err(PacketError::Truncated {
+                part: "Godot ENet spawn argument size",
+                len: packet.len(),
+            });
+        }
+
+        let arg_size = u32::from_le_bytes([
+            packet[offset],
+            packet[offset + 1],
+            packet[offset + 2],
+            packet[offset + 3],
+        ]) as usize;
+        offset += 4;
+
+        if arg_size > packet.len() - offset {
+            return Err(PacketError::Truncated {
+                part: "Godot ENet spawn argument",
+                len: packet.len(),
+            });
+        }
+
+        let decoding_result =
+            variant::decode_and_decompress_variant(&packet[offset..(offset + arg_size)])
+                .map_err(|e| PacketError::variant("decode spawn argument", e))?;
+
+        spawn_arg = Some(Arc::new(decoding_result.variant));
+        offset += arg_size;
+    }
+
+    let mut state = Vec::new();
+
+    while offset < packet.len() {
+        let decoding_result =
+            variant::decode_and_decompress_variant(&packet[offset..]).map_err(|e| {
+                PacketError::variant(
+                    format!("decode spawn state variable {}", state.len() + 1),
+                    e,
+                )
+            })?;
+
+        offset += decoding_result.consumed;
+        state.push(Arc::new(decoding_result.variant));
+    }
+
+    Ok(Packet::NetworkCommandSpawn(SpawnCommand {
+        scene_id,
+        spawner_id,
+        net_id,
+        sync_ids,
+        name,
+
+        spawn_arg,
+        state,
+    }))
+}
+
+// Reverse of parse_packet, based on SceneReplicationInterface::_make_spawn_packet
+pub fn gen_packet(command: &SpawnCommand) -> Result<Vec<u8>, PacketError> {
+    if command.name.is_empty() {
+        return Err(PacketError::Unencodable(
+            "Spawn Command must have a node name",
+        ));
+    }
+
+    if (command.scene_id == CUSTOM_SPAWN_SCENE_ID) != command.spawn_arg.is_some() {
+        return Err(PacketError::Unencodable(
+            "Spawn Command must have a spawn argument if and only if it is a custom spawn",
+        ));
+    }
+
+    let mut out_packet: Vec<u8> = Vec::new();
+
+    out_packet.push(4); // CMD_MASK for Spawn Command
+
+    out_packet.push(command.scene_id);
+
+    out_packet.extend(&command.spawner_id.to_le_bytes());
+    out_packet.extend(&command.net_id.to_le_bytes());
+    out_packet.extend(&(command.sync_ids.len() as u32).to_le_bytes());
+    out_packet.extend(&(command.name.len() as u32 + 1).to_le_bytes()); // Includes Null Terminator
+
+    for sync_id in &command.sync_ids {
+        out_packet.extend(&sync_id.to_le_bytes());
+    }
+
+    out_packet.extend(command.name.as_bytes());
+    out_packet.push(0); // Null Terminator
+
+    if let Some(spawn_arg) = &command.spawn_arg {
+        let encoded = variant::encode_and_compress_variant(spawn_arg.as_ref().as_ref())
+            .map_err(|e| PacketError::variant("encode spawn argument", e))?;
+
+        out_packet.extend(&(encoded.len() as u32).to_le_bytes());
+        out_packet.extend(encoded);
+    }
+
+    let count = command.state.len();
+    for (i, var) in command.state.iter().enumerate() {
+        let encoded = variant::encode_and_compress_variant(var.as_ref().as_ref()).map_err(|e| {
+            PacketError::variant(
+                format!("encode spawn state variable {} of {}", i + 1, count),
+                e,
+            )
+        })?;
+
+        out_packet.extend(encoded);
+    }
+
+    Ok(out_packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::{Int, VariantString, Vector3};
+
+    fn round_trip(command: SpawnCommand) {
+        let packet = gen_packet(&command).unwrap();
+
+        assert_eq!(
+            parse_packet(&packet).unwrap(),
+            Packet::NetworkCommandSpawn(command)
+        );
+    }
+
+    #[test]
+    fn round_trips_scene_spawn() {
+        round_trip(SpawnCommand {
+            scene_id: 2,
+            spawner_id: 0xDEADBEEF,
+            net_id: 7,
+            sync_ids: vec![8, 9],
+            name: "Player".to_string(),
+
+            spawn_arg: None,
+            state: vec![
+                Arc::new(Box::new(Vector3 {
+                    x: 1.5.into(),
+                    y: 0.0.into(),
+                    z: (-2.25).into(),
+                })),
+                Arc::new(Box::new(Int(42))),
+            ],
+        });
+    }
+
+    #[test]
+    fn round_trips_custom_spawn() {
+        round_trip(SpawnCommand {
+            scene_id: CUSTOM_SPAWN_SCENE_ID,
+            spawner_id: 1,
+            net_id: 3,
+            sync_ids: vec![4],
+            name: "Bullet".to_string(),
+
+            spawn_arg: Some(Arc::new(Box::new(VariantString("red".to_string())))),
+            state: vec![Arc::new(Box::new(Int(-1)))],
+        });
+    }
+
+    #[test]
+    fn round_trips_spawn_without_state() {
+        round_trip(SpawnCommand {
+            scene_id: 0,
+            spawner_id: 1,
+            net_id: 2,
+            sync_ids: Vec::new(),
+            name: "Empty".to_string(),
+
+            spawn_arg: None,
+            state: Vec::new(),
+        });
+    }
+
+    #[test]
+    fn rejects_mismatched_spawn_argument() {
+        let mut command = SpawnCommand {
+            scene_id: CUSTOM_SPAWN_SCENE_ID,
+            spawner_id: 1,
+            net_id: 2,
+            sync_ids: Vec::new(),
+            name: "Custom".to_string(),
+
+            spawn_arg: None,
+            state: Vec::new(),
+        };
+
+        assert!(gen_packet(&command).is_err());
+
+        command.scene_id = 0;
+        command.spawn_arg = Some(Arc::new(Box::new(Int(1))));
+
+        assert!(gen_packet(&command).is_err());
+    }
+}
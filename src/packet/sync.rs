@@ -0,0 +1,343 @@
+use super::{Packet, PacketError};
+use crate::variant::{self, Variant};
+use std::sync::Arc;
+
+// From scene_multiplayer.h
+const CMD_FLAG_0_SHIFT: u8 = 3;
+
+/// Set on the command byte when the packet contains delta states.
+const DELTA_FLAG: u8 = 1 << CMD_FLAG_0_SHIFT;
+
+/// Set on a synchronizer's net id when it is a path cache id instead of a spawned net id.
+pub const NET_ID_PATH_FLAG: u32 = 0x80000000;
+
+// Net ID, Size
+const SYNC_STATE_HEADER_SIZE: usize = 4 + 4;
+// Net ID, Indexes, Size
+const DELTA_STATE_HEADER_SIZE: usize = 4 + 8 + 4;
+// From SceneReplicationInterface::on_sync_receive, checked before delta packets are dispatched
+const MIN_SYNC_PACKET_SIZE: usize = 1 + 2 + SYNC_STATE_HEADER_SIZE;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Parsed Sync Command
+///
+/// Replicates the `MultiplayerSynchronizer` updates sent by Godot's `SceneReplicationInterface`.
+pub struct SyncCommand {
+    pub section: SyncSection,
+
+    pub states: Vec<SyncState>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SyncSection {
+    /// Unreliable full sync of every sync property,
+    /// stamped with the sender's network time to drop out of order packets.
+    Sync { sync_net_time: u16 },
+    /// Reliable update of only the watched properties which changed.
+    Delta,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The replicated properties of a single synchronizer
+pub struct SyncState {
+    /// Either the net id assigned at spawn,
+    /// or a path cache id with [`NET_ID_PATH_FLAG`] set.
+    pub net_id: u32,
+
+    /// Bitmask of which watched properties are contained, only present in delta sections.
+    pub indexes: Option<u64>,
+
+    /// The property values, in the order of the synchronizer's replication config.
+    pub values: Vec<Arc<Box<dyn Variant>>>,
+}
+
+impl SyncState {
+    /// Whether the net id refers to a path cache id rather than a spawned net id
+    pub fn is_path_id(&self) -> bool {
+        self.net_id & NET_ID_PATH_FLAG != 0
+    }
+}
+
+// Heavily Uses SceneReplicationInterface::on_sync_receive() and on_delta_receive() in Godot to reverse engineer the packet
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
+    if packet.len() < MIN_SYNC_PACKET_SIZE {
+        return Err(PacketError::Truncated {
+            part: "Godot ENet sync command",
+            len: packet.len(),
+        });
+    }
+
+    let mut states = Vec::new();
+
+    let section = if packet[0] & DELTA_FLAG != 0 {
+        let mut offset = 1;
+
+        while offset + DELTA_STATE_HEADER_SIZE < packet.len() {
+            let net_id = u32::from_le_bytes([
+                packet[offset],
+                packet[offset + 1],
+                packet[offset + 2],
+                packet[offset + 3],
+            ]);
+            let indexes = u64::from_le_bytes([
+                packet[offset + 4],
+                packet[offset + 5],
+                packet[offset + 6],
+                packet[offset + 7],
+                packet[offset + 8],
+                packet[offset + 9],
+                packet[offset + 10],
+                packet[offset + 11],
+            ]);
+            let size = u32::from_le_bytes([
+                packet[offset + 12],
+                packet[offset + 13],
+                packet[offset + 14],
+                packet[offset + 15],
+            ]) as usize;
+            offset += DELTA_STATE_HEADER_SIZE;
+
+            if size > packet.len() - offset {
+                return Err(PacketError::StateTruncated {
+                    net_id,
+                    size,
+                    remaining: packet.len() - offset,
+                });
+            }
+
+            states.push(SyncState {
+                net_id,
+                indexes: Some(indexes),
+                values: parse_values(&packet[offset..(offset + size)], net_id)?,
+            });
+
+            offset += size;
+        }
+
+        SyncSection::Delta
+    } else {
+        let sync_net_time = u16::from_le_bytes([packet[1], packet[2]]);
+
+        let mut offset = 3;
+
+        while offset + SYNC_STATE_HEADER_SIZE < packet.len() {
+            let net_id = u32::from_le_bytes([
+                packet[offset],
+                packet[offset + 1],
+                packet[offset + 2],
+                packet[offset + 3],
+            ]);
+            let size = u32::from_le_bytes([
+                packet[offset + 4],
+                packet[offset + 5],
+                packet[offset + 6],
+                packet[offset + 7],
+            ]) as usize;
+            offset += SYNC_STATE_HEADER_SIZE;
+
+            if size > packet.len() - offset {
+                return Err(PacketError::StateTruncated {
+                    net_id,
+                    size,
+                    remaining: packet.len() - offset,
+                });
+            }
+
+            states.push(SyncState {
+                net_id,
+                indexes: None,
+                values: parse_values(&packet[offset..(offset + size)], net_id)?,
+            });
+
+            offset += size;
+        }
+
+        SyncSection::Sync { sync_net_time }
+    };
+
+    Ok(Packet::NetworkCommandSync(SyncCommand { section, states }))
+}
+
+// Replicated from MultiplayerAPI::decode_and_decompress_variants in Godot
+fn parse_values(raw_bytes: &[u8], net_id: u32) -> Result<Vec<Arc<Box<dyn Variant>>>, PacketError> {
+    let mut values = Vec::new();
+    let mut offset = 0;
+
+    while offset < raw_bytes.len() {
+        let decoding_result = variant::decode_and_decompress_variant(&raw_bytes[offset..])
+            .map_err(|e| {
+                PacketError::variant(
+                    format!("decode variable {} in state {}", values.len() + 1, net_id),
+                    e,
+                )
+            })?;
+
+        offset += decoding_result.consumed;
+        values.push(Arc::new(decoding_result.variant));
+    }
+
+    Ok(values)
+}
+
+// Reverse of parse_packet, based on SceneReplicationInterface::_send_sync and _send_delta
+pub fn gen_packet(command: &SyncCommand) -> Result<Vec<u8>, PacketError> {
+    // Godot drops sync packets without any state
+    if command.states.is_empty() {
+        return Err(PacketError::Unencodable(
+            "Sync Command must contain at least one state",
+        ));
+    }
+
+    let mut out_packet: Vec<u8> = Vec::new();
+
+    match command.section {
+        SyncSection::Sync { sync_net_time } => {
+            out_packet.push(6); // CMD_MASK for Sync Command
+            out_packet.extend(&sync_net_time.to_le_bytes());
+        }
+        SyncSection::Delta => {
+            out_packet.push(6 | DELTA_FLAG); // CMD_MASK for Sync Command
+        }
+    }
+
+    for state in &command.states {
+        // Godot skips empty states when sending, and can not parse one at the end of a packet
+        if state.values.is_empty() {
+            return Err(PacketError::UnencodableState {
+                net_id: state.net_id,
+                reason: "must contain at least one value",
+            });
+        }
+
+        let mut encoded_values = Vec::new();
+
+        let count = state.values.len();
+        for (i, value) in state.values.iter().enumerate() {
+            let encoded =
+                variant::encode_and_compress_variant(value.as_ref().as_ref()).map_err(|e| {
+                    PacketError::variant(
+                        format!(
+                            "encode variable {} of {} in sync state {}",
+                            i + 1,
+                            count,
+                            state.net_id
+                        ),
+                        e,
+                    )
+                })?;
+
+            encoded_values.extend(encoded);
+        }
+
+        out_packet.extend(&state.net_id.to_le_bytes());
+
+        match (command.section, state.indexes) {
+            (SyncSection::Sync { .. }, None) => {}
+            (SyncSection::Delta, Some(indexes)) => {
+                out_packet.extend(&indexes.to_le_bytes());
+            }
+            (SyncSection::Sync { .. }, Some(_)) => {
+                return Err(PacketError::UnencodableState {
+                    net_id: state.net_id,
+                    reason: "in a sync section must not have indexes",
+                });
+            }
+            (SyncSection::Delta, None) => {
+                return Err(PacketError::UnencodableState {
+                    net_id: state.net_id,
+                    reason: "in a delta section must have indexes",
+                });
+            }
+        }
+
+        out_packet.extend(&(encoded_values.len() as u32).to_le_bytes());
+        out_packet.extend(encoded_values);
+    }
+
+    Ok(out_packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::{Bool, Float, Int, VariantString};
+
+    fn value(variant: impl Variant) -> Arc<Box<dyn Variant>> {
+        Arc::new(Box::new(variant))
+    }
+
+    fn round_trip(command: SyncCommand) {
+        let packet = gen_packet(&command).unwrap();
+
+        assert_eq!(
+            parse_packet(&packet).unwrap(),
+            Packet::NetworkCommandSync(command)
+        );
+    }
+
+    #[test]
+    fn round_trips_sync() {
+        round_trip(SyncCommand {
+            section: SyncSection::Sync {
+                sync_net_time: 0xBEEF,
+            },
+            states: vec![
+                SyncState {
+                    net_id: 1,
+                    indexes: None,
+                    values: vec![value(Float::from(0.5)), value(Int(-3))],
+                },
+                SyncState {
+                    net_id: 4 | NET_ID_PATH_FLAG,
+                    indexes: None,
+                    values: vec![value(VariantString("name".to_string()))],
+                },
+            ],
+        });
+    }
+
+    #[test]
+    fn round_trips_delta() {
+        round_trip(SyncCommand {
+            section: SyncSection::Delta,
+            states: vec![
+                SyncState {
+                    net_id: 2,
+                    indexes: Some(0b101),
+                    values: vec![value(Bool(true)), value(Int(7))],
+                },
+                SyncState {
+                    net_id: 3,
+                    indexes: Some(1 << 63),
+                    values: vec![value(Float::from(1.0e300))],
+                },
+            ],
+        });
+    }
+
+    #[test]
+    fn rejects_empty_states() {
+        let mut command = SyncCommand {
+            section: SyncSection::Delta,
+            states: Vec::new(),
+        };
+
+        assert!(gen_packet(&command).is_err());
+
+        command.states.push(SyncState {
+            net_id: 1,
+            indexes: Some(0),
+            values: Vec::new(),
+        });
+
+        assert!(gen_packet(&command).is_err());
+    }
+
+    #[test]
+    fn rejects_short_packets() {
+        // Command and sync time, without any state
+        assert!(parse_packet(&[6, 1, 0]).is_err());
+        // Delta command with a truncated state header
+        assert!(parse_packet(&[6 | DELTA_FLAG, 1, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::Packet;
+use super::{Packet, PacketError};
 use crate::GDPeerID;
 
 const SYS_CMD_SIZE: usize = 6;
@@ -35,9 +35,12 @@ pub enum SysAuthCommand {
 }
 
 // Heavily Uses SceneMultiplayer::_process_sys() in Godot to revese engineer the header
-pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
+pub fn parse_packet(packet: &[u8]) -> Result<Packet, PacketError> {
     if packet.len() < SYS_CMD_SIZE && packet.len() < 1 && packet[1] != 0 {
-        return Err("Packet too short to contain Godot ENet sys command header".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet sys command header",
+            len: packet.len(),
+        });
     }
 
     let sys_cmd = match packet[1] {
@@ -49,16 +52,19 @@ pub fn parse_packet(packet: &[u8]) -> Result<Packet, String> {
             packet[2], packet[3], packet[4], packet[5],
         ]) as i32)),
         3 => parse_relay_command(packet)?,
-        _ => return Err(format!("Invalid value for Sys command: {}", packet[1])),
+        _ => return Err(PacketError::UnknownSysCommand(packet[1])),
     };
 
     Ok(Packet::NetworkCommandSys(SysCommandPacket { sys_cmd }))
 }
 
-fn parse_relay_command(packet: &[u8]) -> Result<SysCommand, String> {
+fn parse_relay_command(packet: &[u8]) -> Result<SysCommand, PacketError> {
     // Make sure the packet still contains content to relay
     if packet.len() < SYS_CMD_SIZE + 1 {
-        return Err("Packet too short to contain Godot ENet sys relay command header".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet sys relay command header",
+            len: packet.len(),
+        });
     }
 
     Ok(SysCommand::SysCommandRelay {
@@ -69,9 +75,12 @@ fn parse_relay_command(packet: &[u8]) -> Result<SysCommand, String> {
 }
 
 // Uses SceneMultiplayer::poll() in Godot to reverse engineer the auth command
-fn parse_auth_command(packet: &[u8]) -> Result<SysCommand, String> {
+fn parse_auth_command(packet: &[u8]) -> Result<SysCommand, PacketError> {
     if packet.len() < 2 {
-        return Err("Packet too short to contain Godot ENet sys auth command".to_string());
+        return Err(PacketError::Truncated {
+            part: "Godot ENet sys auth command",
+            len: packet.len(),
+        });
     }
 
     if packet.len() == 2 {
@@ -86,7 +95,7 @@ fn parse_auth_command(packet: &[u8]) -> Result<SysCommand, String> {
 }
 
 // Reverse of parse_packet
-pub fn gen_packet(packet: &SysCommandPacket) -> Result<Vec<u8>, String> {
+pub fn gen_packet(packet: &SysCommandPacket) -> Result<Vec<u8>, PacketError> {
     let mut out_packet: Vec<u8> = Vec::new();
 
     out_packet.push(7); // CMD_MASK for Sys Command
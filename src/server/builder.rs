@@ -1,26 +1,109 @@
-use crate::{Layer, Server};
+use crate::{
+    ChecksumFunction, CompressorFactory, DispatchMode, HostConfig, Layer, MAX_HOSTS, MAX_SHARDS,
+    SendFailureReporter, Server, ServerError, ServiceBudget, checksum,
+    compression::CompressionMode, dtls::DtlsConfig, socket::BindOptions,
+};
+use dashmap::DashMap;
 use std::{
+    collections::{HashMap, VecDeque},
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    sync::{Arc, mpsc},
+    sync::Arc,
+};
+use tokio::{
+    sync::{Semaphore, mpsc},
+    task::JoinSet,
 };
 
+// Default maximum of events queued or being processed by the layers
+const DEFAULT_MAX_IN_FLIGHT: usize = 1024;
+
+#[derive(Clone)]
 pub struct ServerBuilder {
-    address: SocketAddr,
+    addresses: Vec<SocketAddr>,
+    bind_options: BindOptions,
+    shard: usize,
+    host_config: HostConfig,
+    dtls: Option<DtlsConfig>,
 
     layers: Vec<Arc<dyn Layer>>,
+
+    service_budget: ServiceBudget,
+
+    dispatch_mode: DispatchMode,
+    max_in_flight: usize,
+
+    send_failure_reporter: Option<SendFailureReporter>,
 }
 
 impl ServerBuilder {
     pub fn new() -> ServerBuilder {
         ServerBuilder {
-            address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 55556),
+            addresses: vec![SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                55556,
+            )],
+            bind_options: BindOptions::default(),
+            shard: 0,
+            host_config: HostConfig::default(),
+            dtls: None,
 
             layers: Vec::new(),
+
+            service_budget: ServiceBudget::default(),
+
+            dispatch_mode: DispatchMode::default(),
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+
+            send_failure_reporter: None,
         }
     }
 
-    pub fn build(self) -> Result<Server, String> {
-        let (tx_outgoing, rx_outgoing) = mpsc::channel();
+    pub fn build(self) -> Result<Server, ServerError> {
+        self.host_config.validate()?;
+
+        if self.addresses.is_empty() || self.addresses.len() > MAX_HOSTS {
+            return Err(ServerError::OutOfRange {
+                setting: "Bound addresses",
+                min: 1,
+                max: Some(MAX_HOSTS),
+                value: self.addresses.len(),
+            });
+        }
+
+        if self.shard >= MAX_SHARDS {
+            return Err(ServerError::OutOfRange {
+                setting: "Shard",
+                min: 0,
+                max: Some(MAX_SHARDS - 1),
+                value: self.shard,
+            });
+        }
+
+        for (i, address) in self.addresses.iter().enumerate() {
+            if self.addresses[..i].contains(address) {
+                return Err(ServerError::DuplicateAddress(*address));
+            }
+        }
+
+        if self.service_budget.max_events == 0 {
+            return Err(ServerError::OutOfRange {
+                setting: "Service budget events",
+                min: 1,
+                max: None,
+                value: self.service_budget.max_events,
+            });
+        }
+
+        if self.max_in_flight == 0 {
+            return Err(ServerError::OutOfRange {
+                setting: "Maximum events in flight",
+                min: 1,
+                max: None,
+                value: self.max_in_flight,
+            });
+        }
+
+        let (tx_outgoing, rx_outgoing) = mpsc::unbounded_channel();
 
         let mut layers: Vec<Arc<dyn Layer>> = Vec::new();
 
@@ -29,14 +112,37 @@ impl ServerBuilder {
         }
 
         let server = Server {
-            host: None,
+            hosts: Vec::new(),
+            next_host: 0,
+
+            addresses: self.addresses,
+            bind_options: self.bind_options,
 
-            address: self.address,
+            shard: self.shard,
+            shard_routes: Vec::new(),
+            host_config: self.host_config,
+            dtls: self.dtls,
 
             layers: Arc::new(layers),
 
             tx_outgoing: tx_outgoing,
             rx_outgoing: rx_outgoing,
+
+            gd_peers: HashMap::new(),
+            tasks: JoinSet::new(),
+
+            service_budget: self.service_budget,
+
+            dispatch_mode: self.dispatch_mode,
+            peer_queues: Arc::new(DashMap::new()),
+            in_flight: Arc::new(Semaphore::new(self.max_in_flight)),
+            pending: VecDeque::new(),
+            max_in_flight: self.max_in_flight,
+
+            send_failure_reporter: self.send_failure_reporter,
+            dropped_sends: 0,
+
+            shutdown_reason: None,
         };
 
         Ok(server)
@@ -46,20 +152,166 @@ impl ServerBuilder {
 // Address Implementations
 impl ServerBuilder {
     /// Set the socket address for the server to bind to
-    /// Overrides address and port if previously set
+    /// Overrides every address previously set
     pub fn socket(mut self, address: SocketAddr) -> ServerBuilder {
-        self.address = address;
+        self.addresses = vec![address];
+        self
+    }
+
+    /// Set every socket address for the server to bind to,
+    /// each serviced by its own ENet host
+    ///
+    /// Every host gets the limits of the [`HostConfig`],
+    /// and the events of all of them go through the same layers.
+    pub fn sockets(mut self, addresses: impl IntoIterator<Item = SocketAddr>) -> ServerBuilder {
+        self.addresses = addresses.into_iter().collect();
+        self
+    }
+
+    /// Add a socket address for the server to bind to,
+    /// serviced by its own ENet host
+    pub fn add_socket(mut self, address: SocketAddr) -> ServerBuilder {
+        self.addresses.push(address);
         self
     }
 
     /// Set the address and port for the server to bind to
-    pub fn address(mut self, address: &str, port: &str) -> Result<ServerBuilder, String> {
-        self.address = format!("{}:{}", address, port)
+    /// Overrides every address previously set
+    pub fn address(mut self, address: &str, port: &str) -> Result<ServerBuilder, ServerError> {
+        let address = format!("{}:{}", address, port);
+        let parsed = address
             .parse::<SocketAddr>()
-            .map_err(|e| format!("Failed to parse socket address: {}", e))?;
+            .map_err(|source| ServerError::InvalidAddress { address, source })?;
+
+        self.addresses = vec![parsed];
 
         Ok(self)
     }
+
+    /// Set whether IPv6 addresses also accept IPv4 clients, enabled by default
+    ///
+    /// Disable it to bind both `0.0.0.0` and `[::]` on the same port.
+    pub fn dual_stack(mut self, dual_stack: bool) -> ServerBuilder {
+        self.bind_options.dual_stack = dual_stack;
+        self
+    }
+
+    /// Set `SO_REUSEPORT` on the sockets, so several servers can bind the same addresses
+    ///
+    /// The kernel spreads the clients between the servers, see [`ShardedServer`](crate::ShardedServer).
+    /// Only supported on Unix.
+    pub fn reuse_port(mut self, reuse_port: bool) -> ServerBuilder {
+        self.bind_options.reuse_port = reuse_port;
+        self
+    }
+
+    /// Set the index of the shard the server is, keeping its peer ids distinct
+    /// from those of the other shards, see [`ENetPeerID::in_shard`](crate::ENetPeerID::in_shard)
+    pub fn shard(mut self, shard: usize) -> ServerBuilder {
+        self.shard = shard;
+        self
+    }
+}
+
+// Security Implementations
+impl ServerBuilder {
+    /// Encrypt every connection with DTLS, like `ENetConnection.dtls_server_setup`
+    ///
+    /// Godot clients must connect with `ENetConnection.dtls_client_setup`.
+    pub fn dtls(mut self, dtls: DtlsConfig) -> ServerBuilder {
+        self.dtls = Some(dtls);
+        self
+    }
+}
+
+// Host Implementations
+impl ServerBuilder {
+    /// Set every setting of the ENet host
+    pub fn host_config(mut self, host_config: HostConfig) -> ServerBuilder {
+        self.host_config = host_config;
+        self
+    }
+
+    /// Set the limits of the host like Godot's
+    /// `ENetMultiplayerPeer.create_server(port, max_clients, max_channels, in_bandwidth, out_bandwidth)`
+    ///
+    /// Keeps the compressor, checksum and seed, see [`HostConfig::godot_server`]
+    pub fn godot_server(
+        mut self,
+        max_clients: usize,
+        max_channels: usize,
+        in_bandwidth: u32,
+        out_bandwidth: u32,
+    ) -> ServerBuilder {
+        self.host_config = HostConfig {
+            compressor: self.host_config.compressor,
+            checksum: self.host_config.checksum,
+            seed: self.host_config.seed,
+            ..HostConfig::godot_server(max_clients, max_channels, in_bandwidth, out_bandwidth)
+        };
+        self
+    }
+
+    /// Set the maximum number of peers
+    pub fn peer_limit(mut self, peer_limit: usize) -> ServerBuilder {
+        self.host_config.peer_limit = peer_limit;
+        self
+    }
+
+    /// Set the number of ENet channels
+    pub fn channel_limit(mut self, channel_limit: usize) -> ServerBuilder {
+        self.host_config.channel_limit = channel_limit;
+        self
+    }
+
+    /// Set the bandwidth limits in bytes/second, [`None`] for no limit
+    pub fn bandwidth_limits(
+        mut self,
+        incoming_bandwidth_limit: Option<u32>,
+        outgoing_bandwidth_limit: Option<u32>,
+    ) -> ServerBuilder {
+        self.host_config.incoming_bandwidth_limit = incoming_bandwidth_limit;
+        self.host_config.outgoing_bandwidth_limit = outgoing_bandwidth_limit;
+        self
+    }
+
+    /// Set the compression of the host to one of Godot's modes
+    ///
+    /// Must match the mode Godot clients pass to `ENetConnection.compress`
+    pub fn compression(mut self, compression_mode: CompressionMode) -> ServerBuilder {
+        self.host_config.compressor = compression_mode.factory();
+        self
+    }
+
+    /// Set a custom compressor of the host
+    ///
+    /// Must be compatible with the compression of the Godot clients
+    pub fn compressor(mut self, compressor: CompressorFactory) -> ServerBuilder {
+        self.host_config.compressor = Some(compressor);
+        self
+    }
+
+    /// Set the checksum of the host to ENet's CRC32
+    ///
+    /// Must be enabled on the Godot clients too
+    pub fn crc32_checksum(mut self) -> ServerBuilder {
+        self.host_config.checksum = Some(checksum::crc32_checksum());
+        self
+    }
+
+    /// Set a custom checksum of the host
+    ///
+    /// Must match the checksum of the Godot clients
+    pub fn checksum(mut self, checksum: ChecksumFunction) -> ServerBuilder {
+        self.host_config.checksum = Some(checksum);
+        self
+    }
+
+    /// Set the random seed of the host
+    pub fn seed(mut self, seed: u32) -> ServerBuilder {
+        self.host_config.seed = Some(seed);
+        self
+    }
 }
 
 // Layer Implementations
@@ -110,6 +362,45 @@ impl ServerBuilder {
     // TODO: Add Multiple Layers At Once, Probably With Some "LayerSet"
 }
 
+// Dispatch Implementations
+impl ServerBuilder {
+    /// Set the limits on the events handled by a single service
+    ///
+    /// Use a `max_events` of 1 to handle a single event per service
+    pub fn service_budget(mut self, service_budget: ServiceBudget) -> ServerBuilder {
+        self.service_budget = service_budget;
+        self
+    }
+
+    /// Set how events are processed through the layers
+    ///
+    /// Defaults to [`DispatchMode::Ordered`]
+    pub fn dispatch_mode(mut self, dispatch_mode: DispatchMode) -> ServerBuilder {
+        self.dispatch_mode = dispatch_mode;
+        self
+    }
+
+    /// Set the maximum number of events queued or being processed by the layers
+    ///
+    /// Once the maximum is reached, further events wait in the server until they can start,
+    /// while the hosts keep being serviced so their peers do not time out
+    pub fn max_in_flight(mut self, max_in_flight: usize) -> ServerBuilder {
+        self.max_in_flight = max_in_flight;
+        self
+    }
+}
+
+// Send Implementations
+impl ServerBuilder {
+    /// Set the function called with every outgoing packet which could not be sent
+    ///
+    /// Failed sends are always logged and counted in [`Server::dropped_sends`]
+    pub fn send_failure_reporter(mut self, reporter: SendFailureReporter) -> ServerBuilder {
+        self.send_failure_reporter = Some(reporter);
+        self
+    }
+}
+
 impl Default for ServerBuilder {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,80 @@
+use super::ChecksumFunction;
+use rusty_enet as enet;
+use std::sync::Arc;
+
+/// ENet's CRC32 checksum, the one Godot clients use with `ENetConnection`
+///
+/// Computed over every buffer of the datagram, with the checksum field
+/// holding the connect ID of the peer (or 0 before it is known).
+pub fn crc32(buffers: &[&[u8]]) -> u32 {
+    enet::crc32(buffers)
+}
+
+/// The [`ChecksumFunction`] of [`crc32`]
+pub fn crc32_checksum() -> ChecksumFunction {
+    Arc::new(crc32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Datagrams captured between two hosts using ENet's CRC32 checksum
+    const CONNECT: &[u8] = &[
+        0x8f, 0xff, 0xf0, 0x4c, 0x34, 0xc0, 0xa6, 0xcb, 0x82, 0xff, 0x00, 0x01, 0x00, 0x00, 0xff,
+        0xff, 0x00, 0x00, 0x05, 0x70, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x88, 0x00, 0x00, 0x00, 0x02, 0x00,
+        0x00, 0x00, 0x02, 0xfb, 0x59, 0x4c, 0xff, 0x00, 0x00, 0x00, 0x07,
+    ];
+    const VERIFY_CONNECT: &[u8] = &[
+        0x80, 0x00, 0xf0, 0x56, 0x54, 0xb0, 0xe0, 0x96, 0x83, 0xff, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x05, 0x70, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x88, 0x00, 0x00, 0x00, 0x02, 0x00,
+        0x00, 0x00, 0x02, 0xfb, 0x59, 0x4c, 0xff,
+    ];
+    const ACKNOWLEDGE: &[u8] = &[
+        0x80, 0x00, 0xf0, 0x6e, 0x5e, 0xad, 0x9a, 0x0f, 0x01, 0xff, 0x00, 0x01, 0x00, 0x01, 0xf0,
+        0x56, 0x85, 0xff, 0x00, 0x02,
+    ];
+
+    // Peer ID and sent time, then the checksum
+    const CHECKSUM: std::ops::Range<usize> = 4..8;
+    // Connect ID of the connect command, as it is on the wire
+    const CONNECT_ID: [u8; 4] = [0xfb, 0x59, 0x4c, 0xff];
+
+    fn verify(datagram: &[u8], seed: [u8; 4]) -> bool {
+        let mut datagram = datagram.to_vec();
+        let checksum = u32::from_ne_bytes(datagram[CHECKSUM].try_into().unwrap());
+        datagram[CHECKSUM].copy_from_slice(&seed);
+
+        crc32(&[&datagram]) == checksum
+    }
+
+    #[test]
+    fn verifies_captured_datagrams() {
+        assert!(verify(CONNECT, [0; 4]));
+        assert!(verify(VERIFY_CONNECT, CONNECT_ID));
+        assert!(verify(ACKNOWLEDGE, CONNECT_ID));
+    }
+
+    #[test]
+    fn rejects_corrupted_datagrams() {
+        let mut corrupted = ACKNOWLEDGE.to_vec();
+        corrupted[12] ^= 1;
+
+        assert!(!verify(&corrupted, CONNECT_ID));
+        assert!(!verify(VERIFY_CONNECT, [0; 4]));
+    }
+
+    #[test]
+    fn checksums_split_buffers() {
+        let (header, commands) = ACKNOWLEDGE.split_at(8);
+
+        assert_eq!(crc32(&[header, commands]), crc32(&[ACKNOWLEDGE]));
+    }
+
+    #[test]
+    fn checksum_function_matches_crc32() {
+        assert_eq!(crc32_checksum()(&[CONNECT]), crc32(&[CONNECT]));
+    }
+}
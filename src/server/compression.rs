@@ -0,0 +1,615 @@
+use super::CompressorFactory;
+use flate2::{Compress, Decompress, FlushCompress, FlushDecompress, Status};
+use rusty_enet::{self as enet, Compressor};
+use std::sync::Arc;
+
+// From core/io/compression.cpp, Compression::zlib_level and Compression::zstd_level
+const ZLIB_LEVEL: u32 = 6;
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// How ENet packets are compressed, mirrors `ENetConnection.CompressionMode`
+///
+/// Must match the mode Godot clients pass to `ENetConnection.compress`.
+pub enum CompressionMode {
+    #[default]
+    None,
+    /// ENet's built-in range coder.
+    RangeCoder,
+    FastLz,
+    /// Zlib, which Godot calls `Compression::MODE_DEFLATE`.
+    Zlib,
+    Zstd,
+}
+
+impl CompressionMode {
+    /// Create the compressor of the mode, [`None`] for no compression
+    pub fn compressor(self) -> Option<Box<dyn Compressor>> {
+        match self {
+            CompressionMode::None => None,
+            CompressionMode::RangeCoder => Some(Box::new(enet::RangeCoder::new())),
+            CompressionMode::FastLz => Some(Box::new(FastLzCompressor::default())),
+            CompressionMode::Zlib => Some(Box::new(ZlibCompressor::default())),
+            CompressionMode::Zstd => Some(Box::new(ZstdCompressor::default())),
+        }
+    }
+
+    /// The [`CompressorFactory`] of the mode, [`None`] for no compression
+    pub fn factory(self) -> Option<CompressorFactory> {
+        if self == CompressionMode::None {
+            return None;
+        }
+
+        Some(Arc::new(move || {
+            self.compressor()
+                .expect("Only modes with a compressor have a factory")
+        }))
+    }
+}
+
+/// Copy the buffers ENet compresses into a single buffer,
+/// based on ENetConnection::enet_compress
+fn gather(in_buffers: &[&[u8]], in_limit: usize, src: &mut Vec<u8>) {
+    src.clear();
+
+    for in_buffer in in_buffers {
+        let to_copy = in_buffer.len().min(in_limit - src.len());
+        src.extend_from_slice(&in_buffer[..to_copy]);
+    }
+}
+
+/// Copy the compressed packet out, unless it does not fit
+fn output(compressed: &[u8], out: &mut [u8]) -> usize {
+    if compressed.len() > out.len() {
+        return 0;
+    }
+
+    out[..compressed.len()].copy_from_slice(compressed);
+
+    compressed.len()
+}
+
+#[derive(Default)]
+/// FastLZ compression, compatible with Godot's `COMPRESS_FASTLZ`
+pub struct FastLzCompressor {
+    src: Vec<u8>,
+    dst: Vec<u8>,
+}
+
+impl Compressor for FastLzCompressor {
+    fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
+        gather(in_buffers, in_limit, &mut self.src);
+
+        // Godot pads short inputs, as FastLZ needs at least 16 bytes
+        if self.src.len() < 16 {
+            self.src.resize(16, 0);
+        }
+
+        self.dst.clear();
+        fastlz::compress(&self.src, &mut self.dst);
+
+        output(&self.dst, out)
+    }
+
+    fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
+        fastlz::decompress(in_data, out).unwrap_or(0)
+    }
+}
+
+/// Zlib compression, compatible with Godot's `COMPRESS_ZLIB`
+pub struct ZlibCompressor {
+    src: Vec<u8>,
+    dst: Vec<u8>,
+
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl Default for ZlibCompressor {
+    fn default() -> Self {
+        Self {
+            src: Vec::new(),
+            dst: Vec::new(),
+
+            compress: Compress::new(flate2::Compression::new(ZLIB_LEVEL), true),
+            decompress: Decompress::new(true),
+        }
+    }
+}
+
+impl Compressor for ZlibCompressor {
+    fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
+        gather(in_buffers, in_limit, &mut self.src);
+
+        // Bound of deflate, from zlib's deflateBound
+        let bound = self.src.len() + (self.src.len() >> 12) + (self.src.len() >> 14) + 64;
+
+        self.dst.clear();
+        self.dst.reserve(bound);
+        self.compress.reset();
+
+        match self
+            .compress
+            .compress_vec(&self.src, &mut self.dst, FlushCompress::Finish)
+        {
+            Ok(Status::StreamEnd) => output(&self.dst, out),
+            _ => 0,
+        }
+    }
+
+    fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
+        self.decompress.reset(true);
+
+        match self
+            .decompress
+            .decompress(in_data, out, FlushDecompress::Finish)
+        {
+            Ok(Status::StreamEnd) => self.decompress.total_out() as usize,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Default)]
+/// Zstandard compression, compatible with Godot's `COMPRESS_ZSTD`
+pub struct ZstdCompressor {
+    src: Vec<u8>,
+    dst: Vec<u8>,
+}
+
+impl Compressor for ZstdCompressor {
+    fn compress(&mut self, in_buffers: &[&[u8]], in_limit: usize, out: &mut [u8]) -> usize {
+        gather(in_buffers, in_limit, &mut self.src);
+
+        self.dst.clear();
+        self.dst
+            .reserve(zstd::zstd_safe::compress_bound(self.src.len()));
+
+        match zstd::zstd_safe::compress(&mut self.dst, &self.src, ZSTD_LEVEL) {
+            Ok(_) => output(&self.dst, out),
+            Err(_) => 0,
+        }
+    }
+
+    fn decompress(&mut self, in_data: &[u8], out: &mut [u8]) -> usize {
+        zstd::bulk::decompress_to_buffer(in_data, out).unwrap_or(0)
+    }
+}
+
+/// Port of FastLZ 0.5.0, bundled with Godot in thirdparty/misc/fastlz.c
+///
+/// Only compresses with level 1, as Godot only uses level 2 for inputs of 64 KiB and above,
+/// which ENet packets never reach.
+mod fastlz {
+    const MAX_COPY: usize = 32;
+    const MAX_LEN: usize = 264;
+    const MAX_L1_DISTANCE: usize = 8192;
+
+    const HASH_LOG: u32 = 13;
+    const HASH_SIZE: usize = 1 << HASH_LOG;
+    const HASH_MASK: u32 = (HASH_SIZE as u32) - 1;
+
+    fn read_u32(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+    }
+
+    fn hash(v: u32) -> usize {
+        ((v.wrapping_mul(2654435769) >> (32 - HASH_LOG)) & HASH_MASK) as usize
+    }
+
+    fn literals(mut runs: usize, mut src: &[u8], dest: &mut Vec<u8>) {
+        while runs >= MAX_COPY {
+            dest.push((MAX_COPY - 1) as u8);
+            dest.extend_from_slice(&src[..MAX_COPY]);
+            src = &src[MAX_COPY..];
+            runs -= MAX_COPY;
+        }
+
+        if runs > 0 {
+            dest.push((runs - 1) as u8);
+            dest.extend_from_slice(&src[..runs]);
+        }
+    }
+
+    // Length of the match, one past the first mismatch
+    fn cmp(data: &[u8], mut p: usize, mut q: usize, r: usize) -> usize {
+        let start = p;
+
+        if read_u32(data, p) == read_u32(data, q) {
+            p += 4;
+            q += 4;
+        }
+
+        while q < r {
+            let equal = data[p] == data[q];
+            p += 1;
+            q += 1;
+
+            if !equal {
+                break;
+            }
+        }
+
+        p - start
+    }
+
+    fn match_1(mut len: usize, distance: usize, op: &mut Vec<u8>) {
+        let distance = distance - 1;
+
+        while len > MAX_LEN - 2 {
+            op.push(((7 << 5) + (distance >> 8)) as u8);
+            op.push((MAX_LEN - 2 - 7 - 2) as u8);
+            op.push((distance & 255) as u8);
+            len -= MAX_LEN - 2;
+        }
+
+        if len < 7 {
+            op.push(((len << 5) + (distance >> 8)) as u8);
+            op.push((distance & 255) as u8);
+        } else {
+            op.push(((7 << 5) + (distance >> 8)) as u8);
+            op.push((len - 7) as u8);
+            op.push((distance & 255) as u8);
+        }
+    }
+
+    /// Compress with level 1, based on fastlz1_compress
+    pub fn compress(input: &[u8], op: &mut Vec<u8>) {
+        let length = input.len();
+
+        if length < 13 {
+            literals(length, input, op);
+            return;
+        }
+
+        let ip_bound = length - 4;
+        let ip_limit = length - 12 - 1;
+
+        let mut htab = vec![0usize; HASH_SIZE];
+
+        let mut anchor = 0;
+        let mut ip = 2;
+
+        while ip < ip_limit {
+            let mut reference;
+            let mut distance;
+
+            // Find a potential match
+            loop {
+                let seq = read_u32(input, ip) & 0xffffff;
+                let h = hash(seq);
+                reference = htab[h];
+                htab[h] = ip;
+                distance = ip - reference;
+
+                let cmp = if distance < MAX_L1_DISTANCE {
+                    read_u32(input, reference) & 0xffffff
+                } else {
+                    0x1000000
+                };
+
+                if ip >= ip_limit {
+                    break;
+                }
+
+                ip += 1;
+
+                if seq == cmp {
+                    break;
+                }
+            }
+
+            if ip >= ip_limit {
+                break;
+            }
+
+            ip -= 1;
+
+            if ip > anchor {
+                literals(ip - anchor, &input[anchor..], op);
+            }
+
+            let len = cmp(input, reference + 3, ip + 3, ip_bound);
+            match_1(len, distance, op);
+
+            // Update the hash at the match boundary
+            ip += len;
+            let seq = read_u32(input, ip);
+            htab[hash(seq & 0xffffff)] = ip;
+            ip += 1;
+            htab[hash(seq >> 8)] = ip;
+            ip += 1;
+
+            anchor = ip;
+        }
+
+        literals(length - anchor, &input[anchor..], op);
+    }
+
+    /// Decompress with the level of the input, based on fastlz_decompress,
+    /// returning [`None`] for invalid input or if the output does not fit
+    pub fn decompress(input: &[u8], output: &mut [u8]) -> Option<usize> {
+        match input.first()? >> 5 {
+            0 => decompress_1(input, output),
+            1 => decompress_2(input, output),
+            _ => None,
+        }
+    }
+
+    fn copy_match(output: &mut [u8], op: usize, reference: usize, len: usize) -> Option<()> {
+        if op + len > output.len() {
+            return None;
+        }
+
+        // Byte by byte, as the match may overlap its own output
+        for i in 0..len {
+            output[op + i] = output[reference + i];
+        }
+
+        Some(())
+    }
+
+    fn copy_literals(
+        input: &[u8],
+        ip: usize,
+        output: &mut [u8],
+        op: usize,
+        len: usize,
+    ) -> Option<()> {
+        if op + len > output.len() || ip + len > input.len() {
+            return None;
+        }
+
+        output[op..(op + len)].copy_from_slice(&input[ip..(ip + len)]);
+
+        Some(())
+    }
+
+    // Based on fastlz1_decompress
+    fn decompress_1(input: &[u8], output: &mut [u8]) -> Option<usize> {
+        let ip_bound = input.len().checked_sub(2)?;
+
+        let mut ip = 1;
+        let mut op: usize = 0;
+        let mut ctrl = (input[0] & 31) as usize;
+
+        loop {
+            if ctrl >= 32 {
+                let mut len = (ctrl >> 5) - 1;
+                let offset = (ctrl & 31) << 8;
+
+                if len == 7 - 1 {
+                    len += *input.get(ip)? as usize;
+                    ip += 1;
+                }
+
+                let reference = op
+                    .checked_sub(offset + 1)?
+                    .checked_sub(*input.get(ip)? as usize)?;
+                ip += 1;
+                len += 3;
+
+                copy_match(output, op, reference, len)?;
+                op += len;
+            } else {
+                ctrl += 1;
+
+                copy_literals(input, ip, output, op, ctrl)?;
+                ip += ctrl;
+                op += ctrl;
+            }
+
+            if ip > ip_bound {
+                break;
+            }
+
+            ctrl = input[ip] as usize;
+            ip += 1;
+        }
+
+        Some(op)
+    }
+
+    // Based on fastlz2_decompress
+    fn decompress_2(input: &[u8], output: &mut [u8]) -> Option<usize> {
+        const MAX_L2_DISTANCE: usize = 8191;
+
+        let mut ip = 1;
+        let mut op: usize = 0;
+        let mut ctrl = (input[0] & 31) as usize;
+
+        loop {
+            if ctrl >= 32 {
+                let mut len = (ctrl >> 5) - 1;
+                let mut offset = (ctrl & 31) << 8;
+
+                if len == 7 - 1 {
+                    loop {
+                        let code = *input.get(ip)? as usize;
+                        ip += 1;
+                        len += code;
+
+                        if code != 255 {
+                            break;
+                        }
+                    }
+                }
+
+                let code = *input.get(ip)? as usize;
+                ip += 1;
+                offset += code;
+                len += 3;
+
+                // Far distance match
+                if code == 255 && offset - code == 31 << 8 {
+                    offset = ((*input.get(ip)? as usize) << 8) + *input.get(ip + 1)? as usize;
+                    ip += 2;
+                    offset += MAX_L2_DISTANCE;
+                }
+
+                let reference = op.checked_sub(offset + 1)?;
+
+                copy_match(output, op, reference, len)?;
+                op += len;
+            } else {
+                ctrl += 1;
+
+                copy_literals(input, ip, output, op, ctrl)?;
+                ip += ctrl;
+                op += ctrl;
+            }
+
+            if ip >= input.len() {
+                break;
+            }
+
+            ctrl = input[ip] as usize;
+            ip += 1;
+        }
+
+        Some(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // None of these vectors were captured from a Godot peer,
+    // they come from the libraries Godot bundles, used with Godot's settings.
+    const INPUT: &[u8] = b"godot godot godot godot godot godot";
+
+    // The C zlib 1.2.13 with the deflateInit2 settings of Compression::compress for MODE_DEFLATE:
+    // level 6, 15 window bits, memory level 8 and the default strategy.
+    // flate2 is built on a Rust port, so this checks it against the reference zlib.
+    const ZLIB_VECTOR: &[u8] = &[
+        0x78, 0x9c, 0x4b, 0xcf, 0x4f, 0xc9, 0x2f, 0x51, 0x48, 0xc7, 0x47, 0x02, 0x00, 0xef, 0x03,
+        0x0d, 0x4f,
+    ];
+
+    // The C zstd 1.5.7 bundled by zstd-sys, at the level 3 of Compression::compress for MODE_ZSTD.
+    // The zstd crate wraps the same library, so only the level and framing are checked.
+    const ZSTD_VECTOR: &[u8] = &[
+        0x28, 0xb5, 0x2f, 0xfd, 0x20, 0x23, 0x65, 0x00, 0x00, 0x30, 0x67, 0x6f, 0x64, 0x6f, 0x74,
+        0x20, 0x01, 0x00, 0xb9, 0x4b, 0x11,
+    ];
+
+    const FASTLZ_INPUT: &[u8] = b"godotgodotgodotgodot!";
+
+    // Hand encoded in the level 1 block format of fastlz.c, which Godot bundles in thirdparty/misc:
+    // a literal run, a long match at distance 5, then a literal run
+    const FASTLZ_VECTOR: &[u8] = &[
+        4, b'g', b'o', b'd', b'o', b't', 224, 2, 4, 4, b'o', b'd', b'o', b't', b'!',
+    ];
+
+    // enet_range_coder_compress as ported by rusty_enet from the compress.c Godot bundles in thirdparty/enet,
+    // so changes to the range coder which break the wire format are caught
+    const RANGE_CODER_VECTOR: &[u8] = &[
+        104, 9, 146, 109, 133, 138, 24, 141, 131, 252, 231, 219, 205, 21,
+    ];
+
+    fn compress(compressor: &mut dyn Compressor, input: &[u8]) -> Vec<u8> {
+        // ENet passes the packet in several buffers
+        let (head, tail) = input.split_at(input.len() / 3);
+
+        let mut out = vec![0; 4096];
+        let len = compressor.compress(&[head, tail], input.len(), &mut out);
+        out.truncate(len);
+
+        out
+    }
+
+    fn decompress(compressor: &mut dyn Compressor, input: &[u8]) -> Vec<u8> {
+        let mut out = vec![0; 4096];
+        let len = compressor.decompress(input, &mut out);
+        out.truncate(len);
+
+        out
+    }
+
+    #[test]
+    fn decompresses_reference_vectors() {
+        assert_eq!(
+            decompress(&mut ZlibCompressor::default(), ZLIB_VECTOR),
+            INPUT
+        );
+        assert_eq!(
+            decompress(&mut ZstdCompressor::default(), ZSTD_VECTOR),
+            INPUT
+        );
+        assert_eq!(
+            decompress(&mut FastLzCompressor::default(), FASTLZ_VECTOR),
+            FASTLZ_INPUT
+        );
+        assert_eq!(
+            decompress(&mut enet::RangeCoder::new(), RANGE_CODER_VECTOR),
+            INPUT
+        );
+    }
+
+    #[test]
+    fn compresses_reference_vectors() {
+        assert_eq!(compress(&mut ZstdCompressor::default(), INPUT), ZSTD_VECTOR);
+        assert_eq!(
+            compress(&mut FastLzCompressor::default(), FASTLZ_INPUT),
+            FASTLZ_VECTOR
+        );
+        assert_eq!(
+            compress(&mut enet::RangeCoder::new(), INPUT),
+            RANGE_CODER_VECTOR
+        );
+    }
+
+    #[test]
+    fn decompresses_fastlz_level_2() {
+        let mut vector = FASTLZ_VECTOR.to_vec();
+        vector[0] |= 1 << 5;
+
+        assert_eq!(
+            decompress(&mut FastLzCompressor::default(), &vector),
+            FASTLZ_INPUT
+        );
+    }
+
+    #[test]
+    fn round_trips_every_mode() {
+        let input: Vec<u8> = (0..1200).map(|i| (i % 7 * i % 13) as u8).collect();
+
+        for mode in [
+            CompressionMode::RangeCoder,
+            CompressionMode::FastLz,
+            CompressionMode::Zlib,
+            CompressionMode::Zstd,
+        ] {
+            let mut compressor = mode.compressor().unwrap();
+
+            let compressed = compress(compressor.as_mut(), &input);
+            assert!(!compressed.is_empty(), "{:?} failed to compress", mode);
+
+            assert_eq!(
+                decompress(compressor.as_mut(), &compressed),
+                input,
+                "{:?} failed to round trip",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_output_too_small() {
+        let mut out = vec![0; 4];
+
+        assert_eq!(
+            FastLzCompressor::default().decompress(FASTLZ_VECTOR, &mut out),
+            0
+        );
+        assert_eq!(
+            ZlibCompressor::default().decompress(ZLIB_VECTOR, &mut out),
+            0
+        );
+        assert_eq!(
+            ZstdCompressor::default().decompress(ZSTD_VECTOR, &mut out),
+            0
+        );
+    }
+}
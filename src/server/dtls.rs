@@ -0,0 +1,651 @@
+use super::{ServerError, socket::TokioUdpSocket};
+use hmac::{Hmac, KeyInit, Mac};
+use log::debug;
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    error::ErrorStack,
+    ex_data::Index,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rand::rand_bytes,
+    ssl::{ErrorCode, Ssl, SslContext, SslMethod, SslOptions, SslRef, SslStream, SslVersion},
+    x509::{X509, X509NameBuilder},
+};
+use rusty_enet::{self as enet, Socket};
+use sha2::Sha256;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::{self, Read, Write},
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Fits the largest ENet datagram Godot sends (ENET_HOST_DEFAULT_MTU) with the DTLS overhead,
+// excluding the IP and UDP headers
+const DTLS_MTU: u32 = 1472;
+
+// Handshakes which did not complete in time are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Handshakes of clients which returned their cookie, so own their address
+const MAX_HANDSHAKES: usize = 64;
+// Handshakes waiting for their cookie, the oldest is dropped for a new one
+const MAX_COOKIE_WAITS: usize = 1024;
+
+// Established sessions which received nothing for this long are dropped,
+// well past the point ENet times out their peer
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
+// From the DTLS record header
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
+
+#[derive(Clone)]
+/// Certificate and private key of the DTLS server,
+/// mirrors `TLSOptions.server` given to `ENetConnection.dtls_server_setup`
+pub struct DtlsConfig {
+    context: SslContext,
+    // The client address of each session, which its cookie is made from
+    address_index: Index<Ssl, SocketAddr>,
+    // Set on a session once its client returned a valid cookie
+    verified_index: Index<Ssl, bool>,
+}
+
+impl DtlsConfig {
+    /// Load a PEM encoded certificate chain and private key
+    pub fn from_pem(certificate: &[u8], private_key: &[u8]) -> Result<Self, ServerError> {
+        let mut chain = X509::stack_from_pem(certificate)?.into_iter();
+        let certificate = chain.next().ok_or(ServerError::MissingCertificate)?;
+        let private_key = PKey::private_key_from_pem(private_key)?;
+
+        Self::new(&certificate, chain, &private_key)
+    }
+
+    /// Generate a self-signed certificate, for local tests
+    ///
+    /// Godot clients only accept it with `TLSOptions.client_unsafe`,
+    /// or with `TLSOptions.client` given the certificate.
+    pub fn self_signed(common_name: &str) -> Result<Self, ServerError> {
+        let (certificate, private_key) = self_signed_certificate(common_name)?;
+
+        Self::new(&certificate, std::iter::empty(), &private_key)
+    }
+
+    fn new(
+        certificate: &X509,
+        chain: impl Iterator<Item = X509>,
+        private_key: &PKey<Private>,
+    ) -> Result<Self, ServerError> {
+        let address_index = Ssl::new_ex_index::<SocketAddr>()?;
+        let verified_index = Ssl::new_ex_index::<bool>()?;
+
+        let mut secret = [0; 32];
+        rand_bytes(&mut secret)?;
+        let secret: Arc<[u8]> = Arc::new(secret);
+
+        let mut builder = SslContext::builder(SslMethod::dtls_server())?;
+        builder.set_min_proto_version(Some(SslVersion::DTLS1_2))?;
+        // Clients must prove they own their address before the server sends its certificate,
+        // so spoofed handshakes can not be amplified or hold handshake slots
+        builder.set_options(SslOptions::NO_QUERY_MTU | SslOptions::COOKIE_EXCHANGE);
+
+        let generate_secret = secret.clone();
+        builder.set_cookie_generate_cb(move |ssl, cookie_buffer| {
+            let cookie = cookie_for(&generate_secret, ssl, address_index)?;
+
+            cookie_buffer[..cookie.len()].copy_from_slice(&cookie);
+            Ok(cookie.len())
+        });
+
+        builder.set_cookie_verify_cb(move |ssl, cookie| {
+            let Ok(mac) = address_mac(&secret, ssl, address_index) else {
+                return false;
+            };
+
+            let verified = mac.verify_slice(cookie).is_ok();
+            if verified {
+                ssl.set_ex_data(verified_index, true);
+            }
+
+            verified
+        });
+
+        builder.set_certificate(certificate)?;
+        for certificate in chain {
+            builder.add_extra_chain_cert(certificate)?;
+        }
+        builder.set_private_key(private_key)?;
+        builder.check_private_key()?;
+
+        Ok(Self {
+            context: builder.build(),
+            address_index,
+            verified_index,
+        })
+    }
+}
+
+// An HMAC of the session's client address, which only a client receiving at the address can return
+fn cookie_for(
+    secret: &[u8],
+    ssl: &SslRef,
+    address_index: Index<Ssl, SocketAddr>,
+) -> Result<Vec<u8>, ErrorStack> {
+    Ok(address_mac(secret, ssl, address_index)?
+        .finalize()
+        .into_bytes()
+        .to_vec())
+}
+
+fn address_mac(
+    secret: &[u8],
+    ssl: &SslRef,
+    address_index: Index<Ssl, SocketAddr>,
+) -> Result<HmacSha256, ErrorStack> {
+    let address = ssl.ex_data(address_index).ok_or_else(ErrorStack::get)?;
+
+    let mut mac = HmacSha256::new_from_slice(secret).map_err(|_| ErrorStack::get())?;
+    mac.update(address.to_string().as_bytes());
+
+    Ok(mac)
+}
+
+impl fmt::Debug for DtlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DtlsConfig").finish_non_exhaustive()
+    }
+}
+
+/// Generate a self-signed P-256 certificate valid for a year
+pub fn self_signed_certificate(common_name: &str) -> Result<(X509, PKey<Private>), ServerError> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let private_key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(128, MsbOption::MAYBE_ZERO, false)?;
+    let serial = serial.to_asn1_integer()?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(365)?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&private_key)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.sign(&private_key, MessageDigest::sha256())?;
+
+    Ok((builder.build(), private_key))
+}
+
+#[derive(Debug)]
+/// The datagrams of a single client, read and written by its DTLS session
+struct Datagrams {
+    socket: TokioUdpSocket,
+    address: SocketAddr,
+    incoming: VecDeque<Vec<u8>>,
+}
+
+impl Read for Datagrams {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(datagram) = self.incoming.pop_front() else {
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+
+        let length = datagram.len().min(buf.len());
+        buf[..length].copy_from_slice(&datagram[..length]);
+        Ok(length)
+    }
+}
+
+impl Write for Datagrams {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A datagram which cannot be sent is lost, as it would be on the network
+        self.socket.send(self.address, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Session {
+    stream: SslStream<Datagrams>,
+    started: Instant,
+    last_received: Instant,
+}
+
+#[derive(Debug)]
+/// A [`Socket`](rusty_enet::Socket) encrypting every datagram with DTLS,
+/// like Godot's `ENetConnection.dtls_server_setup`
+///
+/// Each client address gets its own session,
+/// and ENet only sees the datagrams of established sessions.
+/// Clients must return a cookie before their handshake proceeds,
+/// and a new handshake from an address only replaces its session once it completes.
+pub struct DtlsSocket {
+    socket: TokioUdpSocket,
+    config: DtlsConfig,
+    sessions: HashMap<SocketAddr, Session>,
+    // Kept apart from the sessions, so a spoofed handshake can not tear down an established one
+    handshakes: HashMap<SocketAddr, Session>,
+    last_expiry: Instant,
+}
+
+impl DtlsSocket {
+    pub fn new(socket: TokioUdpSocket, config: DtlsConfig) -> Self {
+        Self {
+            socket,
+            config,
+            sessions: HashMap::new(),
+            handshakes: HashMap::new(),
+            last_expiry: Instant::now(),
+        }
+    }
+
+    /// Wait until a packet can be received
+    pub async fn readable(&self) -> io::Result<()> {
+        self.socket.readable().await
+    }
+
+    /// Poll whether a packet can be received
+    pub fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_readable(cx)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Number of clients with an established session
+    pub fn established(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Drop the handshakes which timed out and the sessions which went idle
+    fn expire(&mut self, now: Instant) {
+        if now.duration_since(self.last_expiry) < EXPIRY_INTERVAL {
+            return;
+        }
+
+        self.last_expiry = now;
+
+        self.handshakes
+            .retain(|_, handshake| now.duration_since(handshake.started) < HANDSHAKE_TIMEOUT);
+
+        self.sessions.retain(|address, session| {
+            let idle = now.duration_since(session.last_received) >= SESSION_IDLE_TIMEOUT;
+            if idle {
+                debug!("DTLS session with {} went idle", address);
+            }
+
+            !idle
+        });
+    }
+
+    fn is_verified(&self, handshake: &Session) -> bool {
+        handshake
+            .stream
+            .ssl()
+            .ex_data(self.config.verified_index)
+            .is_some_and(|verified| *verified)
+    }
+
+    fn start_handshake(&mut self, address: SocketAddr, now: Instant) -> Option<&mut Session> {
+        let verified = self
+            .handshakes
+            .values()
+            .filter(|handshake| self.is_verified(handshake))
+            .count();
+
+        if verified >= MAX_HANDSHAKES {
+            debug!(
+                "Too many DTLS handshakes, dropping datagram from {}",
+                address
+            );
+            return None;
+        }
+
+        // Spoofed clients never return their cookie, so they only push out each other
+        if self.handshakes.len() - verified >= MAX_COOKIE_WAITS
+            && let Some(oldest) = self
+                .handshakes
+                .iter()
+                .filter(|(_, handshake)| !self.is_verified(handshake))
+                .min_by_key(|(_, handshake)| handshake.started)
+                .map(|(address, _)| *address)
+        {
+            self.handshakes.remove(&oldest);
+        }
+
+        let datagrams = Datagrams {
+            socket: self.socket.clone(),
+            address,
+            incoming: VecDeque::new(),
+        };
+
+        let stream = Ssl::new(&self.config.context)
+            .and_then(|mut ssl| {
+                ssl.set_mtu(DTLS_MTU)?;
+                ssl.set_ex_data(self.config.address_index, address);
+                SslStream::new(ssl, datagrams)
+            })
+            .map_err(|e| debug!("Failed to start DTLS session with {}: {}", address, e))
+            .ok()?;
+
+        let handshake = Session {
+            stream,
+            started: now,
+            last_received: now,
+        };
+
+        Some(
+            self.handshakes
+                .entry(address)
+                .insert_entry(handshake)
+                .into_mut(),
+        )
+    }
+
+    /// Feed a handshake datagram to the handshake of the address,
+    /// returning the session once it is established
+    fn handshake(&mut self, address: SocketAddr, datagram: &[u8], now: Instant) -> Option<()> {
+        let handshake = match self.handshakes.get_mut(&address) {
+            Some(handshake) => handshake,
+            None => self.start_handshake(address, now)?,
+        };
+
+        handshake.last_received = now;
+        handshake
+            .stream
+            .get_mut()
+            .incoming
+            .push_back(datagram.to_vec());
+
+        match handshake.stream.accept() {
+            Ok(()) => {}
+            Err(e) if e.code() == ErrorCode::WANT_READ => return None,
+            Err(e) => {
+                debug!("DTLS handshake with {} failed: {}", address, e);
+                self.handshakes.remove(&address);
+                return None;
+            }
+        }
+
+        let session = self.handshakes.remove(&address)?;
+
+        // The handshake returned its cookie to get here, so the client owns the address
+        if self.sessions.insert(address, session).is_some() {
+            debug!("DTLS session with {} replaced by a new handshake", address);
+        } else {
+            debug!("DTLS session established with {}", address);
+        }
+
+        Some(())
+    }
+
+    /// Feed a datagram to the session of the address,
+    /// returning the length of the decrypted ENet datagram written back to the buffer
+    fn decrypt(&mut self, address: SocketAddr, buffer: &mut [u8], length: usize) -> Option<usize> {
+        let now = Instant::now();
+        self.expire(now);
+
+        let datagram = &buffer[..length];
+
+        // Application data belongs to the established session,
+        // while handshake records go to a handshake, which may be a client reconnecting
+        let to_handshake = !self.sessions.contains_key(&address)
+            || is_client_hello(datagram)
+            || (self.handshakes.contains_key(&address)
+                && datagram.first() != Some(&CONTENT_TYPE_APPLICATION_DATA));
+
+        if to_handshake {
+            // Records sent along with the client's last flight are read from the new session
+            self.handshake(address, datagram, now)?;
+        } else {
+            let session = self.sessions.get_mut(&address)?;
+
+            session.last_received = now;
+            session
+                .stream
+                .get_mut()
+                .incoming
+                .push_back(datagram.to_vec());
+        }
+
+        let session = self.sessions.get_mut(&address)?;
+
+        match session.stream.ssl_read(buffer) {
+            Ok(length) => Some(length),
+            Err(e) if e.code() == ErrorCode::WANT_READ => None,
+            Err(e) => {
+                if e.code() != ErrorCode::ZERO_RETURN {
+                    debug!("DTLS session with {} failed: {}", address, e);
+                }
+                self.sessions.remove(&address);
+                None
+            }
+        }
+    }
+}
+
+impl Socket for DtlsSocket {
+    type Address = SocketAddr;
+    type Error = io::Error;
+
+    fn init(&mut self, socket_options: enet::SocketOptions) -> Result<(), io::Error> {
+        self.socket.init(socket_options)
+    }
+
+    fn send(&mut self, address: SocketAddr, buffer: &[u8]) -> Result<usize, io::Error> {
+        let Some(session) = self.sessions.get_mut(&address) else {
+            debug!("No DTLS session with {}, dropping datagram", address);
+            return Ok(buffer.len());
+        };
+
+        if let Err(e) = session.stream.ssl_write(buffer) {
+            debug!("DTLS session with {} failed: {}", address, e);
+            self.sessions.remove(&address);
+        }
+
+        Ok(buffer.len())
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; enet::MTU_MAX],
+    ) -> Result<Option<(SocketAddr, enet::PacketReceived)>, io::Error> {
+        loop {
+            let Some((address, received)) = self.socket.receive(buffer)? else {
+                return Ok(None);
+            };
+
+            let enet::PacketReceived::Complete(length) = received else {
+                continue;
+            };
+
+            if let Some(length) = self.decrypt(address, buffer, length) {
+                return Ok(Some((address, enet::PacketReceived::Complete(length))));
+            }
+        }
+    }
+}
+
+// A DTLS handshake record of epoch 0 holding a ClientHello
+fn is_client_hello(datagram: &[u8]) -> bool {
+    const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+
+    datagram.len() > 13
+        && datagram[0] == CONTENT_TYPE_HANDSHAKE
+        && datagram[3..5] == [0, 0]
+        && datagram[13] == HANDSHAKE_CLIENT_HELLO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_pem_certificate() {
+        let (certificate, private_key) = self_signed_certificate("localhost").unwrap();
+
+        let config = DtlsConfig::from_pem(
+            &certificate.to_pem().unwrap(),
+            &private_key.private_key_to_pem_pkcs8().unwrap(),
+        );
+
+        assert!(config.is_ok());
+    }
+
+    #[test]
+    fn rejects_mismatched_key() {
+        let (certificate, _) = self_signed_certificate("localhost").unwrap();
+        let (_, private_key) = self_signed_certificate("localhost").unwrap();
+
+        let config = DtlsConfig::from_pem(
+            &certificate.to_pem().unwrap(),
+            &private_key.private_key_to_pem_pkcs8().unwrap(),
+        );
+
+        assert!(matches!(config, Err(ServerError::Tls(_))));
+    }
+
+    #[test]
+    fn rejects_empty_pem() {
+        let (_, private_key) = self_signed_certificate("localhost").unwrap();
+
+        let config = DtlsConfig::from_pem(b"", &private_key.private_key_to_pem_pkcs8().unwrap());
+
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn detects_client_hello() {
+        // Record header of epoch 0, then the handshake header
+        let mut datagram = [0u8; 25];
+        datagram[..3].copy_from_slice(&[22, 0xfe, 0xfd]);
+        datagram[13] = 1;
+        assert!(is_client_hello(&datagram));
+
+        // Same record in epoch 1, after the handshake
+        datagram[4] = 1;
+        assert!(!is_client_hello(&datagram));
+
+        // Application data
+        datagram[..5].copy_from_slice(&[23, 0xfe, 0xfd, 0, 0]);
+        assert!(!is_client_hello(&datagram));
+    }
+
+    // Datagrams of the client, read and written by its DTLS session
+    struct ClientDatagrams(std::net::UdpSocket);
+
+    impl Read for ClientDatagrams {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.recv(buf)
+        }
+    }
+
+    impl Write for ClientDatagrams {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.send(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Poll the server until it yields a datagram or the client has what it waited for,
+    // sleeping on the runtime so it notices the server socket is readable
+    async fn pump<T>(
+        server: &mut DtlsSocket,
+        mut client: impl FnMut() -> Option<T>,
+    ) -> (Option<T>, Option<Vec<u8>>) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut buffer = [0; enet::MTU_MAX];
+
+        while Instant::now() < deadline {
+            if let Some((_, enet::PacketReceived::Complete(length))) =
+                server.receive(&mut buffer).unwrap()
+            {
+                return (None, Some(buffer[..length].to_vec()));
+            }
+
+            if let Some(result) = client() {
+                return (Some(result), None);
+            }
+
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        panic!("DTLS loopback timed out");
+    }
+
+    #[tokio::test]
+    async fn loopback_handshake_and_datagrams() {
+        let server_socket = TokioUdpSocket::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let server_address = server_socket.local_addr().unwrap();
+        let mut server =
+            DtlsSocket::new(server_socket, DtlsConfig::self_signed("localhost").unwrap());
+
+        let client_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        client_socket.connect(server_address).unwrap();
+        client_socket.set_nonblocking(true).unwrap();
+        let client_address = client_socket.local_addr().unwrap();
+
+        let mut context = SslContext::builder(SslMethod::dtls_client()).unwrap();
+        context.set_verify(openssl::ssl::SslVerifyMode::NONE);
+        context.set_options(SslOptions::NO_QUERY_MTU);
+        let mut ssl = Ssl::new(&context.build()).unwrap();
+        ssl.set_mtu(DTLS_MTU).unwrap();
+        let mut client = SslStream::new(ssl, ClientDatagrams(client_socket)).unwrap();
+
+        let (connected, _) = pump(&mut server, || match client.connect() {
+            Ok(()) => Some(()),
+            Err(e) if e.code() == ErrorCode::WANT_READ => None,
+            Err(e) => panic!("DTLS handshake failed: {}", e),
+        })
+        .await;
+        assert!(connected.is_some());
+
+        client.ssl_write(b"hello").unwrap();
+        let (_, received) = pump(&mut server, || None::<()>).await;
+        assert_eq!(received.as_deref(), Some(&b"hello"[..]));
+        assert_eq!(server.established(), 1);
+        // The client returned its cookie before the handshake went on
+        assert!(server.is_verified(&server.sessions[&client_address]));
+
+        server.send(client_address, b"world").unwrap();
+        let mut buffer = [0; 64];
+        let (read, _) = pump(&mut server, || match client.ssl_read(&mut buffer) {
+            Ok(length) => Some(length),
+            Err(e) if e.code() == ErrorCode::WANT_READ => None,
+            Err(e) => panic!("DTLS read failed: {}", e),
+        })
+        .await;
+        assert_eq!(&buffer[..read.unwrap()], b"world");
+
+        // A ClientHello spoofing the client's address does not end its session
+        let mut hello = [0u8; 64];
+        hello[..3].copy_from_slice(&[22, 0xfe, 0xfd]);
+        hello[13] = 1;
+        assert!(server.decrypt(client_address, &mut hello, 64).is_none());
+        assert_eq!(server.established(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+use super::socket::ServerSocket;
+use crate::ENetPeerID;
+use openssl::error::ErrorStack;
+use rusty_enet as enet;
+use std::{
+    error::Error as StdError,
+    fmt::Display,
+    io,
+    net::{AddrParseError, SocketAddr},
+};
+
+#[derive(Debug)]
+/// Error from the [`Server`](super::Server) or its builder
+pub enum ServerError {
+    /// The server has not been opened, or has been shut down.
+    NotOpen,
+    /// The socket could not be bound to the address.
+    Bind {
+        address: SocketAddr,
+        source: io::Error,
+    },
+    /// The ENet host could not be created on the bound socket.
+    HostCreation {
+        address: SocketAddr,
+        source: enet::error::HostNewError<ServerSocket>,
+    },
+    /// The socket failed while servicing the host.
+    Service(io::Error),
+    /// No peer has the id.
+    PeerNotFound(ENetPeerID),
+    /// The peer exists but is not connected.
+    PeerNotConnected(ENetPeerID),
+    /// ENet refused to queue the packet for the peer.
+    Send {
+        peer_id: ENetPeerID,
+        source: enet::error::PeerSendError,
+    },
+    /// A setting of the builder is outside of its range, `max` is `None` without an upper bound.
+    OutOfRange {
+        setting: &'static str,
+        min: usize,
+        max: Option<usize>,
+        value: usize,
+    },
+    /// The builder was given the same address more than once.
+    DuplicateAddress(SocketAddr),
+    /// The address given to the builder could not be parsed.
+    InvalidAddress {
+        address: String,
+        source: AddrParseError,
+    },
+    /// The DTLS certificate PEM does not contain a certificate.
+    MissingCertificate,
+    /// The DTLS certificate or key could not be loaded.
+    Tls(ErrorStack),
+}
+
+impl StdError for ServerError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ServerError::Bind { source, .. } => Some(source),
+            ServerError::HostCreation { source, .. } => Some(source),
+            ServerError::Service(source) => Some(source),
+            ServerError::Send { source, .. } => Some(source),
+            ServerError::InvalidAddress { source, .. } => Some(source),
+            ServerError::Tls(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerError::NotOpen => write!(f, "Server is not open"),
+            ServerError::Bind { address, source } => {
+                write!(f, "Failed to bind to address {}: {}", address, source)
+            }
+            ServerError::HostCreation { address, source } => write!(
+                f,
+                "Failed to create ENet host with address {}: {}",
+                address, source
+            ),
+            ServerError::Service(source) => write!(f, "Failed to service ENet host: {}", source),
+            ServerError::PeerNotFound(peer_id) => {
+                write!(f, "Failed to find peer with id {:?}", peer_id)
+            }
+            ServerError::PeerNotConnected(peer_id) => {
+                write!(f, "Peer with id {:?} is not connected", peer_id)
+            }
+            ServerError::Send { peer_id, source } => {
+                write!(f, "Failed to send packet to peer {:?}: {}", peer_id, source)
+            }
+            ServerError::OutOfRange {
+                setting,
+                min,
+                max: Some(max),
+                value,
+            } => write!(
+                f,
+                "{} must be between {} and {}, got {}",
+                setting, min, max, value
+            ),
+            ServerError::OutOfRange {
+                setting,
+                min,
+                max: None,
+                value,
+            } => write!(f, "{} must be at least {}, got {}", setting, min, value),
+            ServerError::DuplicateAddress(address) => {
+                write!(f, "Address {} is bound more than once", address)
+            }
+            ServerError::InvalidAddress { address, source } => {
+                write!(f, "Failed to parse socket address {}: {}", address, source)
+            }
+            ServerError::MissingCertificate => write!(f, "DTLS certificate PEM has no certificate"),
+            ServerError::Tls(source) => write!(f, "Failed to set up DTLS: {}", source),
+        }
+    }
+}
+
+impl From<ErrorStack> for ServerError {
+    fn from(source: ErrorStack) -> Self {
+        ServerError::Tls(source)
+    }
+}
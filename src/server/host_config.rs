@@ -0,0 +1,124 @@
+use super::ServerError;
+use crate::packet::channel::SYSCH_MAX;
+use rusty_enet::{self as enet, consts};
+use std::sync::Arc;
+
+/// Creates the compressor of the host every time the server is opened
+pub type CompressorFactory = Arc<dyn Fn() -> Box<dyn enet::Compressor> + Send + Sync>;
+
+/// Checksum of the buffers of an ENet packet
+pub type ChecksumFunction = Arc<dyn Fn(&[&[u8]]) -> u32 + Send + Sync>;
+
+#[derive(Clone)]
+/// Settings of the ENet host opened by the [`Server`](super::Server)
+///
+/// Defaults to the host created by Godot's `ENetMultiplayerPeer.create_server`
+/// with its default arguments.
+pub struct HostConfig {
+    pub peer_limit: usize,
+    pub channel_limit: usize,
+    /// Downstream bandwidth limit in bytes/second, or [`None`] for no limit.
+    pub incoming_bandwidth_limit: Option<u32>,
+    /// Upstream bandwidth limit in bytes/second, or [`None`] for no limit.
+    pub outgoing_bandwidth_limit: Option<u32>,
+    /// Compressor of the host, or [`None`] for no compression.
+    pub compressor: Option<CompressorFactory>,
+    /// Checksum of the host, or [`None`] for no checksum.
+    pub checksum: Option<ChecksumFunction>,
+    /// Random seed of the host, or [`None`] for a random seed.
+    pub seed: Option<u32>,
+}
+
+impl HostConfig {
+    /// Mirror `ENetMultiplayerPeer.create_server(port, max_clients, max_channels, in_bandwidth, out_bandwidth)`
+    ///
+    /// As in Godot, 0 channels allows the ENet maximum,
+    /// otherwise Godot's system channels are added to the channels,
+    /// and 0 bandwidth means no limit.
+    pub fn godot_server(
+        max_clients: usize,
+        max_channels: usize,
+        in_bandwidth: u32,
+        out_bandwidth: u32,
+    ) -> Self {
+        let channel_limit = if max_channels == 0 {
+            consts::PROTOCOL_MAXIMUM_CHANNEL_COUNT as usize
+        } else {
+            max_channels + SYSCH_MAX as usize
+        };
+
+        Self {
+            peer_limit: max_clients,
+            channel_limit,
+            incoming_bandwidth_limit: (in_bandwidth > 0).then_some(in_bandwidth),
+            outgoing_bandwidth_limit: (out_bandwidth > 0).then_some(out_bandwidth),
+            compressor: None,
+            checksum: None,
+            seed: None,
+        }
+    }
+
+    /// Check the settings would be accepted by ENet
+    pub fn validate(&self) -> Result<(), ServerError> {
+        if self.peer_limit == 0 || self.peer_limit > consts::PROTOCOL_MAXIMUM_PEER_ID as usize {
+            return Err(ServerError::OutOfRange {
+                setting: "Peer limit",
+                min: 1,
+                max: Some(consts::PROTOCOL_MAXIMUM_PEER_ID as usize),
+                value: self.peer_limit,
+            });
+        }
+
+        if self.channel_limit < consts::PROTOCOL_MINIMUM_CHANNEL_COUNT as usize
+            || self.channel_limit > consts::PROTOCOL_MAXIMUM_CHANNEL_COUNT as usize
+        {
+            return Err(ServerError::OutOfRange {
+                setting: "Channel limit",
+                min: consts::PROTOCOL_MINIMUM_CHANNEL_COUNT as usize,
+                max: Some(consts::PROTOCOL_MAXIMUM_CHANNEL_COUNT as usize),
+                value: self.channel_limit,
+            });
+        }
+
+        // Use None for no limit
+        for (setting, limit) in [
+            ("Incoming bandwidth limit", self.incoming_bandwidth_limit),
+            ("Outgoing bandwidth limit", self.outgoing_bandwidth_limit),
+        ] {
+            if limit == Some(0) {
+                return Err(ServerError::OutOfRange {
+                    setting,
+                    min: 1,
+                    max: Some(u32::MAX as usize),
+                    value: 0,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the ENet settings, creating a new compressor
+    pub fn settings(&self) -> enet::HostSettings {
+        let checksum = self.checksum.clone();
+
+        enet::HostSettings {
+            peer_limit: self.peer_limit,
+            channel_limit: self.channel_limit,
+            incoming_bandwidth_limit: self.incoming_bandwidth_limit,
+            outgoing_bandwidth_limit: self.outgoing_bandwidth_limit,
+            compressor: self.compressor.as_ref().map(|compressor| compressor()),
+            checksum: checksum.map(|checksum| {
+                Box::new(move |buffers: &[&[u8]]| checksum(buffers)) as Box<dyn Fn(&[&[u8]]) -> u32>
+            }),
+            seed: self.seed,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self::godot_server(32, 0, 0, 0)
+    }
+}
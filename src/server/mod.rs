@@ -1,25 +1,142 @@
 pub mod builder;
+pub mod checksum;
+pub mod compression;
+pub mod dtls;
+mod error;
+mod host_config;
+mod sharded;
+pub mod socket;
+
+pub use error::*;
+pub use host_config::*;
+pub use sharded::*;
 
 use crate::{
-    DataPile, ENetPeerID, Layer,
+    DataPile, ENetPeerID, GDPeerID, Layer,
     event::{Event, EventType},
-    packet::outgoing::OutgoingPacket,
+    packet::{
+        channel::{GodotChannel, TransferMode},
+        outgoing::{ForwardedBroadcast, OutgoingBroadcast, OutgoingCommand, OutgoingPacket},
+    },
 };
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use dtls::{DtlsConfig, DtlsSocket};
 use log::{debug, error, info, warn};
 use rusty_enet as enet;
-use std::net::{SocketAddr, UdpSocket};
-use std::sync::{Arc, mpsc};
+use socket::{BindOptions, ServerSocket, TokioUdpSocket};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
 use std::usize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc};
+use tokio::task::JoinSet;
+
+// How long to wait between services while draining peers on shutdown
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// How long to wait for the layers to process the forced disconnects on shutdown
+const SHUTDOWN_TASK_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Shortest wait for the ENet timers, which count in milliseconds
+const MIN_TIMER_INTERVAL: Duration = Duration::from_millis(1);
+
+type PeerQueue = mpsc::UnboundedSender<(Event, OwnedSemaphorePermit)>;
+type PeerQueueReceiver = mpsc::UnboundedReceiver<(Event, OwnedSemaphorePermit)>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// How events are processed through the layers
+pub enum DispatchMode {
+    /// Events from the same peer are processed one at a time, in the order they were received,
+    /// while events from different peers are processed concurrently.
+    #[default]
+    Ordered,
+    /// Every event is processed in its own task,
+    /// so events from the same peer may be processed out of order.
+    Concurrent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Limits on the events handled by a single [`Server::service`]
+pub struct ServiceBudget {
+    pub max_events: usize,
+    pub max_duration: Duration,
+}
+
+impl Default for ServiceBudget {
+    fn default() -> Self {
+        Self {
+            max_events: 256,
+            max_duration: Duration::from_millis(5),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+/// What a single [`Server::service`] did
+pub struct ServiceStats {
+    pub events_processed: usize,
+    pub packets_sent: usize,
+    /// Packets which could not be sent, see [`SendFailure`].
+    pub packets_dropped: usize,
+    /// Whether the budget ran out, so more events may be ready.
+    pub work_remaining: bool,
+    /// Events waiting for the layers to finish others, see [`ServerBuilder::max_in_flight`](builder::ServerBuilder::max_in_flight).
+    pub events_pending: usize,
+}
+
+#[derive(Debug)]
+/// An outgoing packet which could not be sent,
+/// such as one sent to a peer which disconnected before it was serviced
+pub struct SendFailure {
+    pub peer_id: ENetPeerID,
+    pub channel_id: u8,
+    pub packet: enet::Packet,
+    pub error: ServerError,
+}
+
+/// Called with every [`SendFailure`], from the task servicing the server
+pub type SendFailureReporter = Arc<dyn Fn(&SendFailure) + Send + Sync>;
 
 pub struct Server {
-    host: Option<enet::Host<UdpSocket>>,
+    hosts: Vec<enet::Host<ServerSocket>>,
+    // Host serviced first by the next event, so a busy host can not starve the others
+    next_host: usize,
+
+    addresses: Vec<SocketAddr>,
+    bind_options: BindOptions,
 
-    address: SocketAddr,
+    shard: usize,
+    // Outgoing commands of every shard, empty when the server is not sharded
+    shard_routes: Vec<mpsc::UnboundedSender<OutgoingCommand>>,
+    host_config: HostConfig,
+    dtls: Option<DtlsConfig>,
 
     layers: Arc<Vec<Arc<dyn Layer>>>,
 
-    tx_outgoing: mpsc::Sender<OutgoingPacket>,
-    rx_outgoing: mpsc::Receiver<OutgoingPacket>,
+    tx_outgoing: mpsc::UnboundedSender<OutgoingCommand>,
+    rx_outgoing: mpsc::UnboundedReceiver<OutgoingCommand>,
+
+    gd_peers: HashMap<ENetPeerID, GDPeerID>,
+    tasks: JoinSet<()>,
+
+    service_budget: ServiceBudget,
+
+    dispatch_mode: DispatchMode,
+    // Removed by the queue itself once the peer's disconnect has been processed
+    peer_queues: Arc<DashMap<ENetPeerID, PeerQueue>>,
+    in_flight: Arc<Semaphore>,
+    // Events which did not fit in flight, in the order they happened
+    pending: VecDeque<Event>,
+    max_in_flight: usize,
+
+    send_failure_reporter: Option<SendFailureReporter>,
+    dropped_sends: u64,
+
+    shutdown_reason: Option<u32>,
 }
 
 impl Server {
@@ -28,178 +145,694 @@ impl Server {
         builder::ServerBuilder::default()
     }
 
-    pub fn open(&mut self) -> Result<(), String> {
-        self.open_custom(enet::HostSettings {
-            peer_limit: 32,
-            channel_limit: 2,
-            compressor: None,
-            checksum: None,
-            ..Default::default()
-        })?;
+    /// Start the server with the [`HostConfig`] of the builder
+    pub fn open(&mut self) -> Result<(), ServerError> {
+        let host_config = self.host_config.clone();
+
+        self.open_custom(|| host_config.settings())?;
 
         Ok(())
     }
 
-    /// Start the server
-    pub fn open_limits(&mut self, peer_limit: usize, channel_limit: usize) -> Result<(), String> {
-        self.open_custom(enet::HostSettings {
+    /// Start the server, overriding the limits of the [`HostConfig`]
+    pub fn open_limits(
+        &mut self,
+        peer_limit: usize,
+        channel_limit: usize,
+    ) -> Result<(), ServerError> {
+        let host_config = self.host_config.clone();
+
+        self.open_custom(|| enet::HostSettings {
             peer_limit: peer_limit,
             channel_limit: channel_limit,
-            compressor: None,
-            checksum: None,
-            ..Default::default()
+            ..host_config.settings()
         })?;
 
         Ok(())
     }
 
-    /// Start the server
-    pub fn open_custom(&mut self, enet_settings: enet::HostSettings) -> Result<(), String> {
-        // Open Socket and bind to address
-        debug!("Binding to address: {}", self.address);
-        let socket = UdpSocket::bind(self.address)
-            .map_err(|e| format!("Failed to bind to address: {}", e))?;
+    /// Start the server, with a host on every address
+    ///
+    /// The settings are created once for each host.
+    /// If any address fails to bind, none of the hosts are kept.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn open_custom(
+        &mut self,
+        enet_settings: impl Fn() -> enet::HostSettings,
+    ) -> Result<(), ServerError> {
+        let mut hosts = Vec::with_capacity(self.addresses.len());
 
-        if enet_settings.compressor.is_some() {
-            warn!(
-                "Custom compressor set. Ensure compatibility with Godot's compression algorithm."
-            );
-        }
+        for address in self.addresses.iter().copied() {
+            // Open Socket and bind to address
+            debug!("Binding to address: {}", address);
+            let socket = TokioUdpSocket::bind_with(address, self.bind_options)
+                .map_err(|e| ServerError::Bind { address, source: e })?;
 
-        if enet_settings.checksum.is_some() {
-            warn!(
-                "Custom checksum function set. Ensure compatibility with Godot's checksum algorithm."
-            );
+            let socket = match &self.dtls {
+                Some(dtls) => ServerSocket::Dtls(DtlsSocket::new(socket, dtls.clone())),
+                None => ServerSocket::Udp(socket),
+            };
+
+            // Create ENet Host
+            let host = enet::Host::new(socket, enet_settings())
+                .map_err(|e| ServerError::HostCreation { address, source: e })?;
+
+            hosts.push(host);
         }
 
-        // Create ENet Host
-        self.host = Some(enet::Host::new(socket, enet_settings).map_err(|e| {
-            format!(
-                "Failed to create ENet host with address {}: {}",
-                self.address, e
-            )
-        })?);
+        self.hosts = hosts;
+        self.next_host = 0;
 
         Ok(())
     }
 
     /// Check if the server is currently open
     pub fn is_open(&self) -> bool {
-        self.host.is_some()
+        !self.hosts.is_empty()
+    }
+
+    /// Addresses the hosts are bound to, in the order of the builder
+    pub fn local_addrs(&self) -> Result<Vec<SocketAddr>, ServerError> {
+        self.get_hosts()?
+            .iter()
+            .map(|host| host.socket().local_addr().map_err(ServerError::Service))
+            .collect()
     }
 
     /// Checks for events on the host and shuttles packets between the host and its peers.
     ///
-    /// Returns whether an event was processed.
+    /// Handles every ready event within the [`ServiceBudget`],
+    /// then sends everything the layers have queued.
+    /// Packets which can not be sent are reported as [`SendFailure`]s
+    /// without stopping the rest of the queue.
     ///
     /// Should be called fairly regularly for adequate performance.
-    pub async fn service(&mut self) -> Result<bool, String> {
-        let host = self.get_mut_host()?;
+    pub async fn service(&mut self) -> Result<ServiceStats, ServerError> {
+        let budget = self.service_budget;
+        let deadline = Instant::now() + budget.max_duration;
 
-        if let Some(event) = host.service().unwrap() {
-            let enet_peer_id: ENetPeerID;
-            let godot_enet_event_data;
+        let mut stats = ServiceStats::default();
+        let dropped_before = self.dropped_sends;
+
+        loop {
+            // The hosts are still serviced while events wait, so peers are acknowledged and pinged,
+            // and their new events wait behind those already waiting
+            self.start_pending();
+
+            if stats.events_processed >= budget.max_events || Instant::now() >= deadline {
+                stats.work_remaining = true;
+                break;
+            }
+
+            if !self.service_event().await? {
+                break;
+            }
+
+            stats.events_processed += 1;
+        }
+
+        self.reap_tasks();
+
+        while let Ok(outgoing) = self.rx_outgoing.try_recv() {
+            match self.apply_outgoing(outgoing).await {
+                Ok(packets_sent) => stats.packets_sent += packets_sent,
+                Err(e) => warn!("Failed to apply outgoing command: {}", e),
+            }
+        }
+
+        stats.packets_dropped = (self.dropped_sends - dropped_before) as usize;
+        stats.events_pending = self.pending.len();
+
+        // Send now rather than on the next service
+        for host in self.get_mut_hosts()? {
+            host.flush();
+        }
+
+        return Ok(stats);
+    }
+
+    /// Handle a single event from the hosts, returning whether there was one
+    async fn service_event(&mut self) -> Result<bool, ServerError> {
+        let shutdown_reason = self.shutdown_reason;
+        let host_count = self.get_hosts()?.len();
+
+        for offset in 0..host_count {
+            let host_index = (self.next_host + offset) % host_count;
+
+            let Some(event) = self.hosts[host_index]
+                .service()
+                .map_err(ServerError::Service)?
+            else {
+                continue;
+            };
+
+            self.next_host = (host_index + 1) % host_count;
+
+            let mut godot_enet_event = None;
 
             // Build PeerID and GodotENetEventType
             match event {
                 enet::Event::Connect { peer, data } => {
-                    info!("Peer {:?} connected with {:?}", peer.id().0, data);
+                    let enet_peer_id = ENetPeerID::in_shard(self.shard, host_index, peer.id());
+
+                    if let Some(reason) = shutdown_reason {
+                        info!("Refusing peer {:?} while shutting down", enet_peer_id.0);
+
+                        peer.disconnect_now(reason);
+                    } else {
+                        info!("Peer {:?} connected with {:?}", enet_peer_id.0, data);
 
-                    enet_peer_id = peer.id().into();
-                    godot_enet_event_data = EventType::Connect {
-                        godot_peer: super::GDPeerID::from(data),
-                    };
+                        let godot_peer = GDPeerID::from(data);
+                        self.gd_peers.insert(enet_peer_id, godot_peer);
+
+                        godot_enet_event = Some((enet_peer_id, EventType::Connect { godot_peer }));
+                    }
                 }
                 enet::Event::Disconnect { peer, data } => {
-                    info!("Peer {:?} disconnected with {:?}", peer.id().0, data);
+                    let enet_peer_id = ENetPeerID::in_shard(self.shard, host_index, peer.id());
 
-                    enet_peer_id = peer.id().into();
-                    godot_enet_event_data = EventType::Disconnect {
-                        godot_peer: super::GDPeerID::from(data),
-                    };
+                    info!("Peer {:?} disconnected with {:?}", enet_peer_id.0, data);
+
+                    // Peers refused during shutdown never reached the layers
+                    if let Some(godot_peer) = self.gd_peers.remove(&enet_peer_id) {
+                        godot_enet_event =
+                            Some((enet_peer_id, EventType::Disconnect { godot_peer }));
+                    }
                 }
                 enet::Event::Receive {
                     peer,
                     channel_id,
                     packet,
                 } => {
-                    enet_peer_id = peer.id().into();
-                    godot_enet_event_data = EventType::Receive {
-                        channel_id,
-                        raw_packet: packet,
-                    };
+                    godot_enet_event = Some((
+                        ENetPeerID::in_shard(self.shard, host_index, peer.id()),
+                        EventType::Receive {
+                            channel: GodotChannel::from_enet_channel(channel_id),
+                            transfer_mode: TransferMode::received(channel_id),
+                            raw_packet: packet,
+                        },
+                    ));
                 }
             }
 
-            let godot_enet_event = Event {
-                peer_id: enet_peer_id,
+            if let Some((enet_peer_id, godot_enet_event_data)) = godot_enet_event {
+                self.dispatch(enet_peer_id, godot_enet_event_data);
+            }
+
+            return Ok(true);
+        }
 
-                event: godot_enet_event_data,
+        Ok(false)
+    }
+
+    /// Service the server until it is closed
+    ///
+    /// See [`Server::run_until`].
+    pub async fn run(&mut self) -> Result<(), ServerError> {
+        self.run_until(std::future::pending::<()>()).await
+    }
+
+    /// Service the server until the stop future completes or the server is closed
+    ///
+    /// Instead of polling, waits for incoming packets, commands from the layers,
+    /// or the ENet timers of connected peers, so an idle server uses no CPU.
+    /// Errors while servicing are logged rather than stopping the server.
+    pub async fn run_until<F: Future>(&mut self, stop: F) -> Result<(), ServerError> {
+        tokio::pin!(stop);
 
-                data_pile: DataPile::default(),
+        // When the packets sent and received by each service are next due for the ENet timers
+        let mut timers = BTreeSet::new();
 
-                tx_outgoing: self.tx_outgoing.clone(),
+        while self.is_open() {
+            match self.service().await {
+                Ok(stats) if stats.work_remaining => {
+                    tokio::task::yield_now().await;
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Error servicing host: {}", e),
+            }
+
+            // Peers need their retransmissions, pings and timeouts handled
+            match self.timer_interval()? {
+                Some(interval) => {
+                    let now = Instant::now();
+
+                    timers = timers.split_off(&now);
+                    timers.insert(now + interval);
+                }
+                None => timers.clear(),
+            }
+
+            let timer = timers.first().copied();
+
+            let saturated = !self.pending.is_empty();
+
+            let command = tokio::select! {
+                _ = &mut stop => return Ok(()),
+                // Wait for the layers to finish an event to start the waiting ones
+                _ = self.in_flight.acquire(), if saturated => None,
+                result = readable(&self.hosts) => {
+                    result.map_err(ServerError::Service)?;
+                    None
+                }
+                command = self.rx_outgoing.recv() => command,
+                _ = sleep_until(timer) => None,
             };
 
-            self.process_event(godot_enet_event).await;
+            if let Some(command) = command
+                && let Err(e) = self.apply_outgoing(command).await
+            {
+                error!("Error applying outgoing command: {}", e);
+            }
         }
 
+        Ok(())
+    }
+
+    /// How long after a service the ENet timers of its packets may be due,
+    /// or `None` without any peers
+    ///
+    /// ENet does not expose when its next retransmission or ping is due,
+    /// so it is taken from the peers: a reliable packet is retransmitted
+    /// once its round trip timeout, the round trip time plus four times its variance, has passed,
+    /// a ping is sent once nothing was received for the ping interval,
+    /// and timeouts are only checked along with retransmissions.
+    fn timer_interval(&mut self) -> Result<Option<Duration>, ServerError> {
+        let interval = self
+            .get_mut_hosts()?
+            .iter_mut()
+            .flat_map(|host| {
+                host.peers()
+                    .filter(|peer| peer.state() != enet::PeerState::Disconnected)
+                    .map(|peer| {
+                        let round_trip_timeout =
+                            peer.round_trip_time() + 4 * peer.round_trip_time_variance();
+
+                        round_trip_timeout.min(peer.ping_interval())
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .min();
+
+        Ok(interval.map(|interval| interval.max(MIN_TIMER_INTERVAL)))
+    }
+
+    /// Gracefully close the server
+    ///
+    /// Stops accepting connections and disconnects every peer with the reason,
+    /// then keeps servicing the host until every peer has disconnected
+    /// and every layer task has finished, or the grace period has passed.
+    ///
+    /// Peers still connected after the grace period are disconnected immediately,
+    /// with their disconnects still delivered through the layers.
+    pub async fn shutdown(
+        &mut self,
+        reason: u32,
+        grace_period: Duration,
+    ) -> Result<(), ServerError> {
+        let deadline = Instant::now() + grace_period;
+
+        info!("Shutting down server with reason {:?}", reason);
+
+        self.shutdown_reason = Some(reason);
+
+        // Send whatever the layers queued before the disconnects
         while let Ok(outgoing) = self.rx_outgoing.try_recv() {
-            self.send_outgoing(outgoing).await?;
+            if let Err(e) = self.apply_outgoing(outgoing).await {
+                warn!("Failed to apply outgoing command during shutdown: {}", e);
+            }
+        }
+
+        for host in self.get_mut_hosts()? {
+            for peer in host.peers_mut() {
+                if peer.state() != enet::PeerState::Disconnected {
+                    peer.disconnect(reason);
+                }
+            }
+        }
+
+        while Instant::now() < deadline {
+            let processed = match self.service().await {
+                Ok(stats) => stats.events_processed > 0 || stats.work_remaining,
+                Err(e) => {
+                    warn!("Error servicing host during shutdown: {}", e);
+                    false
+                }
+            };
+
+            let drained = self.get_mut_hosts()?.iter_mut().all(|host| {
+                host.peers()
+                    .all(|peer| peer.state() == enet::PeerState::Disconnected)
+            });
+
+            if drained
+                && self.gd_peers.is_empty()
+                && self.pending.is_empty()
+                && self.events_in_flight() == 0
+            {
+                break;
+            }
+
+            if !processed {
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+            }
+        }
+
+        let shard = self.shard;
+
+        for (host_index, host) in self.get_mut_hosts()?.iter_mut().enumerate() {
+            for peer in host.peers_mut() {
+                if peer.state() != enet::PeerState::Disconnected {
+                    debug!(
+                        "Forcing disconnect of peer {:?}",
+                        ENetPeerID::in_shard(shard, host_index, peer.id()).0
+                    );
+
+                    peer.disconnect_now(reason);
+                }
+            }
+        }
+
+        // The layers have not seen these disconnects yet
+        let remaining: Vec<(ENetPeerID, GDPeerID)> = self.gd_peers.drain().collect();
+
+        for (enet_peer_id, godot_peer) in remaining {
+            self.dispatch(enet_peer_id, EventType::Disconnect { godot_peer });
+        }
+
+        let tasks_finished = tokio::time::timeout(SHUTDOWN_TASK_TIMEOUT, async {
+            while !self.start_pending() {
+                let _ = self.in_flight.acquire().await;
+            }
+
+            // Let the peer queues finish once their events are processed
+            self.peer_queues.clear();
+
+            while self.tasks.join_next().await.is_some() {}
+        })
+        .await;
+
+        if tasks_finished.is_err() {
+            warn!(
+                "Aborting {:?} layer tasks and {:?} pending events still left after shutdown",
+                self.tasks.len(),
+                self.pending.len()
+            );
+
+            self.tasks.abort_all();
+            self.pending.clear();
+            self.peer_queues.clear();
         }
 
-        return Ok(true);
+        // Peers are gone, so nothing left can be sent
+        while self.rx_outgoing.try_recv().is_ok() {}
+
+        self.hosts.clear();
+        self.shutdown_reason = None;
+
+        info!("Server shut down");
+
+        Ok(())
     }
 
-    /// Process and event through layers by spawning an async task
-    async fn process_event(&mut self, mut event: Event) {
-        let layers = Arc::clone(&self.layers);
-        let mut i: usize = 0;
+    /// Build an event and process it through the layers
+    fn dispatch(&mut self, peer_id: ENetPeerID, event: EventType) {
+        let godot_enet_event = Event {
+            peer_id,
 
-        tokio::spawn(async move {
-            while i < layers.len() {
-                let layer = &layers[i];
-                i += 1;
+            event,
 
-                let result = layer.call(event.clone()).await;
+            data_pile: DataPile::default(),
 
-                match result {
-                    Ok(passed_event) => {
-                        if passed_event.is_none() {
-                            return;
-                        }
+            tx_outgoing: self.tx_outgoing.clone(),
+        };
+
+        self.process_event(godot_enet_event);
+    }
+
+    /// Process an event through the layers according to the dispatch mode
+    ///
+    /// Once the maximum number of events are in flight,
+    /// the event waits until the layers have finished another.
+    fn process_event(&mut self, event: Event) {
+        // Events may not overtake those already waiting
+        if self.pending.is_empty()
+            && let Ok(permit) = Arc::clone(&self.in_flight).try_acquire_owned()
+        {
+            self.start_event(event, permit);
+        } else {
+            self.pending.push_back(event);
+        }
+    }
+
+    /// Start the waiting events while they fit in flight, returning whether none are left
+    fn start_pending(&mut self) -> bool {
+        while !self.pending.is_empty() {
+            let Ok(permit) = Arc::clone(&self.in_flight).try_acquire_owned() else {
+                return false;
+            };
+
+            if let Some(event) = self.pending.pop_front() {
+                self.start_event(event, permit);
+            }
+        }
+
+        true
+    }
 
-                        event = match passed_event {
-                            Some(ev) => ev,
-                            None => return,
-                        };
+    /// Hand an event which fits in flight to the layers
+    fn start_event(&mut self, event: Event, permit: OwnedSemaphorePermit) {
+        match self.dispatch_mode {
+            DispatchMode::Concurrent => {
+                let layers = Arc::clone(&self.layers);
+
+                self.tasks.spawn(async move {
+                    run_layers(&layers, event).await;
+
+                    drop(permit);
+                });
+            }
+            DispatchMode::Ordered => {
+                let peer_id = event.peer_id;
+                let peer_queues = Arc::clone(&self.peer_queues);
+
+                // A reused peer slot keeps the queue of its previous peer until its disconnect is processed
+                match peer_queues.entry(peer_id) {
+                    Entry::Occupied(queue) => {
+                        if queue.get().send((event, permit)).is_err() {
+                            error!("Event queue of peer {:?} closed, dropping event", peer_id);
+                        }
                     }
-                    Err(e) => {
-                        error!("Error processing event: \n{}", e);
+                    Entry::Vacant(entry) => {
+                        let (tx_queue, rx_queue) = mpsc::unbounded_channel();
+
+                        // The receiver is still held here
+                        let _ = tx_queue.send((event, permit));
 
-                        return;
+                        entry.insert(tx_queue);
+
+                        self.spawn_peer_queue(peer_id, rx_queue);
                     }
                 }
             }
+        }
+    }
+
+    /// Number of events queued or being processed by the layers
+    pub fn events_in_flight(&self) -> usize {
+        self.max_in_flight - self.in_flight.available_permits()
+    }
+
+    /// Spawn a task processing the events of a single peer in order
+    ///
+    /// The task removes its queue once the peer's disconnect has been processed,
+    /// unless events of a new peer in the same slot were queued behind it.
+    fn spawn_peer_queue(&mut self, peer_id: ENetPeerID, mut rx_queue: PeerQueueReceiver) {
+        let layers = Arc::clone(&self.layers);
+        let peer_queues = Arc::clone(&self.peer_queues);
+
+        self.tasks.spawn(async move {
+            while let Some((event, permit)) = rx_queue.recv().await {
+                let is_disconnect = matches!(event.event, EventType::Disconnect { .. });
+
+                run_layers(&layers, event).await;
+
+                drop(permit);
+
+                // Events are only queued while holding the entry, so none can be missed
+                if is_disconnect
+                    && peer_queues
+                        .remove_if(&peer_id, |_, _| rx_queue.is_empty())
+                        .is_some()
+                {
+                    break;
+                }
+            }
         });
     }
 
-    async fn send_outgoing(&mut self, outgoing: OutgoingPacket) -> Result<(), String> {
-        let host = self.get_mut_host()?;
+    /// Collect the layer tasks which have finished
+    fn reap_tasks(&mut self) {
+        while let Some(result) = self.tasks.try_join_next() {
+            if let Err(e) = result {
+                error!("Layer task failed: {}", e);
+            }
+        }
+    }
+
+    /// Apply a command from the layers, returning the number of packets sent
+    async fn apply_outgoing(&mut self, command: OutgoingCommand) -> Result<usize, ServerError> {
+        let Some(command) = self.route_to_shards(command) else {
+            return Ok(0);
+        };
 
-        let peer = host.get_peer_mut(outgoing.peer_id.into()).ok_or_else(|| {
-            format!(
-                "Failed to find peer with id {:?} to send packet to",
-                outgoing.peer_id
-            )
-        })?;
+        match command {
+            OutgoingCommand::Send(outgoing) => self.send_outgoing(outgoing).await,
+            OutgoingCommand::Broadcast(broadcast) => self.send_broadcast(broadcast).await,
+            OutgoingCommand::Forwarded(forwarded) => self.send_broadcast(forwarded.into()).await,
+            OutgoingCommand::Disconnect { peer_id, data } => {
+                debug!("Disconnecting peer: {:?}\nwith: {:?}", peer_id, data);
+
+                self.get_mut_peer(peer_id)?.disconnect(data);
+
+                Ok(0)
+            }
+            OutgoingCommand::DisconnectLater { peer_id, data } => {
+                debug!("Disconnecting peer later: {:?}\nwith: {:?}", peer_id, data);
+
+                self.get_mut_peer(peer_id)?.disconnect_later(data);
+
+                Ok(0)
+            }
+            OutgoingCommand::DisconnectNow { peer_id, data } => {
+                debug!("Disconnecting peer now: {:?}\nwith: {:?}", peer_id, data);
+
+                self.get_mut_peer(peer_id)?.disconnect_now(data);
+
+                self.dispatch_forced_disconnect(peer_id);
+
+                Ok(0)
+            }
+            OutgoingCommand::Reset { peer_id } => {
+                debug!("Resetting peer: {:?}", peer_id);
+
+                self.get_mut_peer(peer_id)?.reset();
+
+                self.dispatch_forced_disconnect(peer_id);
+
+                Ok(0)
+            }
+            OutgoingCommand::Throttle {
+                peer_id,
+                interval,
+                acceleration,
+                deceleration,
+            } => {
+                self.get_mut_peer(peer_id)?
+                    .set_throttle(interval, acceleration, deceleration);
+
+                Ok(0)
+            }
+            OutgoingCommand::Timeout {
+                peer_id,
+                limit,
+                minimum,
+                maximum,
+            } => {
+                self.get_mut_peer(peer_id)?
+                    .set_timeout(limit, minimum, maximum);
+
+                Ok(0)
+            }
+            OutgoingCommand::PingInterval { peer_id, interval } => {
+                self.get_mut_peer(peer_id)?.set_ping_interval(interval);
 
-        if peer.state() != enet::PeerState::Connected {
-            return Err(format!(
-                "Peer with id {:?} is not connected",
-                outgoing.peer_id
-            ));
+                Ok(0)
+            }
+        }
+    }
+
+    /// ENet does not generate disconnect events for forced disconnects,
+    /// so the layers are sent one directly
+    fn dispatch_forced_disconnect(&mut self, peer_id: ENetPeerID) {
+        if let Some(godot_peer) = self.gd_peers.remove(&peer_id) {
+            self.dispatch(peer_id, EventType::Disconnect { godot_peer });
         }
+    }
+
+    /// Forward the command, or the part of a broadcast, meant for peers of other shards
+    /// to their server, returning what is left for this one
+    ///
+    /// Commands for shards which are not running are left for this server,
+    /// which reports their peers as not found.
+    fn route_to_shards(&mut self, command: OutgoingCommand) -> Option<OutgoingCommand> {
+        if self.shard_routes.is_empty() {
+            return Some(command);
+        }
+
+        let shard = self.shard;
+
+        match command {
+            OutgoingCommand::Broadcast(mut broadcast) => {
+                let mut routed: HashMap<usize, Vec<ENetPeerID>> = HashMap::new();
+
+                broadcast.peer_ids.retain(|peer_id| {
+                    if peer_id.shard() == shard || peer_id.shard() >= self.shard_routes.len() {
+                        return true;
+                    }
+
+                    routed.entry(peer_id.shard()).or_default().push(*peer_id);
+                    false
+                });
+
+                for (peer_shard, peer_ids) in routed {
+                    // The packet itself stays with this shard, see ForwardedBroadcast
+                    let forwarded =
+                        OutgoingCommand::Forwarded(ForwardedBroadcast::new(&broadcast, peer_ids));
+
+                    if let Err(returned) = self.shard_routes[peer_shard].send(forwarded)
+                        && let OutgoingCommand::Forwarded(returned) = returned.0
+                    {
+                        broadcast.peer_ids.extend(returned.peer_ids);
+                    }
+                }
+
+                Some(OutgoingCommand::Broadcast(broadcast))
+            }
+            command => {
+                let route = command
+                    .peer_id()
+                    .filter(|peer_id| peer_id.shard() != shard)
+                    .and_then(|peer_id| self.shard_routes.get(peer_id.shard()));
+
+                match route {
+                    Some(route) => route.send(command).err().map(|returned| returned.0),
+                    None => Some(command),
+                }
+            }
+        }
+    }
+
+    fn get_mut_peer(
+        &mut self,
+        peer_id: ENetPeerID,
+    ) -> Result<&mut enet::Peer<ServerSocket>, ServerError> {
+        let shard = self.shard;
+
+        find_peer(self.get_mut_hosts()?, shard, peer_id)
+    }
+
+    /// Index of the shard of the server, see [`ShardedServer`]
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
+    /// Send the packet, returning the number of packets sent
+    async fn send_outgoing(&mut self, outgoing: OutgoingPacket) -> Result<usize, ServerError> {
+        let shard = self.shard;
+        let hosts = self.get_mut_hosts()?;
 
         debug!(
             "Sending packet: {:?}\nto peer: {:?}\non: {:?}",
@@ -208,32 +841,455 @@ impl Server {
             outgoing.channel_id
         );
 
-        peer.send(outgoing.channel_id, &outgoing.packet)
-            .map_err(|e| {
-                format!(
-                    "Failed to send packet to peer {:?}: {:?}",
-                    outgoing.peer_id, e
-                )
-            })?;
+        if let Err(error) = send_packet(
+            hosts,
+            shard,
+            outgoing.peer_id,
+            outgoing.channel_id,
+            &outgoing.packet,
+        ) {
+            self.report_send_failure(SendFailure {
+                peer_id: outgoing.peer_id,
+                channel_id: outgoing.channel_id,
+                packet: outgoing.packet,
+                error,
+            });
 
-        Ok(())
+            return Ok(0);
+        }
+
+        Ok(1)
     }
 
-    /// Obtain a reference to the ENet host
-    pub fn get_host(&self) -> Result<&enet::Host<UdpSocket>, String> {
+    /// Send the packet to every peer of the broadcast, returning the number of packets sent
+    async fn send_broadcast(&mut self, broadcast: OutgoingBroadcast) -> Result<usize, ServerError> {
+        let shard = self.shard;
+        let hosts = self.get_mut_hosts()?;
+
+        debug!(
+            "Broadcasting packet: {:?}\nto peers: {:?}\non: {:?}",
+            broadcast.packet.data(),
+            broadcast.peer_ids,
+            broadcast.channel_id
+        );
+
+        let mut failures = Vec::new();
+
+        for peer_id in broadcast.peer_ids.iter() {
+            if let Err(error) = send_packet(
+                hosts,
+                shard,
+                *peer_id,
+                broadcast.channel_id,
+                &broadcast.packet,
+            ) {
+                failures.push(SendFailure {
+                    peer_id: *peer_id,
+                    channel_id: broadcast.channel_id,
+                    packet: broadcast.packet.clone(),
+                    error,
+                });
+            }
+        }
+
+        let packets_sent = broadcast.peer_ids.len() - failures.len();
+
+        for failure in failures {
+            self.report_send_failure(failure);
+        }
+
+        Ok(packets_sent)
+    }
+
+    fn report_send_failure(&mut self, failure: SendFailure) {
+        self.dropped_sends += 1;
+
+        warn!(
+            "Dropped packet to peer {:?} on channel {}: {}",
+            failure.peer_id, failure.channel_id, failure.error
+        );
+
+        if let Some(reporter) = &self.send_failure_reporter {
+            reporter(&failure);
+        }
+    }
+
+    /// Number of outgoing packets which could not be sent since the server was built
+    pub fn dropped_sends(&self) -> u64 {
+        self.dropped_sends
+    }
+
+    /// Obtain a reference to the ENet host of the first address
+    pub fn get_host(&self) -> Result<&enet::Host<ServerSocket>, ServerError> {
+        self.hosts.first().ok_or(ServerError::NotOpen)
+    }
+
+    /// Obtain a mutable reference to the ENet host of the first address
+    pub fn get_mut_host(&mut self) -> Result<&mut enet::Host<ServerSocket>, ServerError> {
+        self.hosts.first_mut().ok_or(ServerError::NotOpen)
+    }
+
+    /// Obtain a reference to the ENet hosts, in the order of their addresses
+    pub fn get_hosts(&self) -> Result<&[enet::Host<ServerSocket>], ServerError> {
         if self.is_open() == false {
-            return Err("Server is not open".to_string());
+            return Err(ServerError::NotOpen);
         }
 
-        Ok(self.host.as_ref().unwrap())
+        Ok(&self.hosts)
     }
 
-    /// Obtain a mutable reference to the ENet host
-    pub fn get_mut_host(&mut self) -> Result<&mut enet::Host<UdpSocket>, String> {
+    /// Obtain a mutable reference to the ENet hosts, in the order of their addresses
+    pub fn get_mut_hosts(&mut self) -> Result<&mut [enet::Host<ServerSocket>], ServerError> {
         if self.is_open() == false {
-            return Err("Server is not open".to_string());
+            return Err(ServerError::NotOpen);
+        }
+
+        Ok(&mut self.hosts)
+    }
+}
+
+/// Wait until a packet can be received by any of the hosts
+async fn readable(hosts: &[enet::Host<ServerSocket>]) -> std::io::Result<()> {
+    std::future::poll_fn(|cx| {
+        for host in hosts {
+            if let Poll::Ready(result) = host.socket().poll_readable(cx) {
+                return Poll::Ready(result);
+            }
+        }
+
+        Poll::Pending
+    })
+    .await
+}
+
+/// Wait until the timer, forever without one
+async fn sleep_until(timer: Option<Instant>) {
+    match timer {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Find the peer among the hosts of the shard
+fn find_peer(
+    hosts: &mut [enet::Host<ServerSocket>],
+    shard: usize,
+    peer_id: ENetPeerID,
+) -> Result<&mut enet::Peer<ServerSocket>, ServerError> {
+    if peer_id.shard() != shard {
+        return Err(ServerError::PeerNotFound(peer_id));
+    }
+
+    hosts
+        .get_mut(peer_id.host_index())
+        .and_then(|host| host.get_peer_mut(peer_id.enet_peer_id()))
+        .ok_or(ServerError::PeerNotFound(peer_id))
+}
+
+fn send_packet(
+    hosts: &mut [enet::Host<ServerSocket>],
+    shard: usize,
+    peer_id: ENetPeerID,
+    channel_id: u8,
+    packet: &enet::Packet,
+) -> Result<(), ServerError> {
+    let peer = find_peer(hosts, shard, peer_id)?;
+
+    if peer.state() != enet::PeerState::Connected {
+        return Err(ServerError::PeerNotConnected(peer_id));
+    }
+
+    peer.send(channel_id, packet)
+        .map_err(|e| ServerError::Send { peer_id, source: e })
+}
+
+/// Pass an event through the layers until one consumes it or fails
+async fn run_layers(layers: &[Arc<dyn Layer>], mut event: Event) {
+    let mut i: usize = 0;
+
+    while i < layers.len() {
+        let layer = &layers[i];
+        i += 1;
+
+        let result = layer.call(event.clone()).await;
+
+        match result {
+            Ok(passed_event) => {
+                if passed_event.is_none() {
+                    return;
+                }
+
+                event = match passed_event {
+                    Some(ev) => ev,
+                    None => return,
+                };
+            }
+            Err(e) => {
+                error!("Error processing event: \n{}", e);
+
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_duplicate_addresses() {
+        let address: SocketAddr = "127.0.0.1:6000".parse().unwrap();
+
+        let server = Server::builder()
+            .socket(address)
+            .add_socket(address)
+            .build();
+
+        assert!(matches!(server, Err(ServerError::DuplicateAddress(a)) if a == address));
+    }
+
+    #[tokio::test]
+    async fn numbers_peers_by_host() {
+        let mut server = Server::builder()
+            .socket("0.0.0.0:0".parse().unwrap())
+            .add_socket("127.0.0.1:0".parse().unwrap())
+            .build()
+            .unwrap();
+        server.open().unwrap();
+
+        let addresses = server.local_addrs().unwrap();
+        assert_eq!(addresses.len(), 2);
+        assert_ne!(addresses[0].port(), addresses[1].port());
+
+        let client_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = enet::Host::new(client_socket, enet::HostSettings::default()).unwrap();
+        client.connect(addresses[1], 2, 7).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.gd_peers.is_empty() {
+            assert!(Instant::now() < deadline, "client did not connect");
+
+            while client.service().unwrap().is_some() {}
+            server.service().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let (enet_peer, godot_peer) = server.gd_peers.iter().next().unwrap();
+        assert_eq!(enet_peer.host_index(), 1);
+        assert_eq!(enet_peer.enet_peer_id(), enet::PeerID(0));
+        assert_eq!(*godot_peer, GDPeerID(7));
+    }
+
+    fn peer(shard: usize, peer_id: usize) -> ENetPeerID {
+        ENetPeerID::in_shard(shard, 0, enet::PeerID(peer_id))
+    }
+
+    // A server of shard 1 with routes to shards 0 and 2, the route to shard 2 being closed
+    fn sharded_server() -> (Server, mpsc::UnboundedReceiver<OutgoingCommand>) {
+        let mut server = Server::builder().shard(1).build().unwrap();
+
+        let (tx_shard_0, rx_shard_0) = mpsc::unbounded_channel();
+        let (tx_shard_2, _) = mpsc::unbounded_channel();
+        server.shard_routes = vec![tx_shard_0, server.tx_outgoing.clone(), tx_shard_2];
+
+        (server, rx_shard_0)
+    }
+
+    #[test]
+    fn routes_commands_to_their_shard() {
+        let (mut server, mut rx_shard_0) = sharded_server();
+
+        let reset = OutgoingCommand::Reset {
+            peer_id: peer(0, 4),
+        };
+        assert!(server.route_to_shards(reset).is_none());
+        assert!(matches!(
+            rx_shard_0.try_recv(),
+            Ok(OutgoingCommand::Reset { peer_id }) if peer_id == peer(0, 4)
+        ));
+
+        let reset = OutgoingCommand::Reset {
+            peer_id: peer(1, 4),
+        };
+        assert!(matches!(
+            server.route_to_shards(reset),
+            Some(OutgoingCommand::Reset { peer_id }) if peer_id == peer(1, 4)
+        ));
+
+        // Left for this server when the shard is not running
+        let reset = OutgoingCommand::Reset {
+            peer_id: peer(2, 4),
+        };
+        assert!(matches!(
+            server.route_to_shards(reset),
+            Some(OutgoingCommand::Reset { peer_id }) if peer_id == peer(2, 4)
+        ));
+        assert!(rx_shard_0.try_recv().is_err());
+    }
+
+    #[test]
+    fn splits_broadcasts_between_shards() {
+        let (mut server, mut rx_shard_0) = sharded_server();
+
+        let broadcast = OutgoingCommand::Broadcast(OutgoingBroadcast {
+            peer_ids: vec![peer(0, 1), peer(1, 2), peer(2, 3), peer(0, 4), peer(5, 5)],
+            channel_id: 0,
+            packet: enet::Packet::reliable(&[1u8, 2, 3][..]),
+            kind: enet::PacketKind::Reliable,
+        });
+
+        // Peers of closed or unknown shards stay with this server, which reports them
+        let Some(OutgoingCommand::Broadcast(kept)) = server.route_to_shards(broadcast) else {
+            panic!("broadcast was not kept for this shard");
+        };
+        let mut kept = kept.peer_ids;
+        kept.sort();
+        assert_eq!(kept, vec![peer(1, 2), peer(2, 3), peer(5, 5)]);
+
+        // Shard 0 gets the bytes rather than sharing the packet
+        let Ok(OutgoingCommand::Forwarded(forwarded)) = rx_shard_0.try_recv() else {
+            panic!("broadcast was not forwarded to shard 0");
+        };
+        assert_eq!(forwarded.peer_ids, vec![peer(0, 1), peer(0, 4)]);
+        assert_eq!(forwarded.data, vec![1, 2, 3]);
+        assert_eq!(forwarded.kind, enet::PacketKind::Reliable);
+        assert!(rx_shard_0.try_recv().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn shards_share_a_port() {
+        // Reserve a free port for both shards
+        let address = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let peer_map_layer = crate::layers::PeerMapLayer::default();
+        let peer_map = peer_map_layer.peer_map();
+
+        let mut sharded = ShardedServer::build(2, |_| {
+            Server::builder()
+                .socket(address)
+                .layer(peer_map_layer.clone())
+        })
+        .unwrap();
+        sharded.open().unwrap();
+
+        let client_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = enet::Host::new(client_socket, enet::HostSettings::default()).unwrap();
+        let client_peer = client.connect(address, 2, 7).unwrap().id();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let accepted = loop {
+            assert!(Instant::now() < deadline, "client did not connect");
+
+            while client.service().unwrap().is_some() {}
+            for shard in sharded.shards_mut() {
+                shard.service().await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+
+            if let Some(shard) = sharded
+                .shards()
+                .iter()
+                .position(|shard| !shard.gd_peers.is_empty())
+                && peer_map
+                    .in_shard(shard)
+                    .get_enet_peer(&GDPeerID(7))
+                    .is_some()
+            {
+                break shard;
+            }
+        };
+        let other = 1 - accepted;
+
+        // The peer is numbered and mapped in the shard which accepted it only
+        let enet_peer = *sharded.shards()[accepted].gd_peers.keys().next().unwrap();
+        assert_eq!(enet_peer.shard(), accepted);
+        assert!(sharded.shards()[other].gd_peers.is_empty());
+        assert_eq!(
+            peer_map.in_shard(accepted).get_enet_peer(&GDPeerID(7)),
+            Some(enet_peer)
+        );
+        assert_eq!(peer_map.in_shard(other).get_enet_peer(&GDPeerID(7)), None);
+
+        // Commands queued on the other shard are forwarded to the peer's shard
+        let packet = OutgoingPacket::new(
+            enet_peer,
+            GodotChannel::DEFAULT,
+            TransferMode::Reliable,
+            vec![4, 5, 6],
+        )
+        .unwrap();
+        sharded.shards()[other]
+            .tx_outgoing
+            .send(packet.into())
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            assert!(Instant::now() < deadline, "packet did not reach the client");
+
+            for shard in sharded.shards_mut() {
+                shard.service().await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+
+            if let Some(enet::Event::Receive { peer, packet, .. }) = client.service().unwrap() {
+                assert_eq!(peer.id(), client_peer);
+                assert_eq!(packet.data(), &[4, 5, 6]);
+                break;
+            }
+        }
+    }
+
+    // A layer which never finishes, like a consumer stuck behind a slow backend
+    async fn stall(_event: Event) -> crate::LayerResult {
+        std::future::pending().await
+    }
+
+    #[tokio::test]
+    async fn services_peers_while_layers_are_saturated() {
+        let mut server = Server::builder()
+            .socket("127.0.0.1:0".parse().unwrap())
+            .max_in_flight(1)
+            .layer(crate::AsyncLayer::build(stall))
+            .build()
+            .unwrap();
+        server.open().unwrap();
+        let address = server.local_addrs().unwrap()[0];
+
+        let client_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = enet::Host::new(client_socket, enet::HostSettings::default()).unwrap();
+        let peer = client.connect(address, 2, 7).unwrap();
+        // The client gives up on the server after 500ms without acknowledgements
+        peer.set_ping_interval(50);
+        peer.set_timeout(1, 200, 500);
+        let peer_id = peer.id();
+
+        let deadline = Instant::now() + Duration::from_millis(1500);
+        let mut stats = ServiceStats::default();
+
+        while Instant::now() < deadline {
+            while let Some(event) = client.service().unwrap() {
+                assert!(
+                    !matches!(event, enet::Event::Disconnect { .. }),
+                    "client timed out"
+                );
+            }
+
+            if client.peer(peer_id).state() == enet::PeerState::Connected {
+                let packet = enet::Packet::reliable(&[0u8][..]);
+                client.peer_mut(peer_id).send(0, &packet).unwrap();
+            }
+
+            stats = server.service().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
         }
 
-        Ok(self.host.as_mut().unwrap())
+        assert_eq!(client.peer(peer_id).state(), enet::PeerState::Connected);
+        assert!(stats.events_pending > 0);
     }
 }
@@ -0,0 +1,147 @@
+use super::{Server, ServerError, builder::ServerBuilder};
+use crate::MAX_SHARDS;
+use log::{error, info};
+use std::future::Future;
+use tokio::sync::watch;
+
+/// Several [`Server`]s bound to the same addresses with `SO_REUSEPORT`,
+/// the kernel spreading the clients between them by address and port
+///
+/// Every shard gets the layers of its own builder, which may be clones of the same layers.
+/// Godot clients pick their [`GDPeerID`](crate::GDPeerID) themselves
+/// and clients of different shards may pick the same one,
+/// so the state shared by cloned layers, like a [`PeerMap`](crate::layers::PeerMap),
+/// is namespaced by the shard of each peer's [`ENetPeerID`](crate::ENetPeerID).
+/// Handles taken from a layer look up the peers of shard 0, use their `in_shard` for the others.
+/// Commands for a peer of another shard are forwarded to its server.
+///
+/// Only supported on Unix.
+pub struct ShardedServer {
+    shards: Vec<Server>,
+}
+
+impl ShardedServer {
+    /// Build a server for every shard from the builder made for it
+    ///
+    /// The builder is called with the index of each shard,
+    /// the port reuse and the shard are set on what it returns.
+    pub fn build(
+        shards: usize,
+        mut builder: impl FnMut(usize) -> ServerBuilder,
+    ) -> Result<Self, ServerError> {
+        if shards == 0 || shards > MAX_SHARDS {
+            return Err(ServerError::OutOfRange {
+                setting: "Shard count",
+                min: 1,
+                max: Some(MAX_SHARDS),
+                value: shards,
+            });
+        }
+
+        let mut servers = Vec::with_capacity(shards);
+
+        for shard in 0..shards {
+            servers.push(builder(shard).reuse_port(true).shard(shard).build()?);
+        }
+
+        let routes: Vec<_> = servers
+            .iter()
+            .map(|server| server.tx_outgoing.clone())
+            .collect();
+
+        for server in servers.iter_mut() {
+            server.shard_routes = routes.clone();
+        }
+
+        Ok(Self { shards: servers })
+    }
+
+    /// Open every shard with the [`HostConfig`](crate::HostConfig) of the builder
+    ///
+    /// If any shard fails to open, the shards already opened are closed.
+    pub fn open(&mut self) -> Result<(), ServerError> {
+        for i in 0..self.shards.len() {
+            if let Err(e) = self.shards[i].open() {
+                for shard in self.shards[..i].iter_mut() {
+                    shard.hosts.clear();
+                }
+
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if every shard is open
+    pub fn is_open(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_open())
+    }
+
+    /// Obtain a reference to the servers, in the order of their shard
+    pub fn shards(&self) -> &[Server] {
+        &self.shards
+    }
+
+    /// Obtain a mutable reference to the servers, in the order of their shard
+    pub fn shards_mut(&mut self) -> &mut [Server] {
+        &mut self.shards
+    }
+
+    /// Take the servers, to run them on threads or runtimes of their own
+    ///
+    /// Commands are still forwarded between them while they are alive.
+    pub fn into_shards(self) -> Vec<Server> {
+        self.shards
+    }
+
+    /// Service every shard on its own task until the stop future completes,
+    /// then gracefully shut them all down
+    ///
+    /// See [`Server::run_until`] and [`Server::shutdown`].
+    pub async fn run_until<F: Future>(
+        self,
+        stop: F,
+        reason: u32,
+        grace_period: std::time::Duration,
+    ) -> Result<(), ServerError> {
+        let (tx_stop, rx_stop) = watch::channel(false);
+        let mut tasks = tokio::task::JoinSet::new();
+
+        for mut shard in self.shards {
+            let mut rx_stop = rx_stop.clone();
+
+            tasks.spawn(async move {
+                shard
+                    .run_until(async move {
+                        let _ = rx_stop.wait_for(|stopped| *stopped).await;
+                    })
+                    .await?;
+
+                if shard.is_open() {
+                    shard.shutdown(reason, grace_period).await?;
+                }
+
+                Ok::<usize, ServerError>(shard.shard())
+            });
+        }
+
+        stop.await;
+        let _ = tx_stop.send(true);
+
+        let mut result = Ok(());
+
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(Ok(shard)) => info!("Shard {} stopped", shard),
+                Ok(Err(e)) => {
+                    error!("Shard stopped with error: {}", e);
+                    result = Err(e);
+                }
+                Err(e) => error!("Shard task failed: {}", e),
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,210 @@
+use super::dtls::DtlsSocket;
+use rusty_enet as enet;
+use socket2::{Domain, Protocol, Type};
+use std::{
+    io::{self, ErrorKind},
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Options applied to the sockets of the server before they are bound
+pub struct BindOptions {
+    /// Whether IPv6 sockets also accept IPv4 clients through IPv4-mapped addresses,
+    /// as Godot's do.
+    ///
+    /// Disable it to bind `0.0.0.0` and `[::]` to the same port.
+    pub dual_stack: bool,
+    /// Whether to set `SO_REUSEPORT`, letting several sockets bind the same port
+    /// with the kernel spreading the clients between them.
+    ///
+    /// Only supported on Unix.
+    pub reuse_port: bool,
+}
+
+impl Default for BindOptions {
+    fn default() -> Self {
+        Self {
+            dual_stack: true,
+            reuse_port: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A [`Socket`](rusty_enet::Socket) backed by a [`tokio::net::UdpSocket`],
+/// letting the server wait for incoming packets without blocking.
+pub struct TokioUdpSocket {
+    socket: Arc<tokio::net::UdpSocket>,
+}
+
+impl TokioUdpSocket {
+    /// Bind a socket to the address
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn bind(address: SocketAddr) -> io::Result<Self> {
+        Self::bind_with(address, BindOptions::default())
+    }
+
+    /// Bind a socket to the address with the options
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn bind_with(address: SocketAddr, options: BindOptions) -> io::Result<Self> {
+        let socket = socket2::Socket::new(
+            Domain::for_address(address),
+            Type::DGRAM,
+            Some(Protocol::UDP),
+        )?;
+
+        if address.is_ipv6() {
+            socket.set_only_v6(!options.dual_stack)?;
+        }
+
+        if options.reuse_port {
+            #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+            socket.set_reuse_port(true)?;
+
+            #[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "SO_REUSEPORT is not supported on this platform",
+            ));
+        }
+
+        socket.bind(&address.into())?;
+
+        Self::from_std(socket.into())
+    }
+
+    /// Wrap a bound standard library socket
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn from_std(socket: std::net::UdpSocket) -> io::Result<Self> {
+        if tokio::runtime::Handle::try_current().is_err() {
+            return Err(io::Error::other(
+                "Socket must be created within a Tokio runtime",
+            ));
+        }
+
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket: Arc::new(tokio::net::UdpSocket::from_std(socket)?),
+        })
+    }
+
+    /// Wait until a packet can be received
+    pub async fn readable(&self) -> io::Result<()> {
+        self.socket.readable().await
+    }
+
+    /// Poll whether a packet can be received
+    pub fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.socket.poll_recv_ready(cx)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl enet::Socket for TokioUdpSocket {
+    type Address = SocketAddr;
+    type Error = io::Error;
+
+    fn init(&mut self, socket_options: enet::SocketOptions) -> Result<(), io::Error> {
+        self.socket.set_broadcast(true)?;
+
+        // Mirrors ENet's own sockets, where the kernel may round or cap the sizes
+        let socket = socket2::SockRef::from(self.socket.as_ref());
+        socket.set_recv_buffer_size(socket_options.receive_buffer)?;
+        socket.set_send_buffer_size(socket_options.send_buffer)?;
+
+        Ok(())
+    }
+
+    fn send(&mut self, address: SocketAddr, buffer: &[u8]) -> Result<usize, io::Error> {
+        match self.socket.try_send_to(buffer, address) {
+            Ok(sent_length) => Ok(sent_length),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; enet::MTU_MAX],
+    ) -> Result<Option<(SocketAddr, enet::PacketReceived)>, io::Error> {
+        match self.socket.try_recv_from(buffer) {
+            Ok((recv_length, recv_addr)) => Ok(Some((
+                recv_addr,
+                enet::PacketReceived::Complete(recv_length),
+            ))),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The [`Socket`](rusty_enet::Socket) of the [`Server`](super::Server),
+/// plain UDP or encrypted with DTLS
+pub enum ServerSocket {
+    Udp(TokioUdpSocket),
+    Dtls(DtlsSocket),
+}
+
+impl ServerSocket {
+    /// Wait until a packet can be received
+    pub async fn readable(&self) -> io::Result<()> {
+        match self {
+            ServerSocket::Udp(socket) => socket.readable().await,
+            ServerSocket::Dtls(socket) => socket.readable().await,
+        }
+    }
+
+    /// Poll whether a packet can be received
+    pub fn poll_readable(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self {
+            ServerSocket::Udp(socket) => socket.poll_readable(cx),
+            ServerSocket::Dtls(socket) => socket.poll_readable(cx),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            ServerSocket::Udp(socket) => socket.local_addr(),
+            ServerSocket::Dtls(socket) => socket.local_addr(),
+        }
+    }
+}
+
+impl enet::Socket for ServerSocket {
+    type Address = SocketAddr;
+    type Error = io::Error;
+
+    fn init(&mut self, socket_options: enet::SocketOptions) -> Result<(), io::Error> {
+        match self {
+            ServerSocket::Udp(socket) => socket.init(socket_options),
+            ServerSocket::Dtls(socket) => socket.init(socket_options),
+        }
+    }
+
+    fn send(&mut self, address: SocketAddr, buffer: &[u8]) -> Result<usize, io::Error> {
+        match self {
+            ServerSocket::Udp(socket) => socket.send(address, buffer),
+            ServerSocket::Dtls(socket) => socket.send(address, buffer),
+        }
+    }
+
+    fn receive(
+        &mut self,
+        buffer: &mut [u8; enet::MTU_MAX],
+    ) -> Result<Option<(SocketAddr, enet::PacketReceived)>, io::Error> {
+        match self {
+            ServerSocket::Udp(socket) => socket.receive(buffer),
+            ServerSocket::Dtls(socket) => socket.receive(buffer),
+        }
+    }
+}
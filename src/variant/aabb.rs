@@ -1,7 +1,7 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::hash::Hash;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct AABB {
     pub position: [helpers::WrappedF64; 3],
     pub size: [helpers::WrappedF64; 3],
@@ -9,7 +9,7 @@ pub struct AABB {
 
 impl Variant for AABB {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 16u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -45,7 +45,7 @@ impl Variant for AABB {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -55,7 +55,7 @@ impl Variant for AABB {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < consumed + 6 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit AABB Variant".to_string());
+                return Err(VariantError::Truncated("64-bit AABB Variant"));
             }
 
             for i in 0..3 {
@@ -69,7 +69,7 @@ impl Variant for AABB {
             }
         } else {
             if raw_bytes.len() < consumed + 6 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit AABB Variant".to_string());
+                return Err(VariantError::Truncated("32-bit AABB Variant"));
             }
 
             for i in 0..3 {
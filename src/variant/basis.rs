@@ -1,13 +1,13 @@
 use std::hash::Hash;
 
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Basis(pub [[helpers::WrappedF64; 3]; 3]);
 
 impl Variant for Basis {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 17u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -41,7 +41,7 @@ impl Variant for Basis {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -50,7 +50,7 @@ impl Variant for Basis {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < consumed + 9 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Basis Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Basis Variant"));
             }
 
             for i in 0..3 {
@@ -62,7 +62,7 @@ impl Variant for Basis {
             }
         } else {
             if raw_bytes.len() < consumed + 9 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Basis Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Basis Variant"));
             }
 
             for i in 0..3 {
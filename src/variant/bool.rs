@@ -1,10 +1,10 @@
-use super::{DecodingResult, Variant};
+use super::{DecodingResult, Variant, VariantError};
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Bool(pub bool);
 
 impl Variant for Bool {
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut encoded = 1u32.to_le_bytes().to_vec();
 
         encoded.extend(if self.0 {
@@ -17,12 +17,12 @@ impl Variant for Bool {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 4 {
-            return Err("Not Enough Bytes to Decode Bool Variant".to_string());
+            return Err(VariantError::Truncated("Bool Variant"));
         }
 
         return Ok(DecodingResult {
@@ -35,15 +35,17 @@ impl Variant for Bool {
 }
 
 impl Bool {
-    pub fn encode_compressed(&self) -> Result<Vec<u8>, String> {
+    // Replicated from encode_and_compress_variant in multiplayer_api.cpp
+    pub fn encode_compressed(&self) -> Result<Vec<u8>, VariantError> {
         return if self.0 {
-            Ok(vec![super::VARIANT_META_BOOL_MASK])
+            Ok(vec![super::VARIANT_META_BOOL_MASK | 1])
         } else {
-            Ok(vec![0u8])
+            Ok(vec![1u8])
         };
     }
 
-    pub fn decode_compressed(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    // Replicated from decode_and_decompress_variant in multiplayer_api.cpp
+    pub fn decode_compressed(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -53,7 +55,7 @@ impl Bool {
                 variant: Box::new(Self((raw_bytes[0] & super::VARIANT_META_BOOL_MASK) > 0)),
             })
         } else {
-            Err("Invalid Header for Nil Variant".to_string())
+            Err(VariantError::InvalidHeader("Compressed Bool Variant"))
         };
     }
 }
@@ -1,7 +1,7 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::hash::Hash;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Color {
     pub r: helpers::WrappedF32,
     pub g: helpers::WrappedF32,
@@ -11,7 +11,7 @@ pub struct Color {
 
 impl Variant for Color {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 20u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -25,7 +25,7 @@ impl Variant for Color {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -33,7 +33,7 @@ impl Variant for Color {
         let mut consumed = 0;
 
         if raw_bytes.len() < 4 * 4 {
-            return Err("Not Enough Bytes to Decode 32-bit Color Variant".to_string());
+            return Err(VariantError::Truncated("32-bit Color Variant"));
         }
 
         for i in 0..4 {
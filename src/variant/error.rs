@@ -0,0 +1,108 @@
+use std::{error::Error as StdError, fmt::Display, string::FromUtf8Error};
+
+#[derive(Debug)]
+/// Error encoding or decoding a [`Variant`](super::Variant)
+pub enum VariantError {
+    /// Not enough bytes to decode the named part.
+    Truncated(&'static str),
+    /// The variant type, named if known, can not be decoded.
+    UnsupportedType { variant: &'static str, type_id: u32 },
+    /// The element type of a typed container can not be decoded,
+    /// `None` for script and class types.
+    UnsupportedElementType {
+        container: &'static str,
+        element: &'static str,
+        type_id: Option<u32>,
+    },
+    /// The header does not match the named variant.
+    InvalidHeader(&'static str),
+    /// A string in the named variant is not valid UTF-8.
+    InvalidUtf8 {
+        variant: &'static str,
+        source: FromUtf8Error,
+    },
+    /// An element of a container failed to decode.
+    Element {
+        container: &'static str,
+        /// Whether the element is a key or a value.
+        element: &'static str,
+        /// Index of the element, starting from 1.
+        index: u32,
+        count: u32,
+        source: Box<VariantError>,
+    },
+    /// An element of a typed container is not of the container's type.
+    UnexpectedElementType {
+        container: &'static str,
+        element: &'static str,
+        index: u32,
+        count: u32,
+    },
+    /// The variant can not be encoded, for the named reason.
+    Unencodable(&'static str),
+}
+
+impl StdError for VariantError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            VariantError::InvalidUtf8 { source, .. } => Some(source),
+            VariantError::Element { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for VariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariantError::Truncated(part) => write!(f, "Not Enough Bytes to Decode {}", part),
+            VariantError::UnsupportedType { variant, type_id } => write!(
+                f,
+                "Decoding {} Variants of Type {} Is Not Supported",
+                variant, type_id
+            ),
+            VariantError::UnsupportedElementType {
+                container,
+                element,
+                type_id: Some(type_id),
+            } => write!(
+                f,
+                "Decoding {} with {} Type {} Is Not Supported",
+                container, element, type_id
+            ),
+            VariantError::UnsupportedElementType {
+                container,
+                element,
+                type_id: None,
+            } => write!(
+                f,
+                "Decoding {} with Non-Built In {} Type Is Not Supported",
+                container, element
+            ),
+            VariantError::InvalidHeader(variant) => write!(f, "Invalid Header for {}", variant),
+            VariantError::InvalidUtf8 { variant, .. } => write!(f, "Invalid UTF-8 in {}", variant),
+            VariantError::Element {
+                container,
+                element,
+                index,
+                count,
+                source,
+            } => write!(
+                f,
+                "Failed to Decode {} {} of {} in {}: \n{}",
+                element, index, count, container, source
+            ),
+            VariantError::UnexpectedElementType {
+                container,
+                element,
+                index,
+                count,
+            } => write!(
+                f,
+                "Failed to Downcast {} {} of {} in {} to Expected Type",
+                element, index, count, container
+            ),
+            VariantError::Unencodable(reason) => write!(f, "Cannot Encode {}", reason),
+        }
+    }
+}
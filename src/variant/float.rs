@@ -1,13 +1,13 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::hash::Hash;
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Float(pub helpers::WrappedF64);
 
 impl Variant for Float {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
-        let mut header = 2u32;
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
+        let mut header = 3u32;
 
         if *self.0 as f32 as f64 != *self.0 {
             header |= super::HEADER_DATA_FLAG_64;
@@ -25,13 +25,13 @@ impl Variant for Float {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Float Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Float Variant"));
             }
 
             return Ok(DecodingResult {
@@ -40,7 +40,7 @@ impl Variant for Float {
             });
         } else {
             if raw_bytes.len() < 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Float Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Float Variant"));
             }
 
             return Ok(DecodingResult {
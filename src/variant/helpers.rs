@@ -71,6 +71,19 @@ impl PartialEq for WrappedF64 {
 
 impl Eq for WrappedF64 {}
 
+// Total order over the bits, consistent with the bitwise equality
+impl PartialOrd for WrappedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WrappedF64 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
 impl Hash for WrappedF64 {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         let bits = self.0.to_bits();
@@ -109,6 +122,19 @@ impl PartialEq for WrappedF32 {
 
 impl Eq for WrappedF32 {}
 
+// Total order over the bits, consistent with the bitwise equality
+impl PartialOrd for WrappedF32 {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WrappedF32 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
 impl Hash for WrappedF32 {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         let bits = self.0.to_bits();
@@ -1,11 +1,11 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Int(pub i64);
 
 impl Variant for Int {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 2u32;
 
         if self.0 < i32::MIN as i64 || self.0 > i32::MAX as i64 {
@@ -24,13 +24,13 @@ impl Variant for Int {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Int Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Int Variant"));
             }
 
             return Ok(DecodingResult {
@@ -39,7 +39,7 @@ impl Variant for Int {
             });
         } else {
             if raw_bytes.len() < 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Int Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Int Variant"));
             }
 
             return Ok(DecodingResult {
@@ -51,56 +51,71 @@ impl Variant for Int {
 }
 
 impl Int {
-    pub fn encode_compressed(&self) -> Result<Vec<u8>, String> {
-        // TODO: Encode Compressed Int Variants
+    // Replicated from encode_and_compress_variant in multiplayer_api.cpp
+    pub fn encode_compressed(&self) -> Result<Vec<u8>, VariantError> {
+        let mut encoded = vec![2u8];
+
+        if self.0 >= i8::MIN as i64 && self.0 <= i8::MAX as i64 {
+            // 8-bit encode mode is 0, leaving the meta byte as is
+            encoded.extend((self.0 as i8).to_le_bytes());
+        } else if self.0 >= i16::MIN as i64 && self.0 <= i16::MAX as i64 {
+            encoded[0] |= 1 << 6;
+            encoded.extend((self.0 as i16).to_le_bytes());
+        } else if self.0 >= i32::MIN as i64 && self.0 <= i32::MAX as i64 {
+            encoded[0] |= 2 << 6;
+            encoded.extend((self.0 as i32).to_le_bytes());
+        } else {
+            encoded[0] |= 3 << 6;
+            encoded.extend(self.0.to_le_bytes());
+        }
 
-        return Err("Can Not Encode Compressed Int Variants".to_string());
+        Ok(encoded)
     }
 
     // Replicated from decode_and_decompress_variant in multiplayer_api.cpp
-    pub fn decode_compressed(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    pub fn decode_compressed(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         let encode_mode = raw_bytes[0] & super::VARIANT_META_EMODE_MASK;
 
         if encode_mode == 0 << 6 {
-            if raw_bytes.len() < 1 {
-                return Err("Not Enough Bytes to Decode Compressed 8-bit Int Variant".to_string());
+            if raw_bytes.len() < 1 + 1 {
+                return Err(VariantError::Truncated("Compressed 8-bit Int Variant"));
             }
 
             return Ok(DecodingResult {
                 consumed: 1 + 1,
-                variant: Box::new(Self::from(raw_bytes[1] as i64)),
+                variant: Box::new(Self::from(raw_bytes[1] as i8 as i64)),
             });
         } else if encode_mode == 1 << 6 {
-            if raw_bytes.len() < 2 {
-                return Err("Not Enough Bytes to Decode Compressed 16-bit Int Variant".to_string());
+            if raw_bytes.len() < 1 + 2 {
+                return Err(VariantError::Truncated("Compressed 16-bit Int Variant"));
             }
 
             return Ok(DecodingResult {
                 consumed: 1 + 2,
                 variant: Box::new(Self::from(
-                    u16::from_le_bytes([raw_bytes[1], raw_bytes[2]]) as i64
+                    i16::from_le_bytes([raw_bytes[1], raw_bytes[2]]) as i64
                 )),
             });
         } else if encode_mode == 2 << 6 {
-            if raw_bytes.len() < 4 {
-                return Err("Not Enough Bytes to Decode Compressed 32-bit Int Variant".to_string());
+            if raw_bytes.len() < 1 + 4 {
+                return Err(VariantError::Truncated("Compressed 32-bit Int Variant"));
             }
 
             return Ok(DecodingResult {
                 consumed: 1 + 4,
-                variant: Box::new(Self::from(helpers::parse_u32(raw_bytes) as i64)),
+                variant: Box::new(Self::from(helpers::parse_i32(&raw_bytes[1..]) as i64)),
             });
         } else {
-            if raw_bytes.len() < 1 {
-                return Err("Not Enough Bytes to Decode Compressed 64-bit Int Variant".to_string());
+            if raw_bytes.len() < 1 + 8 {
+                return Err(VariantError::Truncated("Compressed 64-bit Int Variant"));
             }
 
             return Ok(DecodingResult {
                 consumed: 1 + 8,
-                variant: Box::new(Self::from(helpers::parse_u64(raw_bytes) as i64)),
+                variant: Box::new(Self::from(helpers::parse_i64(&raw_bytes[1..]))),
             });
         }
     }
@@ -2,6 +2,7 @@ mod aabb;
 mod basis;
 mod bool;
 mod color;
+mod error;
 mod float;
 pub mod helpers;
 mod int;
@@ -39,6 +40,7 @@ pub use aabb::*;
 pub use basis::*;
 pub use bool::*;
 pub use color::*;
+pub use error::*;
 pub use float::*;
 pub use int::*;
 pub use nil::*;
@@ -103,11 +105,11 @@ pub struct DecodingResult<T: ?Sized> {
 
 pub fn decode_and_decompress_variant(
     raw_bytes: &[u8],
-) -> Result<DecodingResult<dyn Variant>, String> {
+) -> Result<DecodingResult<dyn Variant>, VariantError> {
     // Replicated from decode_and_decompress_variant in multiplayer_api.cpp
 
     if raw_bytes.len() < 1 {
-        return Err("Not Enough Bytes to Decode Compressed Variant".to_string());
+        return Err(VariantError::Truncated("Compressed Variant"));
     }
 
     let variant_type = raw_bytes[0] & VARIANT_META_TYPE_MASK;
@@ -127,11 +129,25 @@ pub fn decode_and_decompress_variant(
     return decode_variant(raw_bytes);
 }
 
-pub fn decode_variant(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String> {
+pub fn encode_and_compress_variant(variant: &dyn Variant) -> Result<Vec<u8>, VariantError> {
+    // Replicated from encode_and_compress_variant in multiplayer_api.cpp
+
+    if let Some(bool_variant) = variant.as_any().downcast_ref::<Bool>() {
+        return bool_variant.encode_compressed();
+    }
+
+    if let Some(int_variant) = variant.as_any().downcast_ref::<Int>() {
+        return int_variant.encode_compressed();
+    }
+
+    return variant.encode();
+}
+
+pub fn decode_variant(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError> {
     // Replicated from decode_variant in marshalls.cpp
 
     if raw_bytes.len() < 4 {
-        return Err("Not Enough Bytes to Decode Variant".to_string());
+        return Err(VariantError::Truncated("Variant"));
     }
 
     let header: u32 = u32::from_le_bytes([raw_bytes[0], raw_bytes[1], raw_bytes[2], raw_bytes[3]]);
@@ -231,7 +247,10 @@ pub fn decode_variant(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, S
         }
         // NODE_PATH
         22 => {
-            return Err("Decoding Node Path Variants Is Not Supported".to_string());
+            return Err(VariantError::UnsupportedType {
+                variant: "Node Path",
+                type_id: 22,
+            });
         }
         // RID
         23 => {
@@ -239,15 +258,24 @@ pub fn decode_variant(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, S
         }
         // OBJECT
         24 => {
-            return Err("Decoding Object Variants Is Not Supported.".to_string());
+            return Err(VariantError::UnsupportedType {
+                variant: "Object",
+                type_id: 24,
+            });
         }
         // CALLABLE
         25 => {
-            return Err("Decoding Callable Variants Is Not Supported.".to_string());
+            return Err(VariantError::UnsupportedType {
+                variant: "Callable",
+                type_id: 25,
+            });
         }
         // SIGNAL
         26 => {
-            return Err("Decoding Signal Variants Is Not Supported.".to_string());
+            return Err(VariantError::UnsupportedType {
+                variant: "Signal",
+                type_id: 26,
+            });
         }
         // DICTIONARY
         27 => {
@@ -283,24 +311,41 @@ pub fn decode_variant(raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, S
         }
         // PACKED_VECTOR2_ARRAY
         35 => {
-            return Err("Decoding PACKED_VECTOR2_ARRAY Variants Is Not Supported.".to_string()); // TODO: Implement
+            // TODO: Implement
+            return Err(VariantError::UnsupportedType {
+                variant: "PACKED_VECTOR2_ARRAY",
+                type_id: 35,
+            });
         }
         // PACKED_VECTOR3_ARRAY
         36 => {
-            return Err("Decoding PACKED_VECTOR3_ARRAY Variants Is Not Supported.".to_string()); // TODO: Implement
+            // TODO: Implement
+            return Err(VariantError::UnsupportedType {
+                variant: "PACKED_VECTOR3_ARRAY",
+                type_id: 36,
+            });
         }
         // PACKED_COLOR_ARRAY
         37 => {
-            return Err("Decoding PACKED_COLOR_ARRAY Variants Is Not Supported.".to_string()); // TODO: Implement
+            // TODO: Implement
+            return Err(VariantError::UnsupportedType {
+                variant: "PACKED_COLOR_ARRAY",
+                type_id: 37,
+            });
         }
         // PACKED_VECTOR4_ARRAY
         38 => {
-            return Err("Decoding PACKED_VECTOR4_ARRAY Variants Is Not Supported.".to_string()); // TODO: Implement
+            // TODO: Implement
+            return Err(VariantError::UnsupportedType {
+                variant: "PACKED_VECTOR4_ARRAY",
+                type_id: 38,
+            });
         }
         _ => {
-            return Err(
-                format! {"Decoding Variant Type {} Not Supported", header & HEADER_TYPE_MASK},
-            );
+            return Err(VariantError::UnsupportedType {
+                variant: "Unknown",
+                type_id: header & HEADER_TYPE_MASK,
+            });
         }
     }
 
@@ -1,15 +1,15 @@
-use super::{DecodingResult, Variant};
+use super::{DecodingResult, Variant, VariantError};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Nil;
 
 impl Variant for Nil {
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         Ok(0u32.to_le_bytes().to_vec())
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, _raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, _raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -19,7 +19,7 @@ impl Variant for Nil {
                 consumed: 4,
             })
         } else {
-            Err("Invalid Header for Nil Variant".to_string())
+            Err(VariantError::InvalidHeader("Nil Variant"))
         };
     }
 }
@@ -1,12 +1,12 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::vec::Vec;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PackedByteArray(pub Vec<u8>);
 
 impl Variant for PackedByteArray {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 29u32;
 
         let mut encoded = Vec::new();
@@ -20,12 +20,12 @@ impl Variant for PackedByteArray {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 4 {
-            return Err("Not Enough Bytes to Decode PackedByteArray Variant".to_string());
+            return Err(VariantError::Truncated("PackedByteArray Variant"));
         }
 
         let count = helpers::parse_u32(raw_bytes) as usize;
@@ -33,7 +33,7 @@ impl Variant for PackedByteArray {
         let mut consumed = 4;
 
         if raw_bytes.len() < count + consumed {
-            return Err("Not Enough Bytes to Decode PackedByteArray Variant".to_string());
+            return Err(VariantError::Truncated("PackedByteArray Variant"));
         }
 
         let data = raw_bytes[consumed..(consumed + count)].to_vec();
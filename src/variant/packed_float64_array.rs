@@ -1,12 +1,12 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::vec::Vec;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PackedFloat64Array(pub Vec<helpers::WrappedF64>);
 
 impl Variant for PackedFloat64Array {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 33u32;
 
         let mut encoded = Vec::new();
@@ -23,12 +23,12 @@ impl Variant for PackedFloat64Array {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 4 {
-            return Err("Not Enough Bytes to Decode PackedFloat64Array Variant".to_string());
+            return Err(VariantError::Truncated("PackedFloat64Array Variant"));
         }
 
         let count = helpers::parse_u32(raw_bytes) as usize;
@@ -36,7 +36,7 @@ impl Variant for PackedFloat64Array {
         let mut consumed = 4;
 
         if raw_bytes.len() < (8 * count) + consumed {
-            return Err("Not Enough Bytes to Decode PackedFloat64Array Variant".to_string());
+            return Err(VariantError::Truncated("PackedFloat64Array Variant"));
         }
 
         let mut data = vec![0f64.into(); count];
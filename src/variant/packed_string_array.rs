@@ -1,12 +1,12 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::vec::Vec;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PackedStringArray(pub Vec<String>);
 
 impl Variant for PackedStringArray {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 34u32;
 
         let mut encoded = Vec::new();
@@ -29,12 +29,12 @@ impl Variant for PackedStringArray {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 4 {
-            return Err("Not Enough Bytes to Decode PackedStringArray Variant".to_string());
+            return Err(VariantError::Truncated("PackedStringArray Variant"));
         }
 
         let count = helpers::parse_u32(raw_bytes) as usize;
@@ -48,18 +48,19 @@ impl Variant for PackedStringArray {
             consumed += 4;
 
             if raw_bytes.len() < consumed + str_len {
-                return Err(
-                    "Not Enough Bytes to Decode String in PackedStringArray Variant".to_string(),
-                );
+                return Err(VariantError::Truncated(
+                    "String in PackedStringArray Variant",
+                ));
             }
 
             let string_data =
                 match String::from_utf8(raw_bytes[consumed..(consumed + str_len)].to_vec()) {
                     Ok(s) => s,
-                    Err(_) => {
-                        return Err(
-                            "Invalid UTF-8 in String in PackedStringArray Variant".to_string()
-                        );
+                    Err(e) => {
+                        return Err(VariantError::InvalidUtf8 {
+                            variant: "String in PackedStringArray Variant",
+                            source: e,
+                        });
                     }
                 };
             consumed += str_len;
@@ -67,10 +68,9 @@ impl Variant for PackedStringArray {
             if (str_len % 4) != 0 {
                 let padding = 4 - (str_len % 4);
                 if raw_bytes.len() < consumed + padding {
-                    return Err(
-                        "Not Enough Bytes to Decode String Padding in PackedStringArray Variant"
-                            .to_string(),
-                    );
+                    return Err(VariantError::Truncated(
+                        "String Padding in PackedStringArray Variant",
+                    ));
                 }
                 consumed += padding;
             }
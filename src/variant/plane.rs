@@ -1,6 +1,6 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Plane {
     pub x: helpers::WrappedF64,
     pub y: helpers::WrappedF64,
@@ -10,7 +10,7 @@ pub struct Plane {
 
 impl Variant for Plane {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 14u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -40,7 +40,7 @@ impl Variant for Plane {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -49,7 +49,7 @@ impl Variant for Plane {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < 4 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Plane Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Plane Variant"));
             }
 
             for i in 0..4 {
@@ -58,7 +58,7 @@ impl Variant for Plane {
             }
         } else {
             if raw_bytes.len() < 4 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Plane Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Plane Variant"));
             }
 
             for i in 0..4 {
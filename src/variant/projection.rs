@@ -1,11 +1,11 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Projection(pub [[helpers::WrappedF64; 4]; 4]);
 
 impl Variant for Projection {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 19u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -39,7 +39,7 @@ impl Variant for Projection {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -48,7 +48,7 @@ impl Variant for Projection {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < consumed + 16 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Projection Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Projection Variant"));
             }
 
             for i in 0..4 {
@@ -60,7 +60,7 @@ impl Variant for Projection {
             }
         } else {
             if raw_bytes.len() < consumed + 16 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Projection Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Projection Variant"));
             }
 
             for i in 0..4 {
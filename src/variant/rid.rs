@@ -1,11 +1,11 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Rid(pub u64);
 
 impl Variant for Rid {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 23u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -16,12 +16,12 @@ impl Variant for Rid {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 8 {
-            return Err("Not Enough Bytes to Decode RID Variant".to_string());
+            return Err(VariantError::Truncated("RID Variant"));
         }
 
         return Ok(DecodingResult {
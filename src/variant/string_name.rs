@@ -1,11 +1,11 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct StringName(pub String);
 
 impl Variant for StringName {
     // Replicated from _encode_string in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 21u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -25,32 +25,37 @@ impl Variant for StringName {
     }
 
     // Replicated from _decode_string in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 4 {
-            return Err("Not Enough Bytes to Decode String Name Length".to_string());
+            return Err(VariantError::Truncated("String Name Length"));
         }
 
         let str_len = helpers::parse_u32(raw_bytes) as usize;
         let mut consumed = 4;
 
         if raw_bytes.len() < consumed + str_len {
-            return Err("Not Enough Bytes to Decode String Name Data".to_string());
+            return Err(VariantError::Truncated("String Name Data"));
         }
 
         let string_data =
             match String::from_utf8(raw_bytes[consumed..(consumed + str_len)].to_vec()) {
                 Ok(s) => s,
-                Err(_) => return Err("Invalid UTF-8 in String Name Variant".to_string()),
+                Err(e) => {
+                    return Err(VariantError::InvalidUtf8 {
+                        variant: "String Name Variant",
+                        source: e,
+                    });
+                }
             };
         consumed += str_len;
 
         if (str_len % 4) != 0 {
             let padding = 4 - (str_len % 4);
             if raw_bytes.len() < consumed + padding {
-                return Err("Not Enough Bytes to Decode String Name Padding".to_string());
+                return Err(VariantError::Truncated("String Name Padding"));
             }
             consumed += padding;
         }
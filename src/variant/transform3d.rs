@@ -1,6 +1,6 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Transform3D {
     pub basis: [[helpers::WrappedF64; 3]; 3],
     pub origin: [helpers::WrappedF64; 3],
@@ -8,7 +8,7 @@ pub struct Transform3D {
 
 impl Variant for Transform3D {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 18u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -58,7 +58,7 @@ impl Variant for Transform3D {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -68,7 +68,7 @@ impl Variant for Transform3D {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < consumed + 12 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Transform3D Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Transform3D Variant"));
             }
 
             for i in 0..3 {
@@ -84,7 +84,7 @@ impl Variant for Transform3D {
             }
         } else {
             if raw_bytes.len() < consumed + 12 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Transform3D Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Transform3D Variant"));
             }
 
             for i in 0..3 {
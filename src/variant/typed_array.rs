@@ -1,17 +1,17 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::{any::TypeId, hash::Hash, ops::Deref, sync::Arc};
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TypedArray<T>(Vec<Arc<T>>)
 where
-    T: Variant + Hash + Eq + Clone;
+    T: Variant + Hash + Ord + Clone;
 
 impl<T> Variant for TypedArray<T>
 where
-    T: Variant + Hash + Eq + Clone,
+    T: Variant + Hash + Ord + Clone,
 {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 28u32;
 
         header |= 1 << super::HEADER_DATA_FIELD_TYPED_ARRAY_SHIFT;
@@ -80,7 +80,9 @@ where
                 value_type = 20;
             }
             _ => {
-                return Err("Cannot Encode Typed Array with Unknown Value Type".to_string());
+                return Err(VariantError::Unencodable(
+                    "Typed Array with Unknown Value Type",
+                ));
             }
         }
 
@@ -100,7 +102,7 @@ where
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -116,12 +118,13 @@ where
             let decoding_result = match crate::variant::decode_variant(&raw_bytes[offset..]) {
                 Ok(result) => result,
                 Err(e) => {
-                    return Err(format!(
-                        "Failed to Decode Key {} of {} in Dictionary Variant: \n{}",
-                        i + 1,
+                    return Err(VariantError::Element {
+                        container: "Typed Array Variant",
+                        element: "Value",
+                        index: i + 1,
                         count,
-                        e
-                    ));
+                        source: Box::new(e),
+                    });
                 }
             };
 
@@ -131,11 +134,12 @@ where
                 .downcast_ref::<T>()
                 .is_none()
             {
-                return Err(format!(
-                    "Failed to Downcast Value {} of {} in Dictionary Array to Expected Type",
-                    i + 1,
-                    count
-                ));
+                return Err(VariantError::UnexpectedElementType {
+                    container: "Typed Array Variant",
+                    element: "Value",
+                    index: i + 1,
+                    count,
+                });
             }
 
             offset += decoding_result.consumed;
@@ -159,7 +163,7 @@ where
 
 impl<T> From<Vec<Arc<T>>> for TypedArray<T>
 where
-    T: Variant + Hash + Eq + Clone,
+    T: Variant + Hash + Ord + Clone,
 {
     fn from(value: Vec<Arc<T>>) -> Self {
         Self(value)
@@ -168,7 +172,7 @@ where
 
 impl<T> Deref for TypedArray<T>
 where
-    T: Variant + Hash + Eq + Clone,
+    T: Variant + Hash + Ord + Clone,
 {
     type Target = Vec<Arc<T>>;
 
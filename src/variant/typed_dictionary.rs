@@ -1,20 +1,20 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use dashmap::DashMap;
 use std::{any::TypeId, hash::Hash, ops::Deref, sync::Arc};
 
 #[derive(Clone, Debug)]
 pub struct TypedDictionary<K, V>(Arc<DashMap<K, V>>)
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone;
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone;
 
 impl<K, V> Variant for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 27u32;
 
         header |= 1 << super::HEADER_DATA_FIELD_TYPED_DICTIONARY_KEY_SHIFT;
@@ -32,7 +32,9 @@ where
         } else if TypeId::of::<K>() == TypeId::of::<super::VariantString>() {
             key_type = 4;
         } else {
-            return Err("Cannot Encode Typed Dictionary with Unknown Key Type".to_string());
+            return Err(VariantError::Unencodable(
+                "Typed Dictionary with Unknown Key Type",
+            ));
         }
 
         if TypeId::of::<V>() == TypeId::of::<super::Bool>() {
@@ -44,7 +46,9 @@ where
         } else if TypeId::of::<V>() == TypeId::of::<super::VariantString>() {
             value_type = 4;
         } else {
-            return Err("Cannot Encode Typed Dictionary with Unknown Value Type".to_string());
+            return Err(VariantError::Unencodable(
+                "Typed Dictionary with Unknown Value Type",
+            ));
         }
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -67,7 +71,7 @@ where
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -83,12 +87,13 @@ where
             let key_decoding_result = match crate::variant::decode_variant(&raw_bytes[offset..]) {
                 Ok(result) => result,
                 Err(e) => {
-                    return Err(format!(
-                        "Failed to Decode Key {} of {} in Dictionary Variant: \n{}",
-                        i + 1,
+                    return Err(VariantError::Element {
+                        container: "Typed Dictionary Variant",
+                        element: "Key",
+                        index: i + 1,
                         count,
-                        e
-                    ));
+                        source: Box::new(e),
+                    });
                 }
             };
 
@@ -98,11 +103,12 @@ where
                 .downcast_ref::<K>()
                 .is_none()
             {
-                return Err(format!(
-                    "Failed to Downcast Key {} of {} in Dictionary Variant to Expected Type",
-                    i + 1,
-                    count
-                ));
+                return Err(VariantError::UnexpectedElementType {
+                    container: "Typed Dictionary Variant",
+                    element: "Key",
+                    index: i + 1,
+                    count,
+                });
             }
 
             offset += key_decoding_result.consumed;
@@ -110,12 +116,13 @@ where
             let value_decoding_result = match crate::variant::decode_variant(&raw_bytes[offset..]) {
                 Ok(result) => result,
                 Err(e) => {
-                    return Err(format!(
-                        "Failed to Decode Value {} of {} in Dictionary Variant: \n{}",
-                        i + 1,
+                    return Err(VariantError::Element {
+                        container: "Typed Dictionary Variant",
+                        element: "Value",
+                        index: i + 1,
                         count,
-                        e
-                    ));
+                        source: Box::new(e),
+                    });
                 }
             };
 
@@ -125,11 +132,12 @@ where
                 .downcast_ref::<V>()
                 .is_none()
             {
-                return Err(format!(
-                    "Failed to Downcast Value {} of {} in Dictionary Variant to Expected Type",
-                    i + 1,
-                    count
-                ));
+                return Err(VariantError::UnexpectedElementType {
+                    container: "Typed Dictionary Variant",
+                    element: "Value",
+                    index: i + 1,
+                    count,
+                });
             }
 
             offset += value_decoding_result.consumed;
@@ -159,8 +167,8 @@ where
 
 impl<K, V> PartialEq for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
     fn eq(&self, other: &Self) -> bool {
         if self.len() != other.len() {
@@ -169,30 +177,26 @@ where
 
         for r in self.iter() {
             match other.get(r.key()) {
-                Some(v) => {
-                    return r.value().eq(&*v.deref());
-                }
-                None => {
-                    return false;
-                }
+                Some(v) if r.value().eq(&*v.deref()) => {}
+                _ => return false,
             }
         }
 
-        return true;
+        true
     }
 }
 
 impl<K, V> Eq for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
 }
 
 impl<K, V> std::hash::Hash for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         for r in self.iter() {
@@ -202,10 +206,44 @@ where
     }
 }
 
+// Orders entries by key, since the maps are unordered
+impl<K, V> PartialOrd for TypedDictionary<K, V>
+where
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K, V> Ord for TypedDictionary<K, V>
+where
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
+{
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if Arc::ptr_eq(&self.0, &other.0) {
+            return std::cmp::Ordering::Equal;
+        }
+
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.key().cmp(b.key()));
+
+        let mut other_entries: Vec<_> = other.iter().collect();
+        other_entries.sort_by(|a, b| a.key().cmp(b.key()));
+
+        entries
+            .iter()
+            .map(|r| (r.key(), r.value()))
+            .cmp(other_entries.iter().map(|r| (r.key(), r.value())))
+    }
+}
+
 impl<K, V> From<DashMap<K, V>> for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
     fn from(value: DashMap<K, V>) -> Self {
         Self(Arc::new(value))
@@ -214,8 +252,8 @@ where
 
 impl<K, V> From<Arc<DashMap<K, V>>> for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
     fn from(value: Arc<DashMap<K, V>>) -> Self {
         Self(value)
@@ -224,8 +262,8 @@ where
 
 impl<K, V> std::ops::Deref for TypedDictionary<K, V>
 where
-    K: Variant + Eq + Hash + Clone,
-    V: Variant + Eq + Hash + Clone,
+    K: Variant + Ord + Hash + Clone,
+    V: Variant + Ord + Hash + Clone,
 {
     type Target = Arc<DashMap<K, V>>;
 
@@ -233,3 +271,30 @@ where
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::Int;
+
+    fn dictionary(entries: &[(i64, i64)]) -> TypedDictionary<Int, Int> {
+        TypedDictionary(Arc::new(
+            entries.iter().map(|(k, v)| (Int(*k), Int(*v))).collect(),
+        ))
+    }
+
+    #[test]
+    fn compares_every_entry() {
+        let entries = [(1, 10), (2, 20), (3, 30)];
+
+        assert_eq!(dictionary(&entries), dictionary(&entries));
+
+        // Whichever entry is iterated first, a differing value is found
+        for i in 0..entries.len() {
+            let mut changed = entries;
+            changed[i].1 += 1;
+
+            assert_ne!(dictionary(&entries), dictionary(&changed));
+        }
+    }
+}
@@ -1,12 +1,12 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use std::{ops::Deref, sync::Arc};
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct VariableArray(Vec<Arc<Box<dyn Variant>>>);
 
 impl Variant for VariableArray {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 28u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -23,7 +23,7 @@ impl Variant for VariableArray {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -39,12 +39,13 @@ impl Variant for VariableArray {
             let decoding_result = match crate::variant::decode_variant(&raw_bytes[offset..]) {
                 Ok(result) => result,
                 Err(e) => {
-                    return Err(format!(
-                        "Failed to Decode Key {} of {} in Dictionary Variant: \n{}",
-                        i + 1,
+                    return Err(VariantError::Element {
+                        container: "Array Variant",
+                        element: "Value",
+                        index: i + 1,
                         count,
-                        e
-                    ));
+                        source: Box::new(e),
+                    });
                 }
             };
 
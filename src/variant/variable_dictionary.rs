@@ -1,4 +1,4 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 use dashmap::DashMap;
 use std::{ops::Deref, sync::Arc};
 
@@ -7,7 +7,7 @@ pub struct VariableDictionary(Arc<DashMap<Box<dyn Variant>, Box<dyn Variant>>>);
 
 impl Variant for VariableDictionary {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 27u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -27,7 +27,7 @@ impl Variant for VariableDictionary {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -43,12 +43,13 @@ impl Variant for VariableDictionary {
             let key_decoding_result = match crate::variant::decode_variant(&raw_bytes[offset..]) {
                 Ok(result) => result,
                 Err(e) => {
-                    return Err(format!(
-                        "Failed to Decode Key {} of {} in Dictionary Variant: \n{}",
-                        i + 1,
+                    return Err(VariantError::Element {
+                        container: "Dictionary Variant",
+                        element: "Key",
+                        index: i + 1,
                         count,
-                        e
-                    ));
+                        source: Box::new(e),
+                    });
                 }
             };
 
@@ -57,12 +58,13 @@ impl Variant for VariableDictionary {
             let value_decoding_result = match crate::variant::decode_variant(&raw_bytes[offset..]) {
                 Ok(result) => result,
                 Err(e) => {
-                    return Err(format!(
-                        "Failed to Decode Value {} of {} in Dictionary Variant: \n{}",
-                        i + 1,
+                    return Err(VariantError::Element {
+                        container: "Dictionary Variant",
+                        element: "Value",
+                        index: i + 1,
                         count,
-                        e
-                    ));
+                        source: Box::new(e),
+                    });
                 }
             };
 
@@ -86,16 +88,12 @@ impl PartialEq for VariableDictionary {
 
         for r in self.iter() {
             match other.get(r.key()) {
-                Some(v) => {
-                    return r.value().deref().eq(&**v.deref()); // woof, that is ugly
-                }
-                None => {
-                    return false;
-                }
+                Some(v) if r.value().deref().eq(&**v.deref()) => {}
+                _ => return false,
             }
         }
 
-        return true;
+        true
     }
 }
 
@@ -110,6 +108,32 @@ impl std::hash::Hash for VariableDictionary {
     }
 }
 
+// Orders entries by key, since the maps are unordered
+impl PartialOrd for VariableDictionary {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VariableDictionary {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if Arc::ptr_eq(&self.0, &other.0) {
+            return std::cmp::Ordering::Equal;
+        }
+
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.key().cmp(b.key()));
+
+        let mut other_entries: Vec<_> = other.iter().collect();
+        other_entries.sort_by(|a, b| a.key().cmp(b.key()));
+
+        entries
+            .iter()
+            .map(|r| (r.key(), r.value()))
+            .cmp(other_entries.iter().map(|r| (r.key(), r.value())))
+    }
+}
+
 impl From<DashMap<Box<dyn Variant>, Box<dyn Variant>>> for VariableDictionary {
     fn from(value: DashMap<Box<dyn Variant>, Box<dyn Variant>>) -> Self {
         Self(Arc::new(value))
@@ -1,9 +1,12 @@
 use crate::variant::TypedArray;
 
-use super::{DecodingResult, TypedDictionary, Variant, helpers};
+use super::{DecodingResult, TypedDictionary, Variant, VariantError, helpers};
 
 // Replicated from decode_variant in marshalls.cpp and others
-pub fn decode_array(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String> {
+pub fn decode_array(
+    header: u32,
+    raw_bytes: &[u8],
+) -> Result<DecodingResult<dyn Variant>, VariantError> {
     let type_kind: u8 = (((header) & super::HEADER_DATA_FIELD_TYPED_ARRAY_MASK)
         >> super::HEADER_DATA_FIELD_TYPED_ARRAY_SHIFT) as u8;
     let mut value_type = 0;
@@ -12,13 +15,17 @@ pub fn decode_array(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn
 
     if type_kind == 1 {
         if raw_bytes.len() + offset < 4 {
-            return Err("Not Enough Bytes to Decode Built In Typed Array Variant".to_string());
+            return Err(VariantError::Truncated("Built In Typed Array Variant"));
         }
 
         value_type = helpers::parse_u32(&raw_bytes[offset..]);
         offset += 4;
     } else if type_kind != 0 {
-        return Err("Decoding Non-Built In Typed Array Variants not supported.".to_string());
+        return Err(VariantError::UnsupportedElementType {
+            container: "Typed Array",
+            element: "Value",
+            type_id: None,
+        });
     }
 
     if value_type > 0 && value_type <= 20 {
@@ -44,10 +51,11 @@ pub fn decode_array(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn
             19 => TypedArray::<super::Projection>::decode(header, &raw_bytes[offset..])?,
             20 => TypedArray::<super::Color>::decode(header, &raw_bytes[offset..])?,
             _ => {
-                return Err(
-                    format! {"Decoding Typed Array Variants with Type {:?} not Fully Supported.", value_type
-                    },
-                );
+                return Err(VariantError::UnsupportedElementType {
+                    container: "Typed Array",
+                    element: "Value",
+                    type_id: Some(value_type),
+                });
             }
         });
     } else if value_type != 0 {
@@ -64,7 +72,7 @@ pub fn decode_array(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn
 pub fn decode_dictionary(
     header: u32,
     raw_bytes: &[u8],
-) -> Result<DecodingResult<dyn Variant>, String> {
+) -> Result<DecodingResult<dyn Variant>, VariantError> {
     let key_type_kind: u8 = (((header) & super::HEADER_DATA_FIELD_TYPED_DICTIONARY_KEY_MASK)
         >> super::HEADER_DATA_FIELD_TYPED_DICTIONARY_KEY_SHIFT) as u8;
     let mut key_type = 0;
@@ -77,24 +85,32 @@ pub fn decode_dictionary(
 
     if key_type_kind == 1 {
         if raw_bytes.len() + offset < 4 {
-            return Err("Not Enough Bytes to Decode Built In Typed Dictionary Variant".to_string());
+            return Err(VariantError::Truncated("Built In Typed Dictionary Variant"));
         }
 
         key_type = helpers::parse_u32(&raw_bytes[offset..]);
         offset += 4;
     } else if key_type_kind != 0 {
-        return Err("Decoding Non-Built In Typed Dictionary Variants not supported.".to_string());
+        return Err(VariantError::UnsupportedElementType {
+            container: "Typed Dictionary",
+            element: "Key",
+            type_id: None,
+        });
     }
 
     if value_type_kind == 1 {
         if raw_bytes.len() + offset < 4 {
-            return Err("Not Enough Bytes to Decode Built In Typed Dictionary Variant".to_string());
+            return Err(VariantError::Truncated("Built In Typed Dictionary Variant"));
         }
 
         value_type = helpers::parse_u32(&raw_bytes[offset..]);
         offset += 4;
     } else if value_type_kind != 0 {
-        return Err("Decoding Non-Built In Typed Dictionary Variants not supported.".to_string());
+        return Err(VariantError::UnsupportedElementType {
+            container: "Typed Dictionary",
+            element: "Value",
+            type_id: None,
+        });
     }
 
     if key_type > 0 && key_type <= 4 && value_type > 0 && value_type <= 4 {
@@ -117,9 +133,11 @@ pub fn decode_dictionary(
                     &raw_bytes[offset..],
                 )?,
                 _ => {
-                    return Err(
-                        format! {"Decoding Typed Dictionary Variants with Keys Type {:?} not Fully Supported.", key_type},
-                    );
+                    return Err(VariantError::UnsupportedElementType {
+                        container: "Typed Dictionary",
+                        element: "Key",
+                        type_id: Some(key_type),
+                    });
                 }
             },
             2 => match value_type {
@@ -139,10 +157,11 @@ pub fn decode_dictionary(
                     &raw_bytes[offset..],
                 )?,
                 _ => {
-                    return Err(
-                        format! {"Decoding Typed Dictionary Variants with Keys Type {:?} not Fully Supported.", key_type
-                        },
-                    );
+                    return Err(VariantError::UnsupportedElementType {
+                        container: "Typed Dictionary",
+                        element: "Key",
+                        type_id: Some(key_type),
+                    });
                 }
             },
             3 => match value_type {
@@ -163,10 +182,11 @@ pub fn decode_dictionary(
                     &raw_bytes[offset..],
                 )?,
                 _ => {
-                    return Err(
-                        format! {"Decoding Typed Dictionary Variants with Keys Type {:?} not Fully Supported.", key_type
-                        },
-                    );
+                    return Err(VariantError::UnsupportedElementType {
+                        container: "Typed Dictionary",
+                        element: "Key",
+                        type_id: Some(key_type),
+                    });
                 }
             },
             4 => match value_type {
@@ -187,17 +207,19 @@ pub fn decode_dictionary(
                     &raw_bytes[offset..],
                 )?,
                 _ => {
-                    return Err(
-                        format! {"Decoding Typed Dictionary Variants with Keys Type {:?} not Fully Supported.", key_type
-                        },
-                    );
+                    return Err(VariantError::UnsupportedElementType {
+                        container: "Typed Dictionary",
+                        element: "Key",
+                        type_id: Some(key_type),
+                    });
                 }
             },
             _ => {
-                return Err(
-                    format! {"Decoding Typed Dictionary Variants with Values Type {:?} not Fully Supported.", value_type
-                    },
-                );
+                return Err(VariantError::UnsupportedElementType {
+                    container: "Typed Dictionary",
+                    element: "Value",
+                    type_id: Some(value_type),
+                });
             }
         });
     } else {
@@ -1,13 +1,20 @@
 use std::any::Any;
 use std::fmt::Debug;
 
-// TODO: Custom Variant Decode Error?
-
-pub trait Variant: Any + Send + Sync + Debug + crate::DynEq + crate::DynHash {
-    fn encode(&self) -> Result<Vec<u8>, String>;
+/// A value encoded like Godot's `Variant`
+///
+/// Implementors must be [`Ord`], so [`DynOrd`](crate::DynOrd) is implemented for them,
+/// so packets and dictionaries holding variants can be ordered.
+pub trait Variant:
+    Any + Send + Sync + Debug + crate::DynEq + crate::DynHash + crate::DynOrd
+{
+    fn encode(&self) -> Result<Vec<u8>, super::VariantError>;
 
     /// Raw Bytes does not include the header
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<super::DecodingResult<dyn Variant>, String>
+    fn decode(
+        header: u32,
+        raw_bytes: &[u8],
+    ) -> Result<super::DecodingResult<dyn Variant>, super::VariantError>
     where
         Self: Sized;
 }
@@ -35,3 +42,15 @@ impl PartialEq for dyn Variant {
 }
 
 impl Eq for dyn Variant {}
+
+impl PartialOrd for dyn Variant {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for dyn Variant {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.dyn_cmp(other)
+    }
+}
@@ -1,6 +1,6 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector2 {
     pub x: helpers::WrappedF64,
     pub y: helpers::WrappedF64,
@@ -8,7 +8,7 @@ pub struct Vector2 {
 
 impl Variant for Vector2 {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 5u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -30,7 +30,7 @@ impl Variant for Vector2 {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -39,7 +39,7 @@ impl Variant for Vector2 {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < 2 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Vector2 Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Vector2 Variant"));
             }
 
             for i in 0..2 {
@@ -48,7 +48,7 @@ impl Variant for Vector2 {
             }
         } else {
             if raw_bytes.len() < 2 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Vector2 Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Vector2 Variant"));
             }
 
             for i in 0..2 {
@@ -1,6 +1,6 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector2I {
     pub x: i32,
     pub y: i32,
@@ -8,7 +8,7 @@ pub struct Vector2I {
 
 impl Variant for Vector2I {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 6u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -20,12 +20,12 @@ impl Variant for Vector2I {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 2 * 4 {
-            return Err("Not Enough Bytes to Decode Vector2I Variant".to_string());
+            return Err(VariantError::Truncated("Vector2I Variant"));
         }
 
         let mut vec2i = [0; 2];
@@ -1,6 +1,6 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector3 {
     pub x: helpers::WrappedF64,
     pub y: helpers::WrappedF64,
@@ -9,7 +9,7 @@ pub struct Vector3 {
 
 impl Variant for Vector3 {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let mut header = 9u32;
 
         // Replaces Compile Time Behavior of Godot
@@ -36,7 +36,7 @@ impl Variant for Vector3 {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
@@ -45,7 +45,7 @@ impl Variant for Vector3 {
 
         if (header & super::HEADER_DATA_FLAG_64) != 0 {
             if raw_bytes.len() < 3 * 8 {
-                return Err("Not Enough Bytes to Decode 64-bit Vector3 Variant".to_string());
+                return Err(VariantError::Truncated("64-bit Vector3 Variant"));
             }
 
             for i in 0..3 {
@@ -54,7 +54,7 @@ impl Variant for Vector3 {
             }
         } else {
             if raw_bytes.len() < 3 * 4 {
-                return Err("Not Enough Bytes to Decode 32-bit Vector3 Variant".to_string());
+                return Err(VariantError::Truncated("32-bit Vector3 Variant"));
             }
 
             for i in 0..3 {
@@ -1,6 +1,6 @@
-use super::{DecodingResult, Variant, helpers};
+use super::{DecodingResult, Variant, VariantError, helpers};
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Vector3I {
     pub x: i32,
     pub y: i32,
@@ -9,7 +9,7 @@ pub struct Vector3I {
 
 impl Variant for Vector3I {
     // Replicated from encode_variant in marshalls.cpp
-    fn encode(&self) -> Result<Vec<u8>, String> {
+    fn encode(&self) -> Result<Vec<u8>, VariantError> {
         let header = 10u32;
 
         let mut encoded = header.to_le_bytes().to_vec();
@@ -22,12 +22,12 @@ impl Variant for Vector3I {
     }
 
     // Replicated from decode_variant in marshalls.cpp
-    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, String>
+    fn decode(_header: u32, raw_bytes: &[u8]) -> Result<DecodingResult<dyn Variant>, VariantError>
     where
         Self: Sized,
     {
         if raw_bytes.len() < 3 * 4 {
-            return Err("Not Enough Bytes to Decode Vector3I Variant".to_string());
+            return Err(VariantError::Truncated("Vector3I Variant"));
         }
 
         let mut vec3i = [0; 3];